@@ -1,13 +1,26 @@
+use crate::models::{DatabaseEngine, ResourceLimits};
 use anyhow::{Result, anyhow};
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
-    pub mysql: MySQLConfig,
+    pub mysql: Option<MySQLConfig>,
+    pub postgres: Option<PostgreSQLConfig>,
+    pub naming: NamingConfig,
+    pub privileges: PrivilegeConfig,
+    /// 新建用户的默认资源限制
+    pub resource_limits: ResourceLimits,
+    pub storage: StorageConfig,
+    pub lifecycle: LifecycleConfig,
+    pub quota: DatabaseQuotaConfig,
+    pub application: ApplicationConfig,
+    pub import: ImportConfig,
+    pub reset: ResetConfig,
     pub admin: AdminConfig,
 }
 
@@ -20,6 +33,8 @@ pub struct ServerConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseConfig {
     pub sqlite_path: String,
+    /// 未在白名单中单独指定时使用的数据库引擎
+    pub default_engine: DatabaseEngine,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +47,277 @@ pub struct MySQLConfig {
     pub allowed_host: Option<String>,
 }
 
+/// PostgreSQL 供应后端配置（可选，设置 POSTGRES_HOST 后启用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostgreSQLConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    /// 用于管理连接的维护数据库
+    pub database: String,
+}
+
+/// 数据库名和用户名的命名规则
+///
+/// 模板支持以下占位符：
+/// - `{prefix}`：数据库名使用 `database_prefix`，用户名使用 `username_prefix`
+/// - `{term}`：学期或批次标识（可选）
+/// - `{identity}`：身份标识（必须出现且只能出现一次）
+///
+/// 多个学校或学期共用同一台数据库服务器时，可以通过不同的前缀或学期避免名称冲突。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamingConfig {
+    pub template: String,
+    pub database_prefix: String,
+    pub username_prefix: String,
+    pub term: Option<String>,
+}
+
+impl Default for NamingConfig {
+    fn default() -> Self {
+        Self {
+            template: "{prefix}_{identity}".to_string(),
+            database_prefix: "db".to_string(),
+            username_prefix: "user".to_string(),
+            term: None,
+        }
+    }
+}
+
+/// 权限配置档
+///
+/// 每个配置档是一组授予用户在自己数据库上的权限。内置配置档：
+/// - `readonly`：只读
+/// - `dml`：数据操作（默认，与早期版本的固定权限一致）
+/// - `ddl-sandbox`：数据操作 + 建表、改表、删表、视图和触发器
+/// - `full-own-schema`：自己数据库上的全部数据库级权限
+///
+/// 可以通过 `PRIVILEGE_PROFILES` 覆盖内置配置档或添加新的配置档。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivilegeConfig {
+    /// 配置档名称 -> 权限列表
+    pub profiles: BTreeMap<String, Vec<String>>,
+    /// 白名单记录和班级都未指定时使用的配置档
+    pub default_profile: String,
+    /// 后端支持角色时，通过角色授予配置档的权限（不支持时自动回退为直接授权）
+    pub use_roles: bool,
+}
+
+impl PrivilegeConfig {
+    /// 内置的权限配置档
+    pub fn builtin_profiles() -> BTreeMap<String, Vec<String>> {
+        let to_vec = |privileges: &[&str]| privileges.iter().map(|p| p.to_string()).collect();
+
+        let dml = crate::database::provisioner::STUDENT_PRIVILEGES;
+        let ddl_sandbox = [
+            dml,
+            &[
+                "CREATE",
+                "DROP",
+                "ALTER",
+                "REFERENCES",
+                "CREATE TEMPORARY TABLES",
+                "CREATE VIEW",
+                "SHOW VIEW",
+                "TRIGGER",
+            ],
+        ]
+        .concat();
+
+        let mut profiles = BTreeMap::new();
+        profiles.insert("readonly".to_string(), to_vec(&["SELECT"]));
+        profiles.insert("dml".to_string(), to_vec(dml));
+        profiles.insert("ddl-sandbox".to_string(), to_vec(&ddl_sandbox));
+        profiles.insert(
+            "full-own-schema".to_string(),
+            to_vec(crate::database::provisioner::DATABASE_PRIVILEGES),
+        );
+        profiles
+    }
+
+    /// 解析 `PRIVILEGE_PROFILES`，格式：`名称=权限,权限;名称=权限`
+    pub fn parse_profiles(value: &str) -> Result<BTreeMap<String, Vec<String>>> {
+        let mut profiles = BTreeMap::new();
+        for entry in value.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, privileges) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("权限配置档格式错误: {}", entry))?;
+            let privileges = privileges
+                .split(',')
+                .map(|p| p.trim().to_ascii_uppercase())
+                .filter(|p| !p.is_empty())
+                .collect();
+            profiles.insert(name.trim().to_string(), privileges);
+        }
+        Ok(profiles)
+    }
+
+    /// 获取配置档的权限列表
+    pub fn privileges_of(&self, profile: &str) -> Option<Vec<&str>> {
+        self.profiles
+            .get(profile)
+            .map(|privileges| privileges.iter().map(String::as_str).collect())
+    }
+
+    /// 校验配置档名称和权限
+    pub fn validate(&self) -> Result<()> {
+        for (name, privileges) in &self.profiles {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(anyhow!("权限配置档名称无效: '{}'", name));
+            }
+
+            if privileges.is_empty() {
+                return Err(anyhow!("权限配置档 {} 至少需要一个权限", name));
+            }
+
+            for privilege in privileges {
+                if !crate::database::provisioner::DATABASE_PRIVILEGES.contains(&privilege.as_str())
+                {
+                    return Err(anyhow!(
+                        "权限配置档 {} 包含不允许的权限: {}",
+                        name,
+                        privilege
+                    ));
+                }
+            }
+        }
+
+        if !self.profiles.contains_key(&self.default_profile) {
+            return Err(anyhow!("默认权限配置档 {} 不存在", self.default_profile));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for PrivilegeConfig {
+    fn default() -> Self {
+        Self {
+            profiles: Self::builtin_profiles(),
+            default_profile: "dml".to_string(),
+            use_roles: true,
+        }
+    }
+}
+
+/// 存储配额
+///
+/// 定期统计每个用户所有数据库的大小。合计超过配额时撤销用户的 INSERT/UPDATE 权限（保留 DELETE 以便清理数据），
+/// 用量回落到配额以内后恢复。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageConfig {
+    /// 每个用户的配额（MB），所有数据库合计，0 表示只统计不限制
+    pub quota_mb: u32,
+    /// 统计间隔（秒），0 表示不启动定期统计
+    pub check_interval_secs: u32,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            quota_mb: 0,
+            check_interval_secs: 600,
+        }
+    }
+}
+
+/// 数据库有效期
+///
+/// 到期后锁定用户账号，宽限期结束后归档并删除数据库。班级可以单独设置有效期，
+/// 管理员也可以为单个用户延期。管理员删除的数据库先移入隔离库，保留期结束后才清除。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleConfig {
+    /// 新建数据库的默认有效期（天），0 表示永不过期
+    pub default_lifetime_days: u32,
+    /// 到期锁定后保留数据库的宽限期（天）
+    pub grace_period_days: u32,
+    /// 管理员删除后保留数据库的天数，期间可以恢复，0 表示立即删除
+    pub deletion_retention_days: u32,
+    /// 检查间隔（秒），0 表示不启动定期检查
+    pub check_interval_secs: u32,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            default_lifetime_days: 0,
+            grace_period_days: 30,
+            deletion_retention_days: 7,
+            check_interval_secs: 3600,
+        }
+    }
+}
+
+/// 数据库数量配额
+///
+/// 每个身份标识除申请时创建的数据库外，还可以创建 `db_学号_后缀` 形式的命名数据库，
+/// 由同一个数据库用户使用。白名单中可以为单个用户指定配额。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatabaseQuotaConfig {
+    /// 每个身份标识最多拥有的数据库数（含申请时创建的数据库），1 表示不允许创建命名数据库
+    pub max_databases_per_identity: u32,
+}
+
+impl Default for DatabaseQuotaConfig {
+    fn default() -> Self {
+        Self {
+            max_databases_per_identity: 1,
+        }
+    }
+}
+
+/// 申请窗口与容量
+///
+/// 申请窗口外的申请被拒绝，学生所在教学班设置了开课、结课时间时以教学班为准。
+/// 容量按未删除、未归档的数据库计算，教学班可以单独限制。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApplicationConfig {
+    /// 开放申请的时间（服务器本地时间，`YYYY-MM-DD` 或 `YYYY-MM-DD HH:MM:SS`），未设置表示立即开放
+    pub opens_at: Option<String>,
+    /// 停止申请的时间，格式同上，未设置表示不停止
+    pub closes_at: Option<String>,
+    /// 每个数据库服务器最多容纳的数据库数，0 表示不限制
+    pub max_databases_per_server: u32,
+}
+
+/// SQL 导入
+///
+/// 学生上传的 SQL 文件以学生自己的用户执行，权限配置档照常生效。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportConfig {
+    /// 上传文件的大小上限（MB，gzip 压缩时按压缩后计算）
+    pub max_upload_mb: u32,
+    /// 解压后 SQL 的大小上限（MB）
+    pub max_sql_mb: u32,
+    /// 失败语句数达到此值时停止执行
+    pub max_errors: u32,
+}
+
+impl Default for ImportConfig {
+    fn default() -> Self {
+        Self {
+            max_upload_mb: 10,
+            max_sql_mb: 50,
+            max_errors: 100,
+        }
+    }
+}
+
+/// 数据库重置
+///
+/// 重置会删除数据库中的所有表并恢复基线，账号和密码保持不变。管理员始终可以重置，
+/// 学生自助重置需要单独开启。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResetConfig {
+    /// 是否允许学生用自己的数据库密码重置数据库
+    pub allow_students: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdminConfig {
     pub password: String,
@@ -72,7 +358,209 @@ impl AppConfig {
             "./dormdb_state.db".to_string()
         });
 
-        // MySQL 配置
+        let default_engine = match env::var("DEFAULT_DB_ENGINE") {
+            Ok(engine) => engine
+                .parse::<DatabaseEngine>()
+                .map_err(|e| anyhow!("DEFAULT_DB_ENGINE 无效: {}", e))?,
+            Err(_) => {
+                info!("使用默认数据库引擎: mysql");
+                DatabaseEngine::MySql
+            }
+        };
+
+        // MySQL 配置（默认引擎为 PostgreSQL 时可选，设置 MYSQL_PASSWORD 后启用）
+        let mysql = match env::var("MYSQL_PASSWORD") {
+            Ok(mysql_password) => Some(Self::mysql_from_env(mysql_password)?),
+            Err(_) if default_engine == DatabaseEngine::Postgres => {
+                info!("未设置 MYSQL_PASSWORD，不启用 MySQL 后端");
+                None
+            }
+            Err(_) => return Err(anyhow!("MYSQL_PASSWORD 环境变量是必需的").into()),
+        };
+
+        // PostgreSQL 配置（可选）
+        let postgres = match env::var("POSTGRES_HOST") {
+            Ok(postgres_host) => {
+                let postgres_port = match env::var("POSTGRES_PORT") {
+                    Ok(port_str) => match port_str.parse::<u16>() {
+                        Ok(port) => port,
+                        Err(_) => {
+                            warn!("无效的PostgreSQL端口号 '{}', 使用默认端口 5432", port_str);
+                            5432
+                        }
+                    },
+                    Err(_) => 5432,
+                };
+
+                let postgres_password = env::var("POSTGRES_PASSWORD").map_err(|_| {
+                    anyhow!("设置了 POSTGRES_HOST 时 POSTGRES_PASSWORD 环境变量是必需的")
+                })?;
+
+                info!("PostgreSQL 后端已启用: {}:{}", postgres_host, postgres_port);
+                Some(PostgreSQLConfig {
+                    host: postgres_host,
+                    port: postgres_port,
+                    username: env::var("POSTGRES_USERNAME")
+                        .unwrap_or_else(|_| "postgres".to_string()),
+                    password: postgres_password,
+                    database: env::var("POSTGRES_DATABASE")
+                        .unwrap_or_else(|_| "postgres".to_string()),
+                })
+            }
+            Err(_) => None,
+        };
+
+        // 命名规则配置
+        let default_naming = NamingConfig::default();
+        let naming = NamingConfig {
+            template: env::var("NAMING_TEMPLATE").unwrap_or(default_naming.template),
+            database_prefix: env::var("NAMING_DB_PREFIX").unwrap_or(default_naming.database_prefix),
+            username_prefix: env::var("NAMING_USER_PREFIX")
+                .unwrap_or(default_naming.username_prefix),
+            term: env::var("NAMING_TERM").ok().filter(|term| !term.is_empty()),
+        };
+
+        // 权限配置档
+        let mut privilege_profiles = PrivilegeConfig::builtin_profiles();
+        if let Ok(value) = env::var("PRIVILEGE_PROFILES") {
+            let custom = PrivilegeConfig::parse_profiles(&value)?;
+            info!(
+                "加载自定义权限配置档: {:?}",
+                custom.keys().collect::<Vec<_>>()
+            );
+            privilege_profiles.extend(custom);
+        }
+        let privileges = PrivilegeConfig {
+            profiles: privilege_profiles,
+            default_profile: env::var("DEFAULT_PRIVILEGE_PROFILE")
+                .unwrap_or_else(|_| "dml".to_string()),
+            use_roles: env::var("PRIVILEGE_USE_ROLES").unwrap_or_default() != "false",
+        };
+
+        // 用户资源限制
+        let default_limits = ResourceLimits::default();
+        let resource_limits = ResourceLimits {
+            max_user_connections: Self::u32_from_env(
+                "MAX_USER_CONNECTIONS",
+                default_limits.max_user_connections,
+            ),
+            max_queries_per_hour: Self::u32_from_env(
+                "MAX_QUERIES_PER_HOUR",
+                default_limits.max_queries_per_hour,
+            ),
+            max_updates_per_hour: Self::u32_from_env(
+                "MAX_UPDATES_PER_HOUR",
+                default_limits.max_updates_per_hour,
+            ),
+        };
+
+        // 存储配额
+        let default_storage = StorageConfig::default();
+        let storage = StorageConfig {
+            quota_mb: Self::u32_from_env("STORAGE_QUOTA_MB", default_storage.quota_mb),
+            check_interval_secs: Self::u32_from_env(
+                "STORAGE_CHECK_INTERVAL_SECS",
+                default_storage.check_interval_secs,
+            ),
+        };
+
+        // 数据库有效期
+        let default_lifecycle = LifecycleConfig::default();
+        let lifecycle = LifecycleConfig {
+            default_lifetime_days: Self::u32_from_env(
+                "DB_LIFETIME_DAYS",
+                default_lifecycle.default_lifetime_days,
+            ),
+            grace_period_days: Self::u32_from_env(
+                "DB_EXPIRY_GRACE_DAYS",
+                default_lifecycle.grace_period_days,
+            ),
+            deletion_retention_days: Self::u32_from_env(
+                "DELETION_RETENTION_DAYS",
+                default_lifecycle.deletion_retention_days,
+            ),
+            check_interval_secs: Self::u32_from_env(
+                "LIFECYCLE_CHECK_INTERVAL_SECS",
+                default_lifecycle.check_interval_secs,
+            ),
+        };
+
+        // 数据库数量配额
+        let quota = DatabaseQuotaConfig {
+            max_databases_per_identity: Self::u32_from_env(
+                "MAX_DATABASES_PER_IDENTITY",
+                DatabaseQuotaConfig::default().max_databases_per_identity,
+            ),
+        };
+
+        // 申请窗口与容量
+        let application = ApplicationConfig {
+            opens_at: env::var("APPLICATION_OPENS_AT")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            closes_at: env::var("APPLICATION_CLOSES_AT")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            max_databases_per_server: Self::u32_from_env("MAX_DATABASES_PER_SERVER", 0),
+        };
+
+        // SQL 导入
+        let default_import = ImportConfig::default();
+        let import = ImportConfig {
+            max_upload_mb: Self::u32_from_env("IMPORT_MAX_UPLOAD_MB", default_import.max_upload_mb),
+            max_sql_mb: Self::u32_from_env("IMPORT_MAX_SQL_MB", default_import.max_sql_mb),
+            max_errors: Self::u32_from_env("IMPORT_MAX_ERRORS", default_import.max_errors),
+        };
+
+        // 数据库重置
+        let reset = ResetConfig {
+            allow_students: env::var("ALLOW_STUDENT_RESET").unwrap_or_default() == "true",
+        };
+
+        // 管理员配置
+        let admin_password = env::var("ADMIN_PASSWORD").unwrap_or_else(|_| {
+            warn!("未设置 ADMIN_PASSWORD，使用默认密码 'admin123'");
+            "admin123".to_string()
+        });
+
+        if admin_password == "admin123" {
+            warn!("⚠️  警告: 正在使用默认管理员密码，请在生产环境中修改！");
+        }
+
+        let config = AppConfig {
+            server: ServerConfig {
+                host: server_host,
+                port: server_port,
+            },
+            database: DatabaseConfig {
+                sqlite_path,
+                default_engine,
+            },
+            mysql,
+            postgres,
+            naming,
+            privileges,
+            resource_limits,
+            storage,
+            lifecycle,
+            quota,
+            application,
+            import,
+            reset,
+            admin: AdminConfig {
+                password: admin_password,
+            },
+        };
+
+        // 验证配置
+        config.validate()?;
+
+        info!("配置加载完成");
+        Ok(config)
+    }
+
+    /// 读取 MySQL 后端配置
+    fn mysql_from_env(mysql_password: String) -> Result<MySQLConfig, Box<dyn std::error::Error>> {
         let mysql_host = env::var("MYSQL_HOST").unwrap_or_else(|_| {
             info!("使用默认MySQL主机: sql.iluwen.cn");
             "sql.iluwen.cn".to_string()
@@ -97,9 +585,6 @@ impl AppConfig {
             "kaiwen".to_string()
         });
 
-        let mysql_password =
-            env::var("MYSQL_PASSWORD").map_err(|_| anyhow!("MYSQL_PASSWORD 环境变量是必需的"))?;
-
         let mysql_database = env::var("MYSQL_DATABASE").unwrap_or_else(|_| {
             info!("使用默认MySQL数据库: default");
             "default".to_string()
@@ -126,40 +611,28 @@ impl AppConfig {
             info!("MySQL允许的主机: localhost (默认)");
         }
 
-        // 管理员配置
-        let admin_password = env::var("ADMIN_PASSWORD").unwrap_or_else(|_| {
-            warn!("未设置 ADMIN_PASSWORD，使用默认密码 'admin123'");
-            "admin123".to_string()
-        });
-
-        if admin_password == "admin123" {
-            warn!("⚠️  警告: 正在使用默认管理员密码，请在生产环境中修改！");
-        }
+        Ok(MySQLConfig {
+            host: mysql_host,
+            port: mysql_port,
+            username: mysql_username,
+            password: mysql_password,
+            database: mysql_database,
+            allowed_host: mysql_allowed_host,
+        })
+    }
 
-        let config = AppConfig {
-            server: ServerConfig {
-                host: server_host,
-                port: server_port,
-            },
-            database: DatabaseConfig { sqlite_path },
-            mysql: MySQLConfig {
-                host: mysql_host,
-                port: mysql_port,
-                username: mysql_username,
-                password: mysql_password,
-                database: mysql_database,
-                allowed_host: mysql_allowed_host,
-            },
-            admin: AdminConfig {
-                password: admin_password,
+    /// 读取数值环境变量，无效时使用默认值
+    fn u32_from_env(name: &str, default: u32) -> u32 {
+        match env::var(name) {
+            Ok(value) => match value.parse::<u32>() {
+                Ok(limit) => limit,
+                Err(_) => {
+                    warn!("无效的 {} '{}', 使用默认值 {}", name, value, default);
+                    default
+                }
             },
-        };
-
-        // 验证配置
-        config.validate()?;
-
-        info!("配置加载完成");
-        Ok(config)
+            Err(_) => default,
+        }
     }
 
     /// 验证配置的有效性
@@ -179,41 +652,86 @@ impl AppConfig {
         }
 
         // 验证MySQL配置
-        if self.mysql.host.is_empty() {
-            return Err(anyhow!("MySQL主机地址不能为空").into());
-        }
+        if let Some(ref mysql) = self.mysql {
+            if mysql.host.is_empty() {
+                return Err(anyhow!("MySQL主机地址不能为空").into());
+            }
 
-        if self.mysql.port == 0 {
-            return Err(anyhow!("MySQL端口不能为0").into());
-        }
+            if mysql.port == 0 {
+                return Err(anyhow!("MySQL端口不能为0").into());
+            }
 
-        if self.mysql.username.is_empty() {
-            return Err(anyhow!("MySQL用户名不能为空").into());
-        }
+            if mysql.username.is_empty() {
+                return Err(anyhow!("MySQL用户名不能为空").into());
+            }
 
-        if self.mysql.password.is_empty() {
-            return Err(anyhow!("MySQL密码不能为空").into());
-        }
+            if mysql.password.is_empty() {
+                return Err(anyhow!("MySQL密码不能为空").into());
+            }
 
-        if self.mysql.database.is_empty() {
-            return Err(anyhow!("MySQL数据库名不能为空").into());
-        }
+            if mysql.database.is_empty() {
+                return Err(anyhow!("MySQL数据库名不能为空").into());
+            }
 
-        // 验证允许的主机配置
-        if let Some(ref allowed_host) = self.mysql.allowed_host {
-            // 检查开发模式
-            let is_dev_mode = std::env::var("DEV_MODE").unwrap_or_default() == "true";
+            // 验证允许的主机配置
+            if let Some(ref allowed_host) = mysql.allowed_host {
+                // 检查开发模式
+                let is_dev_mode = std::env::var("DEV_MODE").unwrap_or_default() == "true";
 
-            if allowed_host == "%" && !is_dev_mode {
-                return Err(anyhow!(
-                    "安全错误: 生产环境不允许使用通配符主机 '%'，请设置 DEV_MODE=true 或使用具体IP"
-                )
-                .into());
+                if allowed_host == "%" && !is_dev_mode {
+                    return Err(anyhow!(
+                        "安全错误: 生产环境不允许使用通配符主机 '%'，请设置 DEV_MODE=true 或使用具体IP"
+                    )
+                    .into());
+                }
+
+                if allowed_host.is_empty() {
+                    return Err(anyhow!("允许的主机不能为空字符串").into());
+                }
             }
+        } else if self.database.default_engine == DatabaseEngine::MySql {
+            return Err(anyhow!("DEFAULT_DB_ENGINE=mysql 需要设置 MYSQL_PASSWORD").into());
+        }
 
-            if allowed_host.is_empty() {
-                return Err(anyhow!("允许的主机不能为空字符串").into());
+        // 验证PostgreSQL配置
+        if let Some(ref postgres) = self.postgres {
+            if postgres.host.is_empty() || postgres.port == 0 {
+                return Err(anyhow!("PostgreSQL主机地址和端口不能为空").into());
             }
+
+            if postgres.username.is_empty() || postgres.password.is_empty() {
+                return Err(anyhow!("PostgreSQL用户名和密码不能为空").into());
+            }
+        } else if self.database.default_engine == DatabaseEngine::Postgres {
+            return Err(anyhow!("DEFAULT_DB_ENGINE=postgres 需要同时设置 POSTGRES_HOST").into());
+        }
+
+        // 验证命名规则
+        crate::database::DatabaseManager::validate_naming(&self.naming)
+            .map_err(|e| anyhow!("命名规则无效: {}", e))?;
+
+        // 验证权限配置档
+        self.privileges
+            .validate()
+            .map_err(|e| anyhow!("权限配置无效: {}", e))?;
+
+        if self.quota.max_databases_per_identity == 0 {
+            return Err(anyhow!("MAX_DATABASES_PER_IDENTITY 不能为0").into());
+        }
+
+        // 验证申请窗口
+        let opens_at = crate::database::DatabaseManager::parse_window_time(
+            self.application.opens_at.as_deref(),
+        )
+        .map_err(|e| anyhow!("APPLICATION_OPENS_AT 无效: {}", e))?;
+        let closes_at = crate::database::DatabaseManager::parse_window_time(
+            self.application.closes_at.as_deref(),
+        )
+        .map_err(|e| anyhow!("APPLICATION_CLOSES_AT 无效: {}", e))?;
+        if let (Some(opens_at), Some(closes_at)) = (opens_at, closes_at)
+            && opens_at >= closes_at
+        {
+            return Err(anyhow!("APPLICATION_CLOSES_AT 必须晚于 APPLICATION_OPENS_AT").into());
         }
 
         info!("配置验证通过");
@@ -225,17 +743,95 @@ impl AppConfig {
         info!("=== DormDB 配置摘要 ===");
         info!("服务器: {}:{}", self.server.host, self.server.port);
         info!("SQLite: {}", self.database.sqlite_path);
-        info!(
-            "MySQL: {}:{}/{}",
-            self.mysql.host, self.mysql.port, self.mysql.database
-        );
-        info!("MySQL用户: {}", self.mysql.username);
-        info!("MySQL密码: [已设置]");
-        if let Some(ref host) = self.mysql.allowed_host {
-            info!("允许的主机: {}", host);
+        if let Some(ref mysql) = self.mysql {
+            info!("MySQL: {}:{}/{}", mysql.host, mysql.port, mysql.database);
+            info!("MySQL用户: {}", mysql.username);
+            info!("MySQL密码: [已设置]");
+            if let Some(ref host) = mysql.allowed_host {
+                info!("允许的主机: {}", host);
+            } else {
+                info!("允许的主机: localhost (默认)");
+            }
         } else {
-            info!("允许的主机: localhost (默认)");
+            info!("MySQL: 未启用");
         }
+        if let Some(ref postgres) = self.postgres {
+            info!(
+                "PostgreSQL: {}:{}/{} (用户: {})",
+                postgres.host, postgres.port, postgres.database, postgres.username
+            );
+        }
+        info!("默认数据库引擎: {}", self.database.default_engine);
+        info!(
+            "命名模板: {} (数据库前缀: {}, 用户前缀: {}, 学期: {})",
+            self.naming.template,
+            self.naming.database_prefix,
+            self.naming.username_prefix,
+            self.naming.term.as_deref().unwrap_or("未设置")
+        );
+        info!(
+            "权限配置档: {} (默认: {}, 使用角色: {})",
+            self.privileges
+                .profiles
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.privileges.default_profile,
+            if self.privileges.use_roles {
+                "是"
+            } else {
+                "否"
+            }
+        );
+        info!(
+            "默认资源限制: 最大连接数 {}, 每小时查询 {}, 每小时更新 {} (0 表示不限制)",
+            self.resource_limits.max_user_connections,
+            self.resource_limits.max_queries_per_hour,
+            self.resource_limits.max_updates_per_hour
+        );
+        info!(
+            "存储配额: {} (统计间隔: {} 秒)",
+            if self.storage.quota_mb == 0 {
+                "不限制".to_string()
+            } else {
+                format!("{} MB", self.storage.quota_mb)
+            },
+            self.storage.check_interval_secs
+        );
+        info!(
+            "数据库有效期: {} (宽限期: {} 天, 删除保留期: {} 天, 检查间隔: {} 秒)",
+            if self.lifecycle.default_lifetime_days == 0 {
+                "永不过期".to_string()
+            } else {
+                format!("{} 天", self.lifecycle.default_lifetime_days)
+            },
+            self.lifecycle.grace_period_days,
+            self.lifecycle.deletion_retention_days,
+            self.lifecycle.check_interval_secs
+        );
+        info!(
+            "申请窗口: {} 至 {} (每个服务器的数据库上限: {})",
+            self.application.opens_at.as_deref().unwrap_or("不限"),
+            self.application.closes_at.as_deref().unwrap_or("不限"),
+            if self.application.max_databases_per_server == 0 {
+                "不限制".to_string()
+            } else {
+                format!("{} 个", self.application.max_databases_per_server)
+            }
+        );
+        info!(
+            "SQL 导入: 上传上限 {} MB, 解压后上限 {} MB, 最多 {} 条失败语句",
+            self.import.max_upload_mb, self.import.max_sql_mb, self.import.max_errors
+        );
+        info!(
+            "学生自助重置数据库: {}",
+            if self.reset.allow_students {
+                "允许"
+            } else {
+                "不允许"
+            }
+        );
         info!("========================");
     }
 }
@@ -0,0 +1,328 @@
+use super::import::SqlStatement;
+use anyhow::Result;
+use tokio::sync::mpsc;
+
+/// 每条 INSERT 语句最多包含的行数
+pub const DUMP_BATCH_ROWS: usize = 500;
+
+/// 每条 INSERT 语句的大致字节上限，远小于 MySQL 默认的 max_allowed_packet
+pub const DUMP_BATCH_BYTES: usize = 1024 * 1024;
+
+/// 客户端停止读取超过此时间（秒）时终止导出，释放后端连接
+pub const DUMP_SEND_TIMEOUT_SECS: u64 = 30;
+
+/// 单次导出的最长时间（秒），超过后终止
+pub const DUMP_MAX_DURATION_SECS: u64 = 600;
+
+/// 导出内容的发送端，后端按顺序发送 SQL 片段，出错时由调用方发送错误并结束
+pub type DumpSender = mpsc::Sender<Result<String>>;
+
+/// 导出内容的接收端
+pub type DumpReceiver = mpsc::Receiver<Result<String>>;
+
+/// 正在进行的导出，SQL 片段在后台任务中生成并按顺序送达
+pub struct DatabaseDump {
+    /// 下载时使用的文件名
+    pub file_name: String,
+    pub chunks: DumpReceiver,
+}
+
+/// 导出的单个字段值
+#[derive(Debug, Clone, PartialEq)]
+pub enum DumpValue {
+    Null,
+    /// 数值，原样输出
+    Number(String),
+    /// 文本，输出为转义后的字符串字面量
+    Text(String),
+    /// 二进制数据的十六进制表示，输出为 X'..'
+    Hex(String),
+    /// BIT 字段的二进制表示，输出为 b'..'
+    Bits(String),
+}
+
+impl DumpValue {
+    pub fn to_sql(&self) -> String {
+        match self {
+            DumpValue::Null => "NULL".to_string(),
+            DumpValue::Number(value) => value.clone(),
+            DumpValue::Text(value) => quote_string(value),
+            DumpValue::Hex(value) if value.is_empty() => "''".to_string(),
+            DumpValue::Hex(value) => format!("X'{}'", value),
+            DumpValue::Bits(value) => format!("b'{}'", value),
+        }
+    }
+}
+
+/// 使用反引号引用标识符
+pub fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// 写入 `--` 注释行的标识符，换行符转义后输出，避免标识符中的换行结束注释
+fn comment_identifier(name: &str) -> String {
+    quote_identifier(name)
+        .replace('\r', "\\r")
+        .replace('\n', "\\n")
+}
+
+/// 转义为 MySQL 字符串字面量
+pub fn quote_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for ch in value.chars() {
+        match ch {
+            '\0' => quoted.push_str("\\0"),
+            '\'' => quoted.push_str("\\'"),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\x1a' => quoted.push_str("\\Z"),
+            _ => quoted.push(ch),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// 导出文件中出现的语句开头，恢复检查点前据此校验脚本
+const DUMP_STATEMENT_PREFIXES: &[&str] = &[
+    "SET ",
+    "DROP TABLE IF EXISTS ",
+    "DROP VIEW IF EXISTS ",
+    "CREATE TABLE ",
+    "CREATE ALGORITHM=",
+    "CREATE VIEW ",
+    "INSERT INTO ",
+];
+
+/// 校验脚本只包含导出文件会生成的语句，出现其他语句时返回错误
+pub fn validate_dump_statements(statements: &[SqlStatement]) -> Result<()> {
+    for statement in statements {
+        let sql = statement.sql.trim_start();
+        let known = DUMP_STATEMENT_PREFIXES.iter().any(|prefix| {
+            sql.get(..prefix.len())
+                .is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+        });
+        if !known {
+            return Err(anyhow::anyhow!(
+                "检查点第 {} 行包含导出文件以外的语句: {}",
+                statement.line,
+                statement.summary()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 导出文件头部
+///
+/// 不包含 CREATE DATABASE 和 USE，导出文件可以导入到任意数据库中。
+pub fn dump_header(db_name: &str, generated_at: &str) -> String {
+    format!(
+        "-- DormDB dump of {}\n-- 生成时间: {}\n\nSET NAMES utf8mb4;\nSET @OLD_FOREIGN_KEY_CHECKS=@@FOREIGN_KEY_CHECKS, FOREIGN_KEY_CHECKS=0;\nSET @OLD_UNIQUE_CHECKS=@@UNIQUE_CHECKS, UNIQUE_CHECKS=0;\n\n",
+        comment_identifier(db_name),
+        generated_at
+    )
+}
+
+/// 导出文件尾部，恢复头部修改的会话变量
+pub fn dump_footer() -> String {
+    "SET UNIQUE_CHECKS=@OLD_UNIQUE_CHECKS;\nSET FOREIGN_KEY_CHECKS=@OLD_FOREIGN_KEY_CHECKS;\n\n-- 导出完成\n"
+        .to_string()
+}
+
+/// 表结构部分
+pub fn table_definition(table: &str, create_table: &str) -> String {
+    format!(
+        "--\n-- 表结构: {}\n--\n\nDROP TABLE IF EXISTS {};\n{};\n\n",
+        comment_identifier(table),
+        quote_identifier(table),
+        create_table
+    )
+}
+
+/// 视图定义部分
+///
+/// 去掉 DEFINER 和库名限定，导入到其他数据库或由其他用户导入时仍然有效。
+pub fn view_definition(db_name: &str, view: &str, create_view: &str) -> String {
+    let definer = regex::Regex::new(r"DEFINER=(`[^`]*`|[^@\s]+)@(`[^`]*`|\S+)\s+").unwrap();
+    let portable = definer
+        .replace(create_view, "")
+        .replace(&format!("{}.", quote_identifier(db_name)), "");
+    format!(
+        "--\n-- 视图: {}\n--\n\nDROP VIEW IF EXISTS {};\n{};\n\n",
+        comment_identifier(view),
+        quote_identifier(view),
+        portable
+    )
+}
+
+/// 生成一条包含多行数据的 INSERT 语句
+pub fn insert_statement(table: &str, columns: &[String], rows: &[Vec<DumpValue>]) -> String {
+    let columns = columns
+        .iter()
+        .map(|column| quote_identifier(column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let values = rows
+        .iter()
+        .map(|row| {
+            let values = row.iter().map(DumpValue::to_sql).collect::<Vec<_>>();
+            format!("({})", values.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+    format!(
+        "INSERT INTO {} ({}) VALUES\n{};\n",
+        quote_identifier(table),
+        columns,
+        values
+    )
+}
+
+/// 按行数和字节数将数据行分批，生成 INSERT 语句
+#[derive(Default)]
+pub struct InsertBatcher {
+    rows: Vec<Vec<DumpValue>>,
+    bytes: usize,
+}
+
+impl InsertBatcher {
+    /// 加入一行数据，达到批量上限时返回生成的 INSERT 语句
+    pub fn push(&mut self, table: &str, columns: &[String], row: Vec<DumpValue>) -> Option<String> {
+        self.bytes += row
+            .iter()
+            .map(|value| match value {
+                DumpValue::Null => 4,
+                DumpValue::Number(v)
+                | DumpValue::Text(v)
+                | DumpValue::Hex(v)
+                | DumpValue::Bits(v) => v.len() + 3,
+            })
+            .sum::<usize>();
+        self.rows.push(row);
+
+        if self.rows.len() >= DUMP_BATCH_ROWS || self.bytes >= DUMP_BATCH_BYTES {
+            self.flush(table, columns)
+        } else {
+            None
+        }
+    }
+
+    /// 输出剩余的数据行
+    pub fn flush(&mut self, table: &str, columns: &[String]) -> Option<String> {
+        if self.rows.is_empty() {
+            return None;
+        }
+        let statement = insert_statement(table, columns, &self.rows);
+        self.rows.clear();
+        self.bytes = 0;
+        Some(statement)
+    }
+}
+
+/// 发送一个 SQL 片段，接收端关闭（客户端断开）或长时间不读取时返回错误以终止导出
+pub async fn send_chunk(sender: &DumpSender, chunk: String) -> Result<()> {
+    let timeout = std::time::Duration::from_secs(DUMP_SEND_TIMEOUT_SECS);
+    match tokio::time::timeout(timeout, sender.send(Ok(chunk))).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(_)) => Err(anyhow::anyhow!("导出已取消：客户端断开连接")),
+        Err(_) => Err(anyhow::anyhow!(
+            "导出已取消：客户端 {} 秒未读取数据",
+            DUMP_SEND_TIMEOUT_SECS
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_string_escapes_special_characters() {
+        assert_eq!(quote_string("it's"), "'it\\'s'");
+        assert_eq!(quote_string("a\\b\nc\r\0\x1a"), "'a\\\\b\\nc\\r\\0\\Z'");
+        assert_eq!(quote_string("中文"), "'中文'");
+        assert_eq!(quote_identifier("we`ird"), "`we``ird`");
+    }
+
+    #[test]
+    fn test_insert_statement_formats_values() {
+        let columns = vec!["id".to_string(), "name".to_string(), "data".to_string()];
+        let rows = vec![
+            vec![
+                DumpValue::Number("1".to_string()),
+                DumpValue::Text("Tom".to_string()),
+                DumpValue::Hex("00FF".to_string()),
+            ],
+            vec![
+                DumpValue::Number("2".to_string()),
+                DumpValue::Null,
+                DumpValue::Bits("101".to_string()),
+            ],
+        ];
+        assert_eq!(
+            insert_statement("users", &columns, &rows),
+            "INSERT INTO `users` (`id`, `name`, `data`) VALUES\n(1, 'Tom', X'00FF'),\n(2, NULL, b'101');\n"
+        );
+    }
+
+    #[test]
+    fn test_batcher_splits_by_row_count() {
+        let columns = vec!["id".to_string()];
+        let mut batcher = InsertBatcher::default();
+        let mut statements = Vec::new();
+        for id in 0..DUMP_BATCH_ROWS + 1 {
+            if let Some(statement) =
+                batcher.push("t", &columns, vec![DumpValue::Number(id.to_string())])
+            {
+                statements.push(statement);
+            }
+        }
+        statements.extend(batcher.flush("t", &columns));
+
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[1], "INSERT INTO `t` (`id`) VALUES\n(500);\n");
+        assert!(batcher.flush("t", &columns).is_none());
+    }
+
+    #[test]
+    fn test_view_definition_is_portable() {
+        let create_view = "CREATE ALGORITHM=UNDEFINED DEFINER=`user_2023010101`@`localhost` SQL SECURITY DEFINER VIEW `v` AS select `db_2023010101`.`t`.`id` AS `id` from `db_2023010101`.`t`";
+        let definition = view_definition("db_2023010101", "v", create_view);
+        assert!(definition.contains(
+            "CREATE ALGORITHM=UNDEFINED SQL SECURITY DEFINER VIEW `v` AS select `t`.`id` AS `id` from `t`;"
+        ));
+    }
+
+    #[test]
+    fn test_newline_in_identifier_stays_in_comment() {
+        let name = "x\nDROP DATABASE mysql;\n-- ";
+        let quoted = quote_identifier(name);
+        let dump = format!(
+            "{}{}",
+            table_definition(name, &format!("CREATE TABLE {} (id int)", quoted)),
+            view_definition(
+                "db_2023010101",
+                name,
+                &format!("CREATE VIEW {} AS select 1 AS `id`", quoted)
+            )
+        );
+        assert!(dump.contains("-- 表结构: `x\\nDROP DATABASE mysql;\\n-- `\n"));
+
+        let statements = super::super::import::split_statements(&dump);
+        assert_eq!(statements.len(), 4);
+        for statement in statements {
+            let sql = statement.sql.trim_start();
+            assert!(
+                sql.starts_with("DROP TABLE IF EXISTS")
+                    || sql.starts_with("CREATE TABLE")
+                    || sql.starts_with("DROP VIEW IF EXISTS")
+                    || sql.starts_with("CREATE VIEW"),
+                "{}",
+                sql
+            );
+        }
+    }
+}
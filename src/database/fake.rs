@@ -0,0 +1,666 @@
+use super::dump::{self, DumpSender};
+use super::grader::QueryRows;
+use super::import::SqlStatement;
+use super::provisioner::Provisioner;
+use super::similarity::{SchemaColumn, SchemaSnapshot};
+use crate::models::{DatabaseCredentials, DatabaseEngine, ResourceLimits};
+use crate::utils::encode_uri_component;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::Mutex;
+
+/// 与 MySQL 一致的名称长度限制
+const MAX_DATABASE_NAME_LEN: usize = 64;
+const MAX_USERNAME_LEN: usize = 32;
+
+/// 可注入失败的供应步骤
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FakeStep {
+    Ping,
+    CreateDatabase,
+    CreateUser,
+    GrantPrivileges,
+    RevokePrivileges,
+    FlushPrivileges,
+    DropUser,
+    DropDatabase,
+    DatabaseExists,
+    UserExists,
+    AlterResourceLimits,
+    SetPassword,
+    LockUser,
+    UnlockUser,
+    KillSessions,
+    MoveDatabase,
+    ClearDatabase,
+    DumpDatabase,
+    DatabaseSize,
+    CreateRole,
+    GrantRole,
+    DropRole,
+}
+
+#[derive(Default)]
+struct FakeState {
+    databases: BTreeSet<String>,
+    /// 用户名 -> 密码
+    users: BTreeMap<String, String>,
+    /// 用户名 -> 资源限制
+    limits: BTreeMap<String, ResourceLimits>,
+    /// (数据库名, 用户名或角色名) -> 权限
+    grants: BTreeMap<(String, String), BTreeSet<String>>,
+    roles: BTreeSet<String>,
+    /// 数据库名 -> 占用空间（字节）
+    sizes: BTreeMap<String, u64>,
+    /// 已锁定的用户
+    locked: BTreeSet<String>,
+    /// 用户名 -> 当前会话数
+    sessions: BTreeMap<String, u32>,
+    /// 数据库名 -> 已执行的 SQL 语句
+    executed: BTreeMap<String, Vec<String>>,
+    /// 包含这些内容的语句执行失败
+    rejected_statements: Vec<String>,
+    /// (数据库名, 查询语句) -> 查询结果
+    query_results: BTreeMap<(String, String), QueryRows>,
+    /// (数据库名, 表名) -> 字段名
+    tables: BTreeMap<(String, String), Vec<String>>,
+    /// 用户名 -> 已授予的角色
+    role_members: BTreeMap<String, BTreeSet<String>>,
+    roles_supported: bool,
+    failures: HashSet<FakeStep>,
+}
+
+/// 内存中的供应后端，仅用于测试
+///
+/// 记录创建的数据库、用户和授权，不连接任何真实数据库。
+/// 通过 `fail_on` 可以让任意步骤返回错误，用于测试回滚和修复流程。
+#[derive(Default)]
+pub struct FakeProvisioner {
+    state: Mutex<FakeState>,
+}
+
+impl FakeProvisioner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建支持角色的供应后端
+    pub fn with_roles() -> Self {
+        let fake = Self::default();
+        fake.state.lock().unwrap().roles_supported = true;
+        fake
+    }
+
+    /// 让指定步骤在之后的调用中失败
+    pub fn fail_on(&self, step: FakeStep) {
+        self.state.lock().unwrap().failures.insert(step);
+    }
+
+    /// 清除所有注入的失败
+    pub fn clear_failures(&self) {
+        self.state.lock().unwrap().failures.clear();
+    }
+
+    pub fn databases(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .databases
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    pub fn users(&self) -> Vec<String> {
+        self.state.lock().unwrap().users.keys().cloned().collect()
+    }
+
+    pub fn has_database(&self, db_name: &str) -> bool {
+        self.state.lock().unwrap().databases.contains(db_name)
+    }
+
+    pub fn has_user(&self, username: &str) -> bool {
+        self.state.lock().unwrap().users.contains_key(username)
+    }
+
+    pub fn is_locked(&self, username: &str) -> bool {
+        self.state.lock().unwrap().locked.contains(username)
+    }
+
+    /// 模拟用户当前打开的会话数
+    pub fn set_sessions(&self, username: &str, count: u32) {
+        self.state
+            .lock()
+            .unwrap()
+            .sessions
+            .insert(username.to_string(), count);
+    }
+
+    pub fn sessions_of(&self, username: &str) -> u32 {
+        self.state
+            .lock()
+            .unwrap()
+            .sessions
+            .get(username)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn limits_of(&self, username: &str) -> Option<ResourceLimits> {
+        self.state.lock().unwrap().limits.get(username).copied()
+    }
+
+    pub fn password_of(&self, username: &str) -> Option<String> {
+        self.state.lock().unwrap().users.get(username).cloned()
+    }
+
+    /// 获取用户在指定数据库上的有效权限（包括通过角色获得的权限）
+    pub fn grants(&self, db_name: &str, username: &str) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        let mut grantees = vec![username.to_string()];
+        if let Some(roles) = state.role_members.get(username) {
+            grantees.extend(roles.iter().cloned());
+        }
+
+        let privileges: BTreeSet<String> = grantees
+            .into_iter()
+            .filter_map(|grantee| state.grants.get(&(db_name.to_string(), grantee)))
+            .flatten()
+            .cloned()
+            .collect();
+        privileges.into_iter().collect()
+    }
+
+    pub fn roles(&self) -> Vec<String> {
+        self.state.lock().unwrap().roles.iter().cloned().collect()
+    }
+
+    pub fn roles_of(&self, username: &str) -> Vec<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .role_members
+            .get(username)
+            .map(|roles| roles.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 设置数据库占用的空间，模拟用户写入或删除数据
+    pub fn set_database_size(&self, db_name: &str, bytes: u64) {
+        self.state
+            .lock()
+            .unwrap()
+            .sizes
+            .insert(db_name.to_string(), bytes);
+    }
+
+    /// 绕过供应流程直接删除数据库，模拟外部造成的数据不一致
+    pub fn remove_database(&self, db_name: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.databases.remove(db_name);
+        state.grants.retain(|(db, _), _| db != db_name);
+    }
+
+    /// 让包含指定内容的语句执行失败
+    pub fn reject_statements_containing(&self, pattern: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .rejected_statements
+            .push(pattern.to_string());
+    }
+
+    /// 设置查询在数据库中返回的结果，未设置的查询执行失败
+    pub fn set_query_result(&self, db_name: &str, sql: &str, rows: QueryRows) {
+        self.state
+            .lock()
+            .unwrap()
+            .query_results
+            .insert((db_name.to_string(), sql.to_string()), rows);
+    }
+
+    /// 在数据库中创建表，只记录字段名
+    pub fn create_table(&self, db_name: &str, table: &str, columns: &[&str]) {
+        self.state.lock().unwrap().tables.insert(
+            (db_name.to_string(), table.to_string()),
+            columns.iter().map(|c| c.to_string()).collect(),
+        );
+    }
+
+    pub fn executed_statements(&self, db_name: &str) -> Vec<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .executed
+            .get(db_name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn check(&self, step: FakeStep) -> Result<()> {
+        if self.state.lock().unwrap().failures.contains(&step) {
+            return Err(anyhow::anyhow!("注入的失败: {:?}", step));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Provisioner for FakeProvisioner {
+    fn engine(&self) -> DatabaseEngine {
+        DatabaseEngine::MySql
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.check(FakeStep::Ping)
+    }
+
+    async fn create_database(&self, db_name: &str) -> Result<()> {
+        self.check(FakeStep::CreateDatabase)?;
+        if db_name.chars().count() > MAX_DATABASE_NAME_LEN {
+            return Err(anyhow::anyhow!("数据库名过长: {}", db_name));
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .databases
+            .insert(db_name.to_string());
+        Ok(())
+    }
+
+    async fn create_user(
+        &self,
+        username: &str,
+        password: &str,
+        limits: &ResourceLimits,
+    ) -> Result<()> {
+        self.check(FakeStep::CreateUser)?;
+        if username.chars().count() > MAX_USERNAME_LEN {
+            return Err(anyhow::anyhow!("用户名过长: {}", username));
+        }
+        let mut state = self.state.lock().unwrap();
+        if !state.users.contains_key(username) {
+            state
+                .users
+                .insert(username.to_string(), password.to_string());
+            state.limits.insert(username.to_string(), *limits);
+        }
+        Ok(())
+    }
+
+    async fn set_password(&self, username: &str, password: &str) -> Result<()> {
+        self.check(FakeStep::SetPassword)?;
+        let mut state = self.state.lock().unwrap();
+        match state.users.get_mut(username) {
+            Some(stored) => {
+                *stored = password.to_string();
+                Ok(())
+            }
+            None => Err(anyhow::anyhow!("用户不存在: {}", username)),
+        }
+    }
+
+    async fn lock_user(&self, username: &str) -> Result<()> {
+        self.check(FakeStep::LockUser)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.users.contains_key(username) {
+            return Err(anyhow::anyhow!("用户不存在: {}", username));
+        }
+        state.locked.insert(username.to_string());
+        Ok(())
+    }
+
+    async fn unlock_user(&self, username: &str) -> Result<()> {
+        self.check(FakeStep::UnlockUser)?;
+        self.state.lock().unwrap().locked.remove(username);
+        Ok(())
+    }
+
+    async fn kill_sessions(&self, username: &str) -> Result<u32> {
+        self.check(FakeStep::KillSessions)?;
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .sessions
+            .remove(username)
+            .unwrap_or(0))
+    }
+
+    async fn alter_resource_limits(&self, username: &str, limits: &ResourceLimits) -> Result<()> {
+        self.check(FakeStep::AlterResourceLimits)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.users.contains_key(username) {
+            return Err(anyhow::anyhow!("用户不存在: {}", username));
+        }
+        state.limits.insert(username.to_string(), *limits);
+        Ok(())
+    }
+
+    async fn grant_privileges(
+        &self,
+        db_name: &str,
+        username: &str,
+        privileges: &[&str],
+    ) -> Result<()> {
+        self.check(FakeStep::GrantPrivileges)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.databases.contains(db_name)
+            || !(state.users.contains_key(username) || state.roles.contains(username))
+        {
+            return Err(anyhow::anyhow!("数据库、用户或角色不存在"));
+        }
+        state
+            .grants
+            .entry((db_name.to_string(), username.to_string()))
+            .or_default()
+            .extend(privileges.iter().map(|p| p.to_string()));
+        Ok(())
+    }
+
+    async fn revoke_privileges(
+        &self,
+        db_name: Option<&str>,
+        username: &str,
+        privileges: &[&str],
+    ) -> Result<()> {
+        self.check(FakeStep::RevokePrivileges)?;
+        let mut state = self.state.lock().unwrap();
+        for ((db, user), granted) in state.grants.iter_mut() {
+            if user == username && db_name.is_none_or(|name| name == db) {
+                granted.retain(|p| !privileges.contains(&p.as_str()));
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush_privileges(&self) -> Result<()> {
+        self.check(FakeStep::FlushPrivileges)
+    }
+
+    async fn drop_user(&self, username: &str) -> Result<()> {
+        self.check(FakeStep::DropUser)?;
+        let mut state = self.state.lock().unwrap();
+        state.users.remove(username);
+        state.locked.remove(username);
+        state.limits.remove(username);
+        state.role_members.remove(username);
+        state.grants.retain(|(_, user), _| user != username);
+        Ok(())
+    }
+
+    async fn drop_database(&self, db_name: &str) -> Result<()> {
+        self.check(FakeStep::DropDatabase)?;
+        let mut state = self.state.lock().unwrap();
+        state.databases.remove(db_name);
+        state.grants.retain(|(db, _), _| db != db_name);
+        state.executed.remove(db_name);
+        state.tables.retain(|(db, _), _| db != db_name);
+        state.query_results.retain(|(db, _), _| db != db_name);
+        Ok(())
+    }
+
+    async fn move_database(&self, from: &str, to: &str) -> Result<()> {
+        self.check(FakeStep::MoveDatabase)?;
+        let mut state = self.state.lock().unwrap();
+        if state.databases.contains(to) {
+            return Err(anyhow::anyhow!("目标数据库已存在: {}", to));
+        }
+        if !state.databases.remove(from) {
+            return Err(anyhow::anyhow!("数据库不存在: {}", from));
+        }
+        state.databases.insert(to.to_string());
+        state.grants.retain(|(db, _), _| db != from);
+        if let Some(size) = state.sizes.remove(from) {
+            state.sizes.insert(to.to_string(), size);
+        }
+        Ok(())
+    }
+
+    async fn clear_database(&self, db_name: &str) -> Result<u32> {
+        self.check(FakeStep::ClearDatabase)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.databases.contains(db_name) {
+            return Err(anyhow::anyhow!("数据库不存在: {}", db_name));
+        }
+        state.tables.retain(|(db, _), _| db != db_name);
+        let cleared = state.executed.remove(db_name).unwrap_or_default();
+        Ok(cleared.len() as u32)
+    }
+
+    async fn verify_password(
+        &self,
+        _db_name: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<bool> {
+        let state = self.state.lock().unwrap();
+        if state.locked.contains(username) {
+            return Err(anyhow::anyhow!("用户已锁定: {}", username));
+        }
+        Ok(state.users.get(username).map(String::as_str) == Some(password))
+    }
+
+    async fn dump_database(&self, db_name: &str, sender: &DumpSender) -> Result<()> {
+        if !self.has_database(db_name) {
+            return Err(anyhow::anyhow!("数据库不存在: {}", db_name));
+        }
+        dump::send_chunk(sender, dump::dump_header(db_name, "fake")).await?;
+        self.check(FakeStep::DumpDatabase)?;
+        dump::send_chunk(sender, dump::dump_footer()).await
+    }
+
+    async fn execute_script(&self, db_name: &str, statements: &[SqlStatement]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if !state.databases.contains(db_name) {
+            return Err(anyhow::anyhow!("数据库不存在: {}", db_name));
+        }
+        for statement in statements {
+            if state
+                .rejected_statements
+                .iter()
+                .any(|pattern| statement.sql.contains(pattern.as_str()))
+            {
+                return Err(anyhow::anyhow!(
+                    "第 {} 行的语句执行失败: 注入的语句错误",
+                    statement.line
+                ));
+            }
+            state
+                .executed
+                .entry(db_name.to_string())
+                .or_default()
+                .push(statement.sql.clone());
+        }
+        Ok(())
+    }
+
+    async fn query_rows(
+        &self,
+        db_name: &str,
+        username: &str,
+        password: &str,
+        sql: &str,
+    ) -> Result<QueryRows> {
+        if !self.verify_password(db_name, username, password).await? {
+            return Err(anyhow::anyhow!("Access denied for user '{}'", username));
+        }
+        if !self
+            .grants(db_name, username)
+            .contains(&"SELECT".to_string())
+        {
+            return Err(anyhow::anyhow!(
+                "SELECT command denied to user '{}'",
+                username
+            ));
+        }
+        let state = self.state.lock().unwrap();
+        if !state.databases.contains(db_name) {
+            return Err(anyhow::anyhow!("数据库不存在: {}", db_name));
+        }
+        state
+            .query_results
+            .get(&(db_name.to_string(), sql.to_string()))
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("查询执行失败: {}", sql))
+    }
+
+    async fn table_columns(&self, db_name: &str, table: &str) -> Result<Option<Vec<String>>> {
+        let state = self.state.lock().unwrap();
+        if !state.databases.contains(db_name) {
+            return Err(anyhow::anyhow!("数据库不存在: {}", db_name));
+        }
+        Ok(state
+            .tables
+            .get(&(db_name.to_string(), table.to_string()))
+            .cloned())
+    }
+
+    /// 测试后端只记录表和字段，不返回索引和数据
+    async fn schema_snapshot(&self, db_name: &str, _include_rows: bool) -> Result<SchemaSnapshot> {
+        let state = self.state.lock().unwrap();
+        if !state.databases.contains(db_name) {
+            return Err(anyhow::anyhow!("数据库不存在: {}", db_name));
+        }
+        let columns = state
+            .tables
+            .iter()
+            .filter(|((db, _), _)| db == db_name)
+            .flat_map(|((_, table), columns)| {
+                columns.iter().map(move |name| SchemaColumn {
+                    table: table.clone(),
+                    name: name.clone(),
+                    data_type: "text".to_string(),
+                    nullable: true,
+                })
+            })
+            .collect();
+        Ok(SchemaSnapshot {
+            columns,
+            ..Default::default()
+        })
+    }
+
+    async fn execute_as_user(
+        &self,
+        db_name: &str,
+        username: &str,
+        password: &str,
+        statements: &[SqlStatement],
+        max_errors: usize,
+    ) -> Result<Vec<std::result::Result<(), String>>> {
+        if !self.verify_password(db_name, username, password).await? {
+            return Err(anyhow::anyhow!("Access denied for user '{}'", username));
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let mut results = Vec::new();
+        let mut failed = 0;
+        for statement in statements {
+            if state
+                .rejected_statements
+                .iter()
+                .any(|pattern| statement.sql.contains(pattern.as_str()))
+            {
+                failed += 1;
+                results.push(Err(format!("注入的语句错误: {}", statement.sql)));
+                if failed >= max_errors {
+                    break;
+                }
+            } else {
+                state
+                    .executed
+                    .entry(db_name.to_string())
+                    .or_default()
+                    .push(statement.sql.clone());
+                results.push(Ok(()));
+            }
+        }
+        Ok(results)
+    }
+
+    async fn database_exists(&self, db_name: &str) -> Result<bool> {
+        self.check(FakeStep::DatabaseExists)?;
+        Ok(self.has_database(db_name))
+    }
+
+    async fn user_exists(&self, username: &str) -> Result<bool> {
+        self.check(FakeStep::UserExists)?;
+        Ok(self.has_user(username))
+    }
+
+    async fn database_size(&self, db_name: &str) -> Result<u64> {
+        self.check(FakeStep::DatabaseSize)?;
+        let state = self.state.lock().unwrap();
+        if !state.databases.contains(db_name) {
+            return Err(anyhow::anyhow!("数据库不存在: {}", db_name));
+        }
+        Ok(state.sizes.get(db_name).copied().unwrap_or(0))
+    }
+
+    async fn supports_roles(&self) -> Result<bool> {
+        Ok(self.state.lock().unwrap().roles_supported)
+    }
+
+    async fn create_role(&self, role: &str) -> Result<()> {
+        self.check(FakeStep::CreateRole)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.roles_supported {
+            return Err(anyhow::anyhow!("不支持角色"));
+        }
+        if role.chars().count() > MAX_USERNAME_LEN {
+            return Err(anyhow::anyhow!("角色名过长: {}", role));
+        }
+        state.roles.insert(role.to_string());
+        Ok(())
+    }
+
+    async fn grant_role(&self, role: &str, username: &str) -> Result<()> {
+        self.check(FakeStep::GrantRole)?;
+        let mut state = self.state.lock().unwrap();
+        if !state.roles.contains(role) || !state.users.contains_key(username) {
+            return Err(anyhow::anyhow!("角色或用户不存在"));
+        }
+        state
+            .role_members
+            .entry(username.to_string())
+            .or_default()
+            .insert(role.to_string());
+        Ok(())
+    }
+
+    async fn drop_role(&self, role: &str) -> Result<()> {
+        self.check(FakeStep::DropRole)?;
+        let mut state = self.state.lock().unwrap();
+        state.roles.remove(role);
+        state.grants.retain(|(_, grantee), _| grantee != role);
+        for roles in state.role_members.values_mut() {
+            roles.remove(role);
+        }
+        Ok(())
+    }
+
+    fn credentials(&self, db_name: &str, username: &str, password: &str) -> DatabaseCredentials {
+        DatabaseCredentials {
+            db_engine: DatabaseEngine::MySql,
+            db_host: "localhost".to_string(),
+            db_port: 3306,
+            db_name: db_name.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            connection_string: format!(
+                "mysql://{}:{}@localhost:3306/{}",
+                encode_uri_component(username),
+                encode_uri_component(password),
+                db_name
+            ),
+            jdbc_url: format!(
+                "jdbc:mysql://localhost:3306/{}?user={}&password={}",
+                db_name,
+                encode_uri_component(username),
+                encode_uri_component(password)
+            ),
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use super::import::split_statements;
+use crate::models::{AssignmentCheck, CheckResult, GradeEntry};
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// 评分查询的执行时间上限（毫秒）
+pub const GRADER_QUERY_TIMEOUT_MS: u64 = 10_000;
+
+/// 评分查询最多读取的行数，超过时判为未通过
+pub const GRADER_MAX_ROWS: usize = 10_000;
+
+/// 评分查询中不允许出现的关键字
+///
+/// 查询以只有 SELECT 权限的临时用户执行，越权操作由数据库拒绝；这里只是保存作业和评分前的快速检查，
+/// 只接受单条 SELECT（可带 WITH），并拒绝可能修改数据或写文件的关键字。
+const GRADER_FORBIDDEN_KEYWORDS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "INTO", "CREATE", "DROP", "ALTER", "TRUNCATE", "RENAME",
+    "GRANT", "REVOKE", "CALL", "LOCK", "HANDLER", "LOAD", "COMMIT", "ROLLBACK",
+];
+
+/// 评分查询中不允许调用的函数：读取服务器文件、长时间占用连接或加锁
+const GRADER_FORBIDDEN_FUNCTIONS: &[&str] = &[
+    "LOAD_FILE",
+    "SLEEP",
+    "BENCHMARK",
+    "GET_LOCK",
+    "PG_SLEEP",
+    "PG_READ_FILE",
+];
+
+/// 查询结果，每个值按文本表示，NULL 为 `None`
+pub type QueryRows = Vec<Vec<Option<String>>>;
+
+/// 校验作业的检查项
+pub fn validate_checks(checks: &[AssignmentCheck]) -> Result<()> {
+    if checks.is_empty() {
+        return Err(anyhow::anyhow!("作业检查项无效: 至少需要一个检查项"));
+    }
+
+    let mut names = HashSet::new();
+    for check in checks {
+        if check.name.trim().is_empty() {
+            return Err(anyhow::anyhow!("作业检查项无效: 名称不能为空"));
+        }
+        if !names.insert(check.name.as_str()) {
+            return Err(anyhow::anyhow!("作业检查项无效: 名称 {} 重复", check.name));
+        }
+        match (&check.query, &check.table) {
+            (Some(query), None) => {
+                if query.trim().is_empty() || check.expected_rows.is_none() {
+                    return Err(anyhow::anyhow!(
+                        "作业检查项无效: {} 需要查询语句和期望结果",
+                        check.name
+                    ));
+                }
+                validate_query(query).map_err(|reason| {
+                    anyhow::anyhow!("作业检查项无效: {} 的查询{}", check.name, reason)
+                })?;
+            }
+            (None, Some(table)) => {
+                if table.trim().is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "作业检查项无效: {} 的表名不能为空",
+                        check.name
+                    ));
+                }
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "作业检查项无效: {} 必须且只能指定 query 或 table 之一",
+                    check.name
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 检查评分查询是否为单条只读的 SELECT，不是时返回原因
+pub fn validate_query(sql: &str) -> std::result::Result<(), String> {
+    let statements = split_statements(sql);
+    let [statement] = statements.as_slice() else {
+        return Err("必须是单条语句".to_string());
+    };
+
+    // 注释已去掉，条件注释 /*! */ 会被 MySQL 执行，按普通文本检查
+    let chars: Vec<char> = statement.sql.chars().collect();
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        match ch {
+            '\'' | '"' | '`' => {
+                i += 1;
+                while i < chars.len() && chars[i] != ch {
+                    if chars[i] == '\\' && ch != '`' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            ';' => return Err("必须是单条语句".to_string()),
+            ':' if chars.get(i + 1) == Some(&'=') => {
+                return Err("不能包含变量赋值 :=".to_string());
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                words.push(
+                    chars[start..i]
+                        .iter()
+                        .collect::<String>()
+                        .to_ascii_uppercase(),
+                );
+            }
+            _ => i += 1,
+        }
+    }
+
+    if !matches!(words.first().map(String::as_str), Some("SELECT" | "WITH")) {
+        return Err("只能是 SELECT 语句".to_string());
+    }
+    if let Some(word) = words.iter().find(|w| {
+        GRADER_FORBIDDEN_KEYWORDS.contains(&w.as_str())
+            || GRADER_FORBIDDEN_FUNCTIONS.contains(&w.as_str())
+    }) {
+        return Err(format!("不能包含 {}", word));
+    }
+    Ok(())
+}
+
+/// 比较查询结果与期望结果，不一致时返回原因
+///
+/// 值按去掉首尾空白后的文本比较；`ordered` 为 false 时忽略行的顺序。
+/// 原因中不包含学生数据库中的实际数据。
+pub fn compare_rows(
+    actual: &QueryRows,
+    expected: &QueryRows,
+    ordered: bool,
+) -> std::result::Result<(), String> {
+    if actual.len() != expected.len() {
+        return Err(format!(
+            "返回 {} 行，应为 {} 行",
+            actual.len(),
+            expected.len()
+        ));
+    }
+
+    let normalize = |rows: &QueryRows| -> QueryRows {
+        let mut rows: QueryRows = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| value.as_ref().map(|v| v.trim().to_string()))
+                    .collect()
+            })
+            .collect();
+        if !ordered {
+            rows.sort();
+        }
+        rows
+    };
+    let (actual, expected) = (normalize(actual), normalize(expected));
+
+    for (index, (actual_row, expected_row)) in actual.iter().zip(&expected).enumerate() {
+        if actual_row.len() != expected_row.len() {
+            return Err(format!(
+                "返回 {} 列，应为 {} 列",
+                actual_row.len(),
+                expected_row.len()
+            ));
+        }
+        if actual_row != expected_row {
+            return Err(if ordered {
+                format!("第 {} 行与期望结果不一致", index + 1)
+            } else {
+                "结果与期望不一致".to_string()
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 检查表中缺少的字段（不区分大小写），全部存在时返回 `Ok`
+pub fn check_columns(
+    table: &str,
+    actual: Option<&[String]>,
+    required: &[String],
+) -> std::result::Result<(), String> {
+    let Some(actual) = actual else {
+        return Err(format!("表 {} 不存在", table));
+    };
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|column| !actual.iter().any(|a| a.eq_ignore_ascii_case(column)))
+        .map(String::as_str)
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("表 {} 缺少字段: {}", table, missing.join(", ")))
+    }
+}
+
+/// 根据检查结果生成单个检查项的评分
+pub fn check_result(
+    check: &AssignmentCheck,
+    outcome: std::result::Result<(), String>,
+) -> CheckResult {
+    match outcome {
+        Ok(()) => CheckResult {
+            name: check.name.clone(),
+            passed: true,
+            points: check.points,
+            message: None,
+        },
+        Err(message) => CheckResult {
+            name: check.name.clone(),
+            passed: false,
+            points: 0,
+            message: Some(message),
+        },
+    }
+}
+
+/// CSV 字段转义
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// 生成成绩单 CSV，每个检查项一列
+///
+/// 作业修改后尚未重新评分的记录中没有的检查项留空。
+pub fn gradebook_csv(checks: &[AssignmentCheck], entries: &[GradeEntry]) -> String {
+    let mut header = vec![
+        "identity_key".to_string(),
+        "student_name".to_string(),
+        "class_info".to_string(),
+    ];
+    header.extend(checks.iter().map(|check| csv_field(&check.name)));
+    header.extend(["score", "max_score", "graded_at"].map(String::from));
+
+    let mut csv = header.join(",");
+    csv.push('\n');
+    for entry in entries {
+        let mut fields = vec![
+            csv_field(&entry.identity_key),
+            csv_field(entry.student_name.as_deref().unwrap_or("")),
+            csv_field(entry.class_info.as_deref().unwrap_or("")),
+        ];
+        fields.extend(checks.iter().map(|check| {
+            entry
+                .checks
+                .iter()
+                .find(|result| result.name == check.name)
+                .map(|result| result.points.to_string())
+                .unwrap_or_default()
+        }));
+        fields.push(entry.score.to_string());
+        fields.push(entry.max_score.to_string());
+        fields.push(csv_field(&entry.graded_at));
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(values: &[&[Option<&str>]]) -> QueryRows {
+        values
+            .iter()
+            .map(|row| row.iter().map(|v| v.map(String::from)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_query() {
+        assert!(validate_query("SELECT name FROM student WHERE dept = 'update';").is_ok());
+        assert!(validate_query("-- 每个系的人数\nWITH c AS (SELECT 1) SELECT * FROM c").is_ok());
+        assert!(validate_query("(SELECT 1) UNION (SELECT 2)").is_ok());
+        assert!(validate_query("SELECT `delete` FROM t").is_ok());
+
+        assert_eq!(
+            validate_query("SELECT 1; DROP TABLE t"),
+            Err("必须是单条语句".to_string())
+        );
+        assert_eq!(
+            validate_query("SELECT 1 /*!; DROP TABLE t */"),
+            Err("必须是单条语句".to_string())
+        );
+        assert_eq!(
+            validate_query("DROP TABLE t"),
+            Err("只能是 SELECT 语句".to_string())
+        );
+        assert_eq!(
+            validate_query("WITH c AS (SELECT 1) DELETE FROM t"),
+            Err("不能包含 DELETE".to_string())
+        );
+        assert_eq!(
+            validate_query("SELECT * FROM t INTO OUTFILE '/tmp/x'"),
+            Err("不能包含 INTO".to_string())
+        );
+        for (sql, keyword) in [
+            ("SELECT LOAD_FILE('/etc/passwd')", "LOAD_FILE"),
+            ("SELECT SLEEP(100)", "SLEEP"),
+            ("SELECT BENCHMARK(100000000, MD5('x'))", "BENCHMARK"),
+            ("SELECT GET_LOCK('grader', 10)", "GET_LOCK"),
+            ("SELECT pg_sleep(100)", "PG_SLEEP"),
+        ] {
+            assert_eq!(validate_query(sql), Err(format!("不能包含 {}", keyword)));
+        }
+        assert_eq!(
+            validate_query("SELECT @x := COUNT(*) FROM t"),
+            Err("不能包含变量赋值 :=".to_string())
+        );
+        assert!(validate_query("SELECT ':=' AS s").is_ok());
+        assert!(validate_query("").is_err());
+    }
+
+    #[test]
+    fn test_compare_rows() {
+        let expected = rows(&[&[Some("a"), Some("1")], &[Some("b"), None]]);
+        let reversed = rows(&[&[Some("b"), None], &[Some("a"), Some(" 1 ")]]);
+
+        assert!(compare_rows(&reversed, &expected, false).is_ok());
+        assert_eq!(
+            compare_rows(&reversed, &expected, true),
+            Err("第 1 行与期望结果不一致".to_string())
+        );
+        assert_eq!(
+            compare_rows(&expected[..1].to_vec(), &expected, false),
+            Err("返回 1 行，应为 2 行".to_string())
+        );
+        assert_eq!(
+            compare_rows(&rows(&[&[Some("a")], &[Some("b")]]), &expected, false),
+            Err("返回 1 列，应为 2 列".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_columns_and_csv() {
+        let columns = vec!["ID".to_string(), "name".to_string()];
+        let required = vec!["id".to_string(), "salary".to_string()];
+        assert_eq!(
+            check_columns("instructor", Some(&columns), &required),
+            Err("表 instructor 缺少字段: salary".to_string())
+        );
+        assert_eq!(
+            check_columns("instructor", None, &required),
+            Err("表 instructor 不存在".to_string())
+        );
+
+        let check = AssignmentCheck {
+            name: "q1".to_string(),
+            points: 10,
+            query: Some("SELECT 1".to_string()),
+            expected_rows: Some(vec![vec![Some("1".to_string())]]),
+            ordered: false,
+            table: None,
+            columns: Vec::new(),
+        };
+        let entry = GradeEntry {
+            identity_key: "2023010101".to_string(),
+            student_name: Some("张三, Jr.".to_string()),
+            class_info: None,
+            score: 10,
+            max_score: 10,
+            checks: vec![check_result(&check, Ok(()))],
+            graded_at: "2025-10-08 20:00:00".to_string(),
+        };
+        assert_eq!(
+            gradebook_csv(&[check], &[entry]),
+            "identity_key,student_name,class_info,q1,score,max_score,graded_at\n2023010101,\"张三, Jr.\",,10,10,10,2025-10-08 20:00:00\n"
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use std::io::Read;
+
+/// gzip 文件头
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// 错误报告中保留的语句长度（字符数）
+const REPORT_STATEMENT_CHARS: usize = 200;
+
+/// 从 SQL 文件中拆分出的单条语句
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlStatement {
+    /// 语句在文件中的起始行号（从 1 开始）
+    pub line: usize,
+    pub sql: String,
+}
+
+impl SqlStatement {
+    /// 错误报告中展示的语句，过长时截断
+    pub fn summary(&self) -> String {
+        let mut chars = self.sql.chars();
+        let summary: String = chars.by_ref().take(REPORT_STATEMENT_CHARS).collect();
+        if chars.next().is_some() {
+            format!("{}...", summary)
+        } else {
+            summary
+        }
+    }
+}
+
+/// 解码上传的 SQL 文件，gzip 压缩的文件会先解压
+///
+/// 解压后超过 `max_sql_bytes` 或不是 UTF-8 编码时返回错误。
+pub fn decode_upload(data: &[u8], max_sql_bytes: usize) -> Result<String> {
+    let mut bytes = Vec::new();
+    if data.starts_with(&GZIP_MAGIC) {
+        GzDecoder::new(data)
+            .take(max_sql_bytes as u64 + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|e| anyhow::anyhow!("SQL 文件无效: gzip 解压失败: {}", e))?;
+    } else {
+        bytes.extend_from_slice(data);
+    }
+
+    if bytes.len() > max_sql_bytes {
+        return Err(anyhow::anyhow!(
+            "SQL 文件无效: 解压后超过 {} MB 的大小限制",
+            max_sql_bytes / 1024 / 1024
+        ));
+    }
+
+    let sql =
+        String::from_utf8(bytes).map_err(|_| anyhow::anyhow!("SQL 文件无效: 不是 UTF-8 编码"))?;
+    Ok(sql.trim_start_matches('\u{feff}').to_string())
+}
+
+/// 按 MySQL 客户端的规则将 SQL 文本拆分为单条语句
+///
+/// - 识别单引号、双引号、反引号中的分隔符和反斜杠转义
+/// - 去掉 `--`、`#` 和 `/* */` 注释，保留 `/*! */` 条件注释和 `/*+ */` 优化器提示
+/// - 支持 `DELIMITER` 指令，用于导入存储过程和触发器
+pub fn split_statements(sql: &str) -> Vec<SqlStatement> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut statements = Vec::new();
+    let mut delimiter: Vec<char> = vec![';'];
+    let mut current = String::new();
+    // 当前语句第一个非注释字符所在的行，为 None 时语句中还没有内容
+    let mut start_line: Option<usize> = None;
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+        let at_line_start = i == 0 || chars[i - 1] == '\n';
+
+        // DELIMITER 是客户端指令，只能单独占一行且位于语句之间
+        if at_line_start && start_line.is_none() {
+            let end = chars[i..]
+                .iter()
+                .position(|&c| c == '\n')
+                .map_or(chars.len(), |offset| i + offset);
+            let text: String = chars[i..end].iter().collect();
+            let mut parts = text.split_whitespace();
+            if let (Some(keyword), Some(value)) = (parts.next(), parts.next())
+                && keyword.eq_ignore_ascii_case("DELIMITER")
+            {
+                delimiter = value.chars().collect();
+                current.clear();
+                i = end;
+                continue;
+            }
+        }
+
+        if ch == '\n' {
+            line += 1;
+        }
+
+        // 语句结束
+        if chars[i..].starts_with(&delimiter) {
+            if let Some(start) = start_line {
+                statements.push(SqlStatement {
+                    line: start,
+                    sql: current.trim().to_string(),
+                });
+            }
+            current.clear();
+            start_line = None;
+            i += delimiter.len();
+            continue;
+        }
+
+        match ch {
+            '\'' | '"' | '`' => {
+                if start_line.is_none() {
+                    start_line = Some(line);
+                }
+                let end = quoted_end(&chars, i);
+                for &c in &chars[i..end] {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    current.push(c);
+                }
+                i = end;
+                continue;
+            }
+            '#' => {
+                i = line_end(&chars, i);
+                continue;
+            }
+            '-' if chars.get(i + 1) == Some(&'-')
+                && chars.get(i + 2).is_none_or(|c| c.is_whitespace()) =>
+            {
+                i = line_end(&chars, i);
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let end = chars[i + 2..]
+                    .windows(2)
+                    .position(|w| w == ['*', '/'])
+                    .map_or(chars.len(), |offset| i + 2 + offset + 2);
+                let keep = matches!(chars.get(i + 2), Some('!') | Some('+'));
+                if keep && start_line.is_none() {
+                    start_line = Some(line);
+                }
+                for &c in &chars[i..end] {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    if keep {
+                        current.push(c);
+                    }
+                }
+                if !keep {
+                    current.push(' ');
+                }
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+
+        if !ch.is_whitespace() && start_line.is_none() {
+            start_line = Some(line);
+        }
+        if start_line.is_some() {
+            current.push(ch);
+        }
+        i += 1;
+    }
+
+    if let Some(start) = start_line {
+        statements.push(SqlStatement {
+            line: start,
+            sql: current.trim().to_string(),
+        });
+    }
+    statements
+}
+
+/// 引号字符串的结束位置（不含），单引号和双引号中的反斜杠转义下一个字符
+fn quoted_end(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if quote != '`' => i += 2,
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// 注释所在行的结束位置，换行符留给主循环计数
+fn line_end(chars: &[char], start: usize) -> usize {
+    chars[start..]
+        .iter()
+        .position(|&c| c == '\n')
+        .map_or(chars.len(), |offset| start + offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    #[test]
+    fn test_split_statements_handles_quotes_and_comments() {
+        let sql = "-- 创建表\nCREATE TABLE t (id INT, name VARCHAR(20)); # 注释\n\nINSERT INTO t VALUES (1, 'a;b'), (2, 'it\\'s -- not a comment');\n/* 块注释; */\n/*!40101 SET NAMES utf8mb4 */;\nSELECT `weird;name` FROM t";
+        let statements = split_statements(sql);
+
+        assert_eq!(
+            statements,
+            vec![
+                SqlStatement {
+                    line: 2,
+                    sql: "CREATE TABLE t (id INT, name VARCHAR(20))".to_string(),
+                },
+                SqlStatement {
+                    line: 4,
+                    sql: "INSERT INTO t VALUES (1, 'a;b'), (2, 'it\\'s -- not a comment')"
+                        .to_string(),
+                },
+                SqlStatement {
+                    line: 6,
+                    sql: "/*!40101 SET NAMES utf8mb4 */".to_string(),
+                },
+                SqlStatement {
+                    line: 7,
+                    sql: "SELECT `weird;name` FROM t".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_statements_supports_delimiter() {
+        let sql = "DELIMITER $$\nCREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\n  SELECT 2;\nEND$$\nDELIMITER ;\nCALL p();\n";
+        let statements = split_statements(sql);
+
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            statements[0].sql,
+            "CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\n  SELECT 2;\nEND"
+        );
+        assert_eq!(statements[0].line, 2);
+        assert_eq!(statements[1].sql, "CALL p()");
+        assert_eq!(statements[1].line, 8);
+    }
+
+    #[test]
+    fn test_decode_upload_accepts_gzip_and_enforces_limit() {
+        let sql = "SELECT 1;\n".repeat(100);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(sql.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(decode_upload(&compressed, 1024 * 1024).unwrap(), sql);
+        assert_eq!(decode_upload(sql.as_bytes(), 1024 * 1024).unwrap(), sql);
+        assert!(decode_upload(&compressed, 100).is_err());
+        assert!(decode_upload(&[0xff, 0xfe, 0x00], 1024).is_err());
+    }
+}
@@ -1,13 +1,138 @@
-use crate::config::{AppConfig, MySQLConfig};
-use crate::models::{Applicant, DatabaseCredentials};
+pub mod dump;
+#[cfg(test)]
+pub mod fake;
+pub mod grader;
+pub mod import;
+pub mod mysql;
+pub mod postgres;
+pub mod provisioner;
+pub mod similarity;
+
+pub use dump::DatabaseDump;
+pub use mysql::MySqlProvisioner;
+pub use postgres::PostgresProvisioner;
+pub use provisioner::Provisioner;
+
+use crate::config::{
+    AppConfig, ApplicationConfig, DatabaseQuotaConfig, ImportConfig, LifecycleConfig, NamingConfig,
+    PrivilegeConfig, ResetConfig, StorageConfig,
+};
+use crate::models::{
+    Applicant, ApplicantHistoryEntry, Assignment, AssignmentCheck, BaselineInfo, CheckResult,
+    Cohort, CohortArchiveResult, DatabaseCredentials, DatabaseEngine, DatabaseSummary, ExpiryInfo,
+    GradeEntry, GradingResult, ImportReport, ImportStatementError, LifecycleResult,
+    PrivilegeProfile, ResetResult, ResourceLimits, SaveCohortRequest, SchemaTemplate,
+    SchemaTemplateSummary, SimilarityReport, StorageCheckResult, StudentDatabase,
+    StudentDatabaseList, Teacher, TeacherAccessResult, TeamInfo, TeamMember,
+    UpdateResourceLimitsRequest,
+};
 use anyhow::Result;
 use log::{error, info, warn};
-use sqlx::{MySql, Pool, Row, Sqlite};
+use provisioner::{DANGEROUS_PRIVILEGES, DATABASE_PRIVILEGES, QUOTA_RESTRICTED_PRIVILEGES};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+/// 数据库名最大长度（MySQL 为 64，PostgreSQL 为 63，取较小值）
+const MAX_DATABASE_NAME_LEN: usize = 63;
+/// 用户名最大长度（MySQL 限制为 32）
+const MAX_USERNAME_LEN: usize = 32;
+/// 映射名称中哈希后缀的长度（十六进制字符数）
+const NAME_HASH_LEN: usize = 10;
+/// 重置码长度
+const RESET_CODE_LEN: usize = 8;
+/// 重置码有效期（分钟）
+const RESET_CODE_TTL_MINUTES: i64 = 30;
+/// 重置码允许的最大错误尝试次数，超过后作废
+const MAX_RESET_CODE_ATTEMPTS: i64 = 5;
+/// 导出时缓存在内存中的 SQL 片段数，客户端读取慢时后端会等待
+const DUMP_CHANNEL_CAPACITY: usize = 16;
+/// 同时进行的导出数上限，每个导出占用一个数据库连接
+const MAX_CONCURRENT_DUMPS: usize = 4;
+/// 团队名称最大长度
+const MAX_TEAM_NAME_LEN: usize = 32;
+/// 团队数据库名和成员用户名中身份标识部分的开头
+///
+/// 学号只能以字母或数字开头，映射后的身份标识部分也以字母或数字开头，
+/// 以下划线开头的团队名称在任何命名模板下都不会与学生的名称重合。
+const TEAM_NAME_MARKER: &str = "_team_";
+/// 命名数据库后缀的最大长度
+const MAX_DATABASE_SUFFIX_LEN: usize = 16;
+/// 单独删除命名数据库时记录的删除原因，恢复用户时不恢复这些数据库
+const NAMED_DATABASE_DELETION_REASON: &str = "单独删除命名数据库";
+/// 教师账号数据库用户名的前缀
+const TEACHER_USERNAME_PREFIX: &str = "teacher";
+/// 教师在任课班级学生数据库上的权限
+const TEACHER_PRIVILEGES: &[&str] = &["SELECT", "SHOW VIEW"];
+/// 评分临时用户名的前缀，后接随机字符
+const GRADER_USERNAME_PREFIX: &str = "grader_";
+/// 评分临时用户名中随机部分的长度
+const GRADER_USERNAME_RANDOM_LEN: usize = 12;
+/// 评分临时用户在学生数据库上的权限
+const GRADER_PRIVILEGES: &[&str] = &["SELECT"];
+
+/// 申请过程中已创建的资源，失败时据此回滚
+#[derive(Debug, Default)]
+struct CreatedResources {
+    database: bool,
+    user: bool,
+    role: Option<String>,
+}
+
+/// 申请被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApplicationRejection {
+    /// 尚未开放申请，附开放时间
+    NotOpenYet(String),
+    /// 申请已截止，附截止时间
+    Closed(String),
+    /// 数据库数量已达上限，附说明
+    CapacityReached(String),
+}
+
+impl std::fmt::Display for ApplicationRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotOpenYet(opens_at) => write!(f, "尚未开放申请，开放时间 {}", opens_at),
+            Self::Closed(closes_at) => write!(f, "申请已截止，截止时间 {}", closes_at),
+            Self::CapacityReached(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ApplicationRejection {}
 
 pub struct DatabaseManager {
     sqlite_pool: Pool<Sqlite>,
-    mysql_pool: Pool<MySql>,
-    mysql_config: MySQLConfig,
+    /// 已配置的供应后端，按引擎索引
+    provisioners: HashMap<DatabaseEngine, Arc<dyn Provisioner>>,
+    /// 白名单记录未指定引擎时使用的默认引擎
+    default_engine: DatabaseEngine,
+    /// 数据库名和用户名的命名规则
+    naming: NamingConfig,
+    /// 权限配置档，管理员可以在运行时修改
+    privileges: std::sync::RwLock<PrivilegeConfig>,
+    /// 通过角色授予配置档权限的引擎（启用角色且后端支持）
+    role_engines: HashSet<DatabaseEngine>,
+    /// 新建用户的默认资源限制
+    resource_limits: ResourceLimits,
+    /// 存储配额
+    storage: StorageConfig,
+    /// 数据库有效期
+    lifecycle: LifecycleConfig,
+    /// 数据库数量配额
+    quota: DatabaseQuotaConfig,
+    /// 申请窗口与容量
+    application: ApplicationConfig,
+    /// SQL 导入
+    import: ImportConfig,
+    /// 数据库重置
+    reset: ResetConfig,
+    /// 导出名额，限制同时占用的数据库连接数
+    dump_slots: Arc<tokio::sync::Semaphore>,
+    /// 串行化申请窗口和容量检查与数据库的创建，并发请求不会越过上限
+    provision_lock: tokio::sync::Mutex<()>,
 }
 
 impl DatabaseManager {
@@ -40,16 +165,247 @@ impl DatabaseManager {
         // 初始化 SQLite 连接池（优化配置）
         let sqlite_pool = Self::connect_sqlite_with_retry(config, max_retries).await?;
 
-        // 初始化 MySQL 连接池（带重试）
-        let mysql_pool = Self::connect_mysql_with_retry(config, max_retries).await?;
+        let mut provisioners: HashMap<DatabaseEngine, Arc<dyn Provisioner>> = HashMap::new();
+
+        // 初始化 MySQL 供应后端（带重试，可选）
+        if let Some(ref mysql_config) = config.mysql {
+            let mysql = MySqlProvisioner::connect(mysql_config, max_retries).await?;
+            provisioners.insert(DatabaseEngine::MySql, Arc::new(mysql));
+        }
+
+        // 初始化 PostgreSQL 供应后端（可选）
+        if let Some(ref postgres_config) = config.postgres {
+            let postgres = PostgresProvisioner::connect(postgres_config, max_retries).await?;
+            provisioners.insert(DatabaseEngine::Postgres, Arc::new(postgres));
+        }
+
+        let role_engines =
+            Self::detect_role_support(&provisioners, config.privileges.use_roles).await;
+
+        let manager = Self {
+            sqlite_pool,
+            provisioners,
+            default_engine: config.database.default_engine,
+            naming: config.naming.clone(),
+            privileges: std::sync::RwLock::new(config.privileges.clone()),
+            role_engines,
+            resource_limits: config.resource_limits,
+            storage: config.storage.clone(),
+            lifecycle: config.lifecycle.clone(),
+            quota: config.quota.clone(),
+            application: config.application.clone(),
+            import: config.import.clone(),
+            reset: config.reset.clone(),
+            dump_slots: Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_DUMPS)),
+            provision_lock: tokio::sync::Mutex::new(()),
+        };
+
+        // 加载管理员修改过的配置档，配置档的权限有变化时，同步到已有的角色
+        if let Err(e) = manager.load_saved_profiles().await {
+            error!("加载管理员修改的权限配置档失败: {}", e);
+        }
+        if let Err(e) = manager.sync_profile_roles().await {
+            error!("同步权限配置档角色失败: {}", e);
+        }
+
+        Ok(manager)
+    }
+
+    /// 使用指定的供应后端初始化（SQLite 仍按配置连接），该后端的引擎同时作为默认引擎
+    #[cfg(test)]
+    pub async fn with_provisioner(
+        config: &AppConfig,
+        provisioner: Arc<dyn Provisioner>,
+    ) -> Result<Self> {
+        let sqlite_pool = Self::connect_sqlite_with_retry(config, 1).await?;
+        let default_engine = provisioner.engine();
+
+        let mut provisioners = HashMap::new();
+        provisioners.insert(default_engine, provisioner);
+
+        let role_engines =
+            Self::detect_role_support(&provisioners, config.privileges.use_roles).await;
 
         Ok(Self {
             sqlite_pool,
-            mysql_pool,
-            mysql_config: config.mysql.clone(),
+            provisioners,
+            default_engine,
+            naming: config.naming.clone(),
+            privileges: std::sync::RwLock::new(config.privileges.clone()),
+            role_engines,
+            resource_limits: config.resource_limits,
+            storage: config.storage.clone(),
+            lifecycle: config.lifecycle.clone(),
+            quota: config.quota.clone(),
+            application: config.application.clone(),
+            import: config.import.clone(),
+            reset: config.reset.clone(),
+            dump_slots: Arc::new(tokio::sync::Semaphore::new(MAX_CONCURRENT_DUMPS)),
+            provision_lock: tokio::sync::Mutex::new(()),
         })
     }
 
+    /// 检测各后端是否支持角色，不支持或检测失败的后端回退为直接授权
+    async fn detect_role_support(
+        provisioners: &HashMap<DatabaseEngine, Arc<dyn Provisioner>>,
+        use_roles: bool,
+    ) -> HashSet<DatabaseEngine> {
+        let mut role_engines = HashSet::new();
+        if !use_roles {
+            info!("已禁用角色，权限配置档将直接授予用户");
+            return role_engines;
+        }
+
+        for (engine, provisioner) in provisioners {
+            match provisioner.supports_roles().await {
+                Ok(true) => {
+                    info!("{} 后端支持角色，权限配置档将通过角色授予", engine);
+                    role_engines.insert(*engine);
+                }
+                Ok(false) => info!("{} 后端不支持角色，回退为直接授权", engine),
+                Err(e) => warn!("检测 {} 后端角色支持失败，回退为直接授权: {}", engine, e),
+            }
+        }
+        role_engines
+    }
+
+    /// 指定引擎是否通过角色授予配置档权限
+    fn uses_roles(&self, engine: DatabaseEngine) -> bool {
+        self.role_engines.contains(&engine)
+    }
+
+    /// 获取指定引擎的供应后端
+    fn provisioner(&self, engine: DatabaseEngine) -> Result<&Arc<dyn Provisioner>> {
+        self.provisioners
+            .get(&engine)
+            .ok_or_else(|| anyhow::anyhow!("数据库引擎 {} 未配置", engine))
+    }
+
+    /// 解析身份标识应使用的数据库引擎
+    ///
+    /// 优先使用已有申请记录中的引擎，其次是白名单记录中指定的引擎，最后使用部署默认值。
+    async fn resolve_engine(&self, identity_key: &str) -> Result<DatabaseEngine> {
+        let applied: Option<String> = sqlx::query_scalar(
+            "SELECT db_engine FROM applicants WHERE identity_key = ? AND status != 'failed' ORDER BY id DESC LIMIT 1",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+
+        if let Some(engine) = applied {
+            return engine.parse();
+        }
+
+        let assigned: Option<Option<String>> =
+            sqlx::query_scalar("SELECT db_engine FROM student_ids WHERE student_id = ?")
+                .bind(identity_key)
+                .fetch_optional(&self.sqlite_pool)
+                .await?;
+
+        match assigned.flatten() {
+            Some(engine) if !engine.is_empty() => engine.parse(),
+            _ => Ok(self.default_engine),
+        }
+    }
+
+    /// 检查指定的引擎是否已配置，未指定时视为使用默认引擎
+    fn ensure_engine_configured(&self, engine: Option<DatabaseEngine>) -> Result<()> {
+        match engine {
+            Some(engine) => self.provisioner(engine).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// 检查指定的权限配置档是否存在
+    fn ensure_profile_exists(&self, profile: Option<&str>) -> Result<()> {
+        match profile {
+            Some(profile) => self.profile_privileges(profile).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// 获取配置档的权限列表
+    ///
+    /// 配置档可能在运行时被修改，返回的权限取自 `DATABASE_PRIVILEGES`，不借用配置。
+    fn profile_privileges(&self, profile: &str) -> Result<Vec<&'static str>> {
+        let privileges = self.privileges.read().unwrap();
+        let granted = privileges
+            .privileges_of(profile)
+            .ok_or_else(|| anyhow::anyhow!("权限配置档 {} 不存在", profile))?;
+        Ok(granted
+            .into_iter()
+            .filter_map(|p| DATABASE_PRIVILEGES.iter().find(|d| **d == p).copied())
+            .collect())
+    }
+
+    /// 未指定配置档时使用的默认配置档
+    fn default_profile(&self) -> String {
+        self.privileges.read().unwrap().default_profile.clone()
+    }
+
+    /// 解析身份标识应使用的权限配置档
+    ///
+    /// 优先使用白名单记录中指定的配置档，其次是班级的配置档和教学班的默认配置档，
+    /// 最后使用部署默认值。
+    async fn resolve_profile(&self, identity_key: &str) -> Result<String> {
+        let assigned = sqlx::query_as::<_, (Option<String>, Option<String>, Option<String>)>(
+            r#"
+            SELECT s.privilege_profile, s.class_info, c.default_profile FROM student_ids s
+            LEFT JOIN cohorts c ON c.id = s.cohort_id
+            WHERE s.student_id = ?
+            "#,
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+
+        let Some((profile, class_info, cohort_profile)) = assigned else {
+            return Ok(self.default_profile());
+        };
+
+        if let Some(profile) = profile.filter(|p| !p.is_empty()) {
+            return Ok(profile);
+        }
+
+        if let Some(class_info) = class_info {
+            let class_profile: Option<String> = sqlx::query_scalar(
+                "SELECT privilege_profile FROM class_privilege_profiles WHERE class_info = ?",
+            )
+            .bind(class_info)
+            .fetch_optional(&self.sqlite_pool)
+            .await?;
+            if let Some(profile) = class_profile {
+                return Ok(profile);
+            }
+        }
+
+        Ok(cohort_profile
+            .filter(|p| !p.is_empty())
+            .unwrap_or_else(|| self.default_profile()))
+    }
+
+    /// 解析身份标识的数据库有效期（天），0 表示永不过期
+    ///
+    /// 优先使用班级的设置，其次是教学班的默认有效期，最后使用部署默认值。
+    async fn resolve_lifetime_days(&self, identity_key: &str) -> Result<u32> {
+        let lifetime: Option<Option<i64>> = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(p.lifetime_days, c.default_lifetime_days) FROM student_ids s
+            LEFT JOIN class_expiry_policies p ON p.class_info = s.class_info
+            LEFT JOIN cohorts c ON c.id = s.cohort_id
+            WHERE s.student_id = ?
+            "#,
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+
+        Ok(lifetime
+            .flatten()
+            .map(|days| days.max(0) as u32)
+            .unwrap_or(self.lifecycle.default_lifetime_days))
+    }
+
     /// SQLite 连接重试
     async fn connect_sqlite_with_retry(
         config: &AppConfig,
@@ -78,9 +434,26 @@ impl DatabaseManager {
                         r#"
                         CREATE TABLE IF NOT EXISTS applicants (
                             id INTEGER PRIMARY KEY AUTOINCREMENT,
-                            identity_key TEXT UNIQUE NOT NULL,
+                            identity_key TEXT NOT NULL,
                             db_name TEXT NOT NULL,
+                            db_suffix TEXT,
                             db_user TEXT NOT NULL,
+                            db_engine TEXT NOT NULL DEFAULT 'mysql',
+                            privilege_profile TEXT,
+                            privilege_role TEXT,
+                            max_user_connections INTEGER,
+                            max_queries_per_hour INTEGER,
+                            max_updates_per_hour INTEGER,
+                            storage_bytes INTEGER,
+                            storage_checked_at DATETIME,
+                            quota_exceeded BOOLEAN NOT NULL DEFAULT FALSE,
+                            expires_at DATETIME,
+                            locked_at DATETIME,
+                            archived_at DATETIME,
+                            archive_name TEXT,
+                            suspended_at DATETIME,
+                            suspension_reason TEXT,
+                            schema_template TEXT,
                             status TEXT NOT NULL DEFAULT 'success',
                             failure_reason TEXT,
                             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
@@ -102,6 +475,118 @@ impl DatabaseManager {
                             class_info TEXT,
                             has_applied BOOLEAN NOT NULL DEFAULT FALSE,
                             applied_db_name TEXT,
+                            db_engine TEXT,
+                            privilege_profile TEXT,
+                            schema_template TEXT,
+                            max_databases INTEGER,
+                            cohort_id INTEGER,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建教学班表，白名单记录通过 cohort_id 关联
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS cohorts (
+                            id INTEGER PRIMARY KEY AUTOINCREMENT,
+                            name TEXT UNIQUE NOT NULL,
+                            course TEXT NOT NULL,
+                            term TEXT NOT NULL,
+                            teacher TEXT,
+                            opens_at DATETIME,
+                            closes_at DATETIME,
+                            default_profile TEXT,
+                            default_lifetime_days INTEGER,
+                            max_databases INTEGER,
+                            archived_at DATETIME,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建班级权限配置档表
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS class_privilege_profiles (
+                            class_info TEXT PRIMARY KEY,
+                            privilege_profile TEXT NOT NULL,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建班级数据库有效期表
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS class_expiry_policies (
+                            class_info TEXT PRIMARY KEY,
+                            lifetime_days INTEGER NOT NULL,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建结构模板表，模板脚本在创建数据库后以管理员连接执行
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS schema_templates (
+                            name TEXT PRIMARY KEY,
+                            description TEXT,
+                            script TEXT NOT NULL,
+                            statement_count INTEGER NOT NULL,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建班级结构模板表
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS class_schema_templates (
+                            class_info TEXT PRIMARY KEY,
+                            template_name TEXT NOT NULL,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建数据库检查点表，重置数据库时恢复到管理员保存的状态
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS database_baselines (
+                            identity_key TEXT PRIMARY KEY,
+                            script TEXT NOT NULL,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建作业表，检查项以 JSON 保存
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS assignments (
+                            name TEXT PRIMARY KEY,
+                            title TEXT NOT NULL,
+                            description TEXT,
+                            checks TEXT NOT NULL,
                             created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
                             updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
                         )
@@ -110,6 +595,160 @@ impl DatabaseManager {
                     .execute(&pool)
                     .await?;
 
+                    // 创建作业成绩表，每个学生每个作业保留最近一次评分
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS assignment_results (
+                            assignment_name TEXT NOT NULL,
+                            identity_key TEXT NOT NULL,
+                            score INTEGER NOT NULL,
+                            max_score INTEGER NOT NULL,
+                            details TEXT NOT NULL,
+                            graded_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                            PRIMARY KEY (assignment_name, identity_key)
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建教师账号表，每个教师对应各引擎上的一个数据库用户
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS teachers (
+                            name TEXT PRIMARY KEY,
+                            display_name TEXT,
+                            db_user TEXT NOT NULL UNIQUE,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建教师任课班级表
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS teacher_classes (
+                            teacher TEXT NOT NULL,
+                            class_info TEXT NOT NULL,
+                            PRIMARY KEY (teacher, class_info)
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建团队数据库表，多名成员共用一个数据库
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS teams (
+                            name TEXT PRIMARY KEY,
+                            db_name TEXT NOT NULL UNIQUE,
+                            db_engine TEXT NOT NULL,
+                            privilege_profile TEXT NOT NULL,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建团队成员表，每个成员在团队数据库上有独立的用户
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS team_members (
+                            team TEXT NOT NULL,
+                            identity_key TEXT NOT NULL,
+                            db_user TEXT NOT NULL UNIQUE,
+                            privilege_role TEXT,
+                            joined_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                            PRIMARY KEY (team, identity_key)
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建结构相似度报告表，相似的学生对以 JSON 保存
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS similarity_reports (
+                            id INTEGER PRIMARY KEY AUTOINCREMENT,
+                            class_info TEXT,
+                            include_rows BOOLEAN NOT NULL DEFAULT FALSE,
+                            threshold REAL NOT NULL,
+                            student_count INTEGER NOT NULL,
+                            pairs TEXT NOT NULL,
+                            errors TEXT NOT NULL,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建管理员保存的权限配置档表
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS saved_privilege_profiles (
+                            profile TEXT PRIMARY KEY,
+                            privileges TEXT NOT NULL,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 记录角色最近一次同步时各配置档的权限，用于检测配置档变化
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS privilege_profile_definitions (
+                            profile TEXT PRIMARY KEY,
+                            privileges TEXT NOT NULL,
+                            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建申请记录历史表
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS applicant_history (
+                            id INTEGER PRIMARY KEY AUTOINCREMENT,
+                            identity_key TEXT NOT NULL,
+                            event TEXT NOT NULL,
+                            detail TEXT,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+                    sqlx::query(
+                        "CREATE INDEX IF NOT EXISTS idx_applicant_history_identity ON applicant_history (identity_key)",
+                    )
+                    .execute(&pool)
+                    .await?;
+
+                    // 创建密码重置码表（只保存重置码的哈希）
+                    sqlx::query(
+                        r#"
+                        CREATE TABLE IF NOT EXISTS credential_reset_codes (
+                            identity_key TEXT PRIMARY KEY,
+                            code_hash TEXT NOT NULL,
+                            attempts INTEGER NOT NULL DEFAULT 0,
+                            expires_at DATETIME NOT NULL,
+                            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                        )
+                        "#,
+                    )
+                    .execute(&pool)
+                    .await?;
+
                     // 为现有记录添加新字段 (如果表已存在)
                     let _ = sqlx::query(
                         "ALTER TABLE applicants ADD COLUMN status TEXT DEFAULT 'success'",
@@ -125,26 +764,108 @@ impl DatabaseManager {
                     let _ = sqlx::query("ALTER TABLE applicants ADD COLUMN deletion_reason TEXT")
                         .execute(&pool)
                         .await;
-
-                    // 迁移：如果存在 username 列，将其重命名为 db_user
-                    // SQLite 不支持直接重命名列，所以我们需要检查列是否存在
-                    let column_check = sqlx::query("PRAGMA table_info(applicants)")
-                        .fetch_all(&pool)
+                    let _ = sqlx::query(
+                        "ALTER TABLE applicants ADD COLUMN db_engine TEXT NOT NULL DEFAULT 'mysql'",
+                    )
+                    .execute(&pool)
+                    .await;
+                    let _ = sqlx::query("ALTER TABLE student_ids ADD COLUMN db_engine TEXT")
+                        .execute(&pool)
                         .await;
-
-                    if let Ok(columns) = column_check {
-                        let has_username = columns.iter().any(|row| {
-                            if let Ok(name) = row.try_get::<String, _>("name") {
-                                name == "username"
-                            } else {
-                                false
-                            }
-                        });
-
-                        let has_db_user = columns.iter().any(|row| {
-                            if let Ok(name) = row.try_get::<String, _>("name") {
-                                name == "db_user"
-                            } else {
+                    let _ = sqlx::query("ALTER TABLE applicants ADD COLUMN privilege_profile TEXT")
+                        .execute(&pool)
+                        .await;
+                    let _ =
+                        sqlx::query("ALTER TABLE student_ids ADD COLUMN privilege_profile TEXT")
+                            .execute(&pool)
+                            .await;
+                    let _ = sqlx::query("ALTER TABLE applicants ADD COLUMN privilege_role TEXT")
+                        .execute(&pool)
+                        .await;
+                    // 资源限制为空表示使用默认值
+                    for column in [
+                        "max_user_connections",
+                        "max_queries_per_hour",
+                        "max_updates_per_hour",
+                    ] {
+                        let _ = sqlx::query(&format!(
+                            "ALTER TABLE applicants ADD COLUMN {} INTEGER",
+                            column
+                        ))
+                        .execute(&pool)
+                        .await;
+                    }
+                    let _ = sqlx::query("ALTER TABLE applicants ADD COLUMN storage_bytes INTEGER")
+                        .execute(&pool)
+                        .await;
+                    let _ = sqlx::query(
+                        "ALTER TABLE applicants ADD COLUMN storage_checked_at DATETIME",
+                    )
+                    .execute(&pool)
+                    .await;
+                    let _ = sqlx::query(
+                        "ALTER TABLE applicants ADD COLUMN quota_exceeded BOOLEAN NOT NULL DEFAULT FALSE",
+                    )
+                    .execute(&pool)
+                    .await;
+                    for column in ["expires_at", "locked_at", "archived_at", "suspended_at"] {
+                        let _ = sqlx::query(&format!(
+                            "ALTER TABLE applicants ADD COLUMN {} DATETIME",
+                            column
+                        ))
+                        .execute(&pool)
+                        .await;
+                    }
+                    let _ = sqlx::query("ALTER TABLE student_ids ADD COLUMN schema_template TEXT")
+                        .execute(&pool)
+                        .await;
+                    for column in ["max_databases", "cohort_id"] {
+                        let _ = sqlx::query(&format!(
+                            "ALTER TABLE student_ids ADD COLUMN {} INTEGER",
+                            column
+                        ))
+                        .execute(&pool)
+                        .await;
+                    }
+                    let _ = sqlx::query("ALTER TABLE cohorts ADD COLUMN max_databases INTEGER")
+                        .execute(&pool)
+                        .await;
+                    let _ = sqlx::query("ALTER TABLE team_members ADD COLUMN privilege_role TEXT")
+                        .execute(&pool)
+                        .await;
+                    for column in [
+                        "archive_name",
+                        "suspension_reason",
+                        "schema_template",
+                        "db_suffix",
+                    ] {
+                        let _ = sqlx::query(&format!(
+                            "ALTER TABLE applicants ADD COLUMN {} TEXT",
+                            column
+                        ))
+                        .execute(&pool)
+                        .await;
+                    }
+
+                    // 迁移：如果存在 username 列，将其重命名为 db_user
+                    // SQLite 不支持直接重命名列，所以我们需要检查列是否存在
+                    let column_check = sqlx::query("PRAGMA table_info(applicants)")
+                        .fetch_all(&pool)
+                        .await;
+
+                    if let Ok(columns) = column_check {
+                        let has_username = columns.iter().any(|row| {
+                            if let Ok(name) = row.try_get::<String, _>("name") {
+                                name == "username"
+                            } else {
+                                false
+                            }
+                        });
+
+                        let has_db_user = columns.iter().any(|row| {
+                            if let Ok(name) = row.try_get::<String, _>("name") {
+                                name == "db_user"
+                            } else {
                                 false
                             }
                         });
@@ -158,9 +879,26 @@ impl DatabaseManager {
                                 r#"
                                 CREATE TABLE applicants_new (
                                     id INTEGER PRIMARY KEY AUTOINCREMENT,
-                                    identity_key TEXT UNIQUE NOT NULL,
+                                    identity_key TEXT NOT NULL,
                                     db_name TEXT NOT NULL,
+                                    db_suffix TEXT,
                                     db_user TEXT NOT NULL,
+                                    db_engine TEXT NOT NULL DEFAULT 'mysql',
+                                    privilege_profile TEXT,
+                                    privilege_role TEXT,
+                                    max_user_connections INTEGER,
+                                    max_queries_per_hour INTEGER,
+                                    max_updates_per_hour INTEGER,
+                                    storage_bytes INTEGER,
+                                    storage_checked_at DATETIME,
+                                    quota_exceeded BOOLEAN NOT NULL DEFAULT FALSE,
+                                    expires_at DATETIME,
+                                    locked_at DATETIME,
+                                    archived_at DATETIME,
+                                    archive_name TEXT,
+                                    suspended_at DATETIME,
+                                    suspension_reason TEXT,
+                                    schema_template TEXT,
                                     status TEXT NOT NULL DEFAULT 'success',
                                     failure_reason TEXT,
                                     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
@@ -191,6 +929,28 @@ impl DatabaseManager {
                         }
                     }
 
+                    // 迁移：去掉表上的唯一约束，改为只约束有效记录的唯一索引，
+                    // 失败、已删除和已归档的记录可以与重新申请的记录同名。
+                    // SQLite 不支持修改约束，需要重建表
+                    let applicants_sql: Option<String> = sqlx::query_scalar(
+                        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'applicants'",
+                    )
+                    .fetch_optional(&pool)
+                    .await?;
+                    if applicants_sql.is_some_and(|sql| {
+                        sql.contains("identity_key TEXT UNIQUE")
+                            || sql.contains("UNIQUE (identity_key, db_name)")
+                    }) {
+                        info!("迁移 applicants 表的唯一约束...");
+                        Self::rebuild_applicants_table(&pool).await?;
+                        info!("applicants 表迁移完成");
+                    }
+                    sqlx::query(
+                        "CREATE UNIQUE INDEX IF NOT EXISTS idx_applicants_live_db ON applicants (identity_key, db_name) WHERE status IN ('success', 'suspended', 'expired')",
+                    )
+                    .execute(&pool)
+                    .await?;
+
                     return Ok(pool);
                 }
                 Err(e) => {
@@ -209,55 +969,67 @@ impl DatabaseManager {
         Err(last_error.unwrap().into())
     }
 
-    /// MySQL 连接重试
-    async fn connect_mysql_with_retry(config: &AppConfig, max_retries: u32) -> Result<Pool<MySql>> {
-        let mysql_url = format!(
-            "mysql://{}:{}@{}:{}/{}?ssl-mode=disabled&allowPublicKeyRetrieval=true",
-            config.mysql.username,
-            config.mysql.password,
-            config.mysql.host,
-            config.mysql.port,
-            config.mysql.database
-        );
-
-        let mut last_error = None;
-
-        for attempt in 1..=max_retries {
-            info!("尝试连接 MySQL 数据库 (第 {} 次)", attempt);
-
-            match sqlx::mysql::MySqlPoolOptions::new()
-                .max_connections(10) // 增加最大连接数
-                .min_connections(1) // 设置最小连接数
-                .acquire_timeout(std::time::Duration::from_secs(15)) // 获取连接超时
-                .idle_timeout(std::time::Duration::from_secs(600)) // 空闲连接超时
-                .max_lifetime(std::time::Duration::from_secs(3600)) // 连接最大生命周期
-                .test_before_acquire(true) // 获取前测试连接
-                .connect(&mysql_url)
-                .await
-            {
-                Ok(pool) => {
-                    info!("MySQL 连接成功");
-                    return Ok(pool);
-                }
-                Err(e) => {
-                    error!("MySQL 连接失败 (第 {} 次): {}", attempt, e);
-                    last_error = Some(e);
-
-                    if attempt < max_retries {
-                        let delay = std::time::Duration::from_secs(2_u64.pow(attempt - 1)); // 指数退避
-                        warn!("等待 {:?} 后重试...", delay);
-                        tokio::time::sleep(delay).await;
-                    }
-                }
-            }
-        }
+    /// 按当前结构重建 applicants 表并复制所有记录
+    async fn rebuild_applicants_table(pool: &Pool<Sqlite>) -> Result<()> {
+        const COLUMNS: &str = "id, identity_key, db_name, db_suffix, db_user, db_engine, privilege_profile, privilege_role, \
+            max_user_connections, max_queries_per_hour, max_updates_per_hour, storage_bytes, storage_checked_at, \
+            quota_exceeded, expires_at, locked_at, archived_at, archive_name, suspended_at, suspension_reason, \
+            schema_template, status, failure_reason, created_at, deleted_at, deletion_reason";
 
-        Err(last_error.unwrap().into())
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            r#"
+            CREATE TABLE applicants_new (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                identity_key TEXT NOT NULL,
+                db_name TEXT NOT NULL,
+                db_suffix TEXT,
+                db_user TEXT NOT NULL,
+                db_engine TEXT NOT NULL DEFAULT 'mysql',
+                privilege_profile TEXT,
+                privilege_role TEXT,
+                max_user_connections INTEGER,
+                max_queries_per_hour INTEGER,
+                max_updates_per_hour INTEGER,
+                storage_bytes INTEGER,
+                storage_checked_at DATETIME,
+                quota_exceeded BOOLEAN NOT NULL DEFAULT FALSE,
+                expires_at DATETIME,
+                locked_at DATETIME,
+                archived_at DATETIME,
+                archive_name TEXT,
+                suspended_at DATETIME,
+                suspension_reason TEXT,
+                schema_template TEXT,
+                status TEXT NOT NULL DEFAULT 'success',
+                failure_reason TEXT,
+                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                deleted_at DATETIME,
+                deletion_reason TEXT
+            )
+            "#,
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!(
+            "INSERT INTO applicants_new ({0}) SELECT {0} FROM applicants",
+            COLUMNS
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DROP TABLE applicants")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("ALTER TABLE applicants_new RENAME TO applicants")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
     }
 
-    // 检查身份标识是否已存在（排除已删除的记录）
+    // 检查身份标识是否已存在（排除已删除和失败的记录）
     pub async fn check_identity_exists(&self, identity_key: &str) -> Result<bool> {
-        let row = sqlx::query("SELECT COUNT(*) as count FROM applicants WHERE identity_key = ? AND status != 'deleted'")
+        let row = sqlx::query("SELECT COUNT(*) as count FROM applicants WHERE identity_key = ? AND status NOT IN ('deleted', 'failed')")
             .bind(identity_key)
             .fetch_one(&self.sqlite_pool)
             .await?;
@@ -267,18 +1039,27 @@ impl DatabaseManager {
     }
 
     // 创建新的申请记录
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_applicant(
         &self,
         identity_key: &str,
         db_name: &str,
         db_user: &str,
+        db_engine: DatabaseEngine,
+        privilege_profile: &str,
+        privilege_role: Option<&str>,
+        schema_template: Option<&str>,
     ) -> Result<()> {
         sqlx::query(
-            "INSERT INTO applicants (identity_key, db_name, db_user, status) VALUES (?, ?, ?, 'success')"
+            "INSERT INTO applicants (identity_key, db_name, db_user, db_engine, privilege_profile, privilege_role, schema_template, status) VALUES (?, ?, ?, ?, ?, ?, ?, 'success')"
         )
         .bind(identity_key)
         .bind(db_name)
         .bind(db_user)
+        .bind(db_engine.as_str())
+        .bind(privilege_profile)
+        .bind(privilege_role)
+        .bind(schema_template)
         .execute(&self.sqlite_pool)
         .await?;
 
@@ -298,7 +1079,7 @@ impl DatabaseManager {
         Ok(())
     }
 
-    // 在 MySQL 中创建数据库和用户
+    // 在目标数据库服务器中创建数据库和用户
     pub async fn provision_database(
         &self,
         identity_key: &str,
@@ -310,140 +1091,137 @@ impl DatabaseManager {
             return Err(anyhow::anyhow!("学号不在允许列表中或已申请过数据库"));
         }
 
-        let db_name = format!("db_{}", identity_key);
-        let username = format!("user_{}", identity_key);
-
-        info!("开始为身份标识 {} 创建数据库和用户", identity_key);
-        info!("数据库名: {}, 用户名: {}", db_name, username);
-
-        // 从配置获取允许的主机，默认为 localhost 以确保安全
-        let allowed_host = self
-            .mysql_config
-            .allowed_host
-            .as_deref()
-            .unwrap_or("localhost");
-        info!("允许的主机: {}", allowed_host);
+        let db_name = Self::database_name_for(&self.naming, identity_key);
+        let username = Self::username_for(&self.naming, identity_key);
 
-        // 验证主机不是通配符 % (严禁在生产环境使用)
-        let is_dev_mode = std::env::var("DEV_MODE").unwrap_or_default() == "true";
-        if allowed_host == "%" && !is_dev_mode {
-            error!("安全违规: 生产环境禁止使用通配符主机 '%'");
-            return Err(anyhow::anyhow!("安全错误: 不允许使用通配符主机 '%'"));
+        if self
+            .is_name_taken(identity_key, &db_name, &username)
+            .await?
+        {
+            error!(
+                "数据库名 {} 或用户名 {} 已被其他身份标识占用",
+                db_name, username
+            );
+            return Err(anyhow::anyhow!("数据库名或用户名已被占用"));
         }
 
-        if allowed_host == "%" && is_dev_mode {
-            warn!("⚠️  开发模式: 使用通配符主机 '%' - 仅用于开发环境！");
-        }
+        let engine = self.resolve_engine(identity_key).await?;
+        let provisioner = self.provisioner(engine)?;
+        let profile = self.resolve_profile(identity_key).await?;
+        let privileges = self.profile_privileges(&profile)?;
 
-        // 创建数据库 - 使用参数化查询防止SQL注入
-        // 注意：MySQL不支持数据库名的参数化，但我们验证输入格式
+        // 数据库名和用户名无法参数化，创建任何资源之前先做格式验证
         if !Self::is_valid_identifier(&db_name) {
             error!("无效的数据库名格式: {}", db_name);
             return Err(anyhow::anyhow!("Invalid database name format"));
         }
-
-        info!("步骤 1: 创建数据库 {}", db_name);
-        // 使用更安全的方式创建数据库，避免SQL注入
-        // 由于MySQL不支持对数据库名进行参数化，我们使用白名单验证
-        let sanitized_db_name = format!("db_{}", identity_key);
-        if !Self::is_valid_database_name(&sanitized_db_name) {
-            error!("数据库名不符合安全规范: {}", sanitized_db_name);
+        if !Self::is_valid_database_name(&db_name) {
+            error!("数据库名不符合安全规范: {}", db_name);
             return Err(anyhow::anyhow!("数据库名不符合安全规范"));
         }
-
-        let create_db_sql = format!("CREATE DATABASE IF NOT EXISTS `{}`", sanitized_db_name);
-        if let Err(e) = sqlx::query(&create_db_sql).execute(&self.mysql_pool).await {
-            error!("创建数据库失败: {}, SQL: {}", e, create_db_sql);
-            return Err(e.into());
-        }
-        info!("数据库 {} 创建成功", sanitized_db_name);
-
-        // 创建用户 - 使用更安全的方式
         if !Self::is_valid_username(&username) {
             error!("无效的用户名格式: {}", username);
             return Err(anyhow::anyhow!("Invalid username format"));
         }
-        if !Self::is_valid_host(allowed_host) {
-            error!("无效的主机格式: {}", allowed_host);
-            return Err(anyhow::anyhow!("Invalid host format"));
-        }
 
-        info!("步骤 2: 创建用户 {}@{}", username, allowed_host);
+        // 已存在的数据库和用户不属于本次申请，不接管，失败时也不会删除
+        if provisioner.database_exists(&db_name).await? {
+            error!("数据库 {} 已存在，拒绝为 {} 创建", db_name, identity_key);
+            return Err(anyhow::anyhow!("数据库 {} 已存在", db_name));
+        }
+        if provisioner.user_exists(&username).await? {
+            error!("用户 {} 已存在，拒绝为 {} 创建", username, identity_key);
+            return Err(anyhow::anyhow!("用户 {} 已存在", username));
+        }
 
-        // 创建用户时使用预处理语句（某些MySQL版本支持）
-        let create_user_sql = format!(
-            "CREATE USER IF NOT EXISTS '{}'@'{}' IDENTIFIED BY '{}'",
-            username,
-            allowed_host,
-            password.replace("'", "''") // 转义单引号
+        info!(
+            "开始为身份标识 {} 创建数据库和用户 (后端: {}, 权限配置档: {})",
+            identity_key, engine, profile
         );
+        info!("数据库名: {}, 用户名: {}", db_name, username);
 
-        if let Err(e) = sqlx::query(&create_user_sql)
-            .execute(&self.mysql_pool)
-            .await
-        {
-            error!(
-                "创建用户失败: {}, SQL: CREATE USER IF NOT EXISTS '{}'@'{}' IDENTIFIED BY '[REDACTED]'",
-                e, username, allowed_host
+        // 记录本次申请实际创建的资源，失败时只回滚这些资源，不影响名称相同的已有资源
+        let mut created = CreatedResources::default();
+        let result = async {
+            info!("步骤 1: 创建数据库 {}", db_name);
+            provisioner.create_database(&db_name).await?;
+            created.database = true;
+            info!("数据库 {} 创建成功", db_name);
+
+            // 结构模板在授权前以管理员连接执行，模板创建的对象不受学生权限配置档限制
+            if let Some(template) = self.resolve_template(identity_key).await? {
+                info!("应用结构模板 {} 到数据库 {}", template, db_name);
+                self.apply_schema_template(provisioner.as_ref(), &db_name, &template)
+                    .await
+                    .inspect_err(|e| error!("应用结构模板 {} 失败: {}", template, e))?;
+                info!("结构模板 {} 应用成功", template);
+            }
+
+            info!("步骤 2: 创建用户 {}", username);
+            provisioner
+                .create_user(&username, password, &self.resource_limits)
+                .await?;
+            created.user = true;
+            info!(
+                "用户 {} 创建成功 (最大连接数: {}, 每小时查询: {}, 每小时更新: {})",
+                username,
+                self.resource_limits.max_user_connections,
+                self.resource_limits.max_queries_per_hour,
+                self.resource_limits.max_updates_per_hour
             );
-            return Err(e.into());
-        }
-        info!("用户 {}@{} 创建成功", username, allowed_host);
 
-        info!("步骤 3: 授予安全权限给用户 {}@{}", username, allowed_host);
-        // 授权 - 严格限制权限，只授予必要的数据库操作权限
-        // 不授予 CREATE, DROP, ALTER 等危险权限，防止用户删除数据库或修改结构
-        let grant_sql = format!(
-            "GRANT SELECT, INSERT, UPDATE, DELETE, INDEX, LOCK TABLES ON `{}`.* TO '{}'@'{}'",
-            db_name, username, allowed_host
-        );
-        if let Err(e) = sqlx::query(&grant_sql).execute(&self.mysql_pool).await {
-            error!("授权失败: {}, SQL: {}", e, grant_sql);
-            return Err(e.into());
-        }
-        info!("权限授予成功: SELECT, INSERT, UPDATE, DELETE, INDEX, LOCK TABLES");
+            info!("步骤 3: 授予安全权限给用户 {}", username);
+            // 授权 - 按权限配置档授权，作用域严格限定在用户自己的数据库
+            if self.uses_roles(engine) {
+                let role = Self::role_name_for(&profile, &db_name);
+                created.role = Some(role.clone());
+                self.set_role_privileges(provisioner.as_ref(), &db_name, &role, &privileges)
+                    .await?;
+                provisioner.grant_role(&role, &username).await?;
+                info!("权限授予成功 (角色 {}): {}", role, privileges.join(", "));
+            } else {
+                provisioner
+                    .grant_privileges(&db_name, &username, &privileges)
+                    .await?;
+                info!("权限授予成功: {}", privileges.join(", "));
+            }
 
-        // 明确拒绝全局权限和危险操作
-        info!("步骤 4: 撤销危险权限");
-        let revoke_dangerous_sql = format!(
-            "REVOKE CREATE, DROP, ALTER, REFERENCES, CREATE TEMPORARY TABLES, EXECUTE, CREATE VIEW, SHOW VIEW, CREATE ROUTINE, ALTER ROUTINE, EVENT, TRIGGER ON *.* FROM '{}'@'{}'",
-            username, allowed_host
-        );
-        // 注意：REVOKE 可能失败如果用户没有这些权限，所以我们忽略错误
-        if let Err(e) = sqlx::query(&revoke_dangerous_sql)
-            .execute(&self.mysql_pool)
-            .await
-        {
-            warn!("撤销危险权限时出现警告 (可忽略): {}", e);
-        } else {
-            info!("危险权限撤销成功");
+            // 明确拒绝全局权限和危险操作
+            info!("步骤 4: 撤销危险权限");
+            // 注意：REVOKE 可能失败如果用户没有这些权限，所以我们忽略错误
+            if let Err(e) = provisioner
+                .revoke_privileges(None, &username, DANGEROUS_PRIVILEGES)
+                .await
+            {
+                warn!("撤销危险权限时出现警告 (可忽略): {}", e);
+            } else {
+                info!("危险权限撤销成功");
+            }
+
+            // 刷新权限
+            info!("步骤 5: 刷新权限");
+            provisioner
+                .flush_privileges()
+                .await
+                .inspect_err(|e| error!("刷新权限失败: {}", e))?;
+            info!("权限刷新成功");
+            Ok::<(), anyhow::Error>(())
         }
+        .await;
 
-        // 刷新权限
-        info!("步骤 5: 刷新权限");
-        if let Err(e) = sqlx::query("FLUSH PRIVILEGES")
-            .execute(&self.mysql_pool)
-            .await
-        {
-            error!("刷新权限失败: {}", e);
-            return Err(e.into());
+        if let Err(e) = result {
+            self.undo_created_resources(provisioner.as_ref(), &db_name, &username, &created)
+                .await;
+            return Err(e);
         }
-        info!("权限刷新成功");
 
         info!("✅ 数据库和用户创建完成！身份标识: {}", identity_key);
         info!("   数据库: {}", db_name);
-        info!("   用户: {}@{}", username, allowed_host);
-        info!("   权限: SELECT, INSERT, UPDATE, DELETE, INDEX, LOCK TABLES (仅限指定数据库)");
-
-        // 生成完整的连接字符串
-        let connection_string = format!(
-            "mysql://{}:{}@{}:{}/{}?allowPublicKeyRetrieval=true&useSSL=false",
-            username, password, self.mysql_config.host, self.mysql_config.port, db_name
-        );
-
-        let jdbc_url = format!(
-            "jdbc:mysql://{}:{}/{}?allowPublicKeyRetrieval=true&useSSL=false&user={}&password={}",
-            self.mysql_config.host, self.mysql_config.port, db_name, username, password
+        info!("   用户: {}", username);
+        info!(
+            "   权限: {} ({}，仅限指定数据库)",
+            privileges.join(", "),
+            profile
         );
 
         // 标记学号已申请数据库
@@ -452,15 +1230,7 @@ impl DatabaseManager {
             // 不影响主流程，只记录警告
         }
 
-        Ok(DatabaseCredentials {
-            db_host: self.mysql_config.host.clone(),
-            db_port: self.mysql_config.port,
-            db_name,
-            username,
-            password: password.to_string(),
-            connection_string,
-            jdbc_url,
-        })
+        Ok(provisioner.credentials(&db_name, &username, password))
     }
 
     // 获取所有申请者列表（管理员功能）
@@ -473,6 +1243,224 @@ impl DatabaseManager {
         Ok(applicants)
     }
 
+    // 命名映射
+
+    /// 身份标识对应的数据库名
+    pub fn database_name_for(naming: &NamingConfig, identity_key: &str) -> String {
+        Self::map_identifier(
+            naming,
+            &naming.database_prefix,
+            identity_key,
+            MAX_DATABASE_NAME_LEN,
+        )
+    }
+
+    /// 身份标识对应的用户名
+    pub fn username_for(naming: &NamingConfig, identity_key: &str) -> String {
+        Self::map_identifier(
+            naming,
+            &naming.username_prefix,
+            identity_key,
+            MAX_USERNAME_LEN,
+        )
+    }
+
+    /// 配置档在指定数据库上对应的角色名
+    ///
+    /// 角色的权限只能作用于具体的数据库，为保证用户之间互相隔离，
+    /// 每个数据库的每个配置档各对应一个角色。角色名包含配置档名称便于辨认，
+    /// 并以配置档和数据库名的哈希作为后缀保证唯一，长度不超过用户名限制。
+    pub fn role_name_for(profile: &str, db_name: &str) -> String {
+        let label_len = MAX_USERNAME_LEN - NAME_HASH_LEN - 3;
+        let label: String = profile
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .take(label_len)
+            .collect();
+
+        let hash = format!(
+            "{:x}",
+            Sha256::digest(format!("{}\0{}", profile, db_name).as_bytes())
+        );
+        format!("r_{}_{}", label, &hash[..NAME_HASH_LEN])
+    }
+
+    /// 用给定的身份标识部分渲染命名模板
+    fn render_name(naming: &NamingConfig, prefix: &str, identity_part: &str) -> String {
+        naming
+            .template
+            .replace("{prefix}", prefix)
+            .replace("{term}", naming.term.as_deref().unwrap_or(""))
+            .replace("{identity}", identity_part)
+    }
+
+    /// 将身份标识确定性地映射为合法的标识符
+    ///
+    /// 仅包含 ASCII 字母、数字和下划线且长度不超限的身份标识直接代入模板，
+    /// 与历史命名保持一致；否则将不安全字符替换为下划线、截断，并追加身份标识的
+    /// SHA-256 前缀作为后缀，保证不同身份标识得到不同的名称。
+    fn map_identifier(
+        naming: &NamingConfig,
+        prefix: &str,
+        identity_key: &str,
+        max_len: usize,
+    ) -> String {
+        Self::map_marked_identifier(naming, prefix, "", identity_key, max_len)
+    }
+
+    /// 与 [`Self::map_identifier`] 相同，身份标识部分以 `marker` 开头，截断时保留 `marker`
+    fn map_marked_identifier(
+        naming: &NamingConfig,
+        prefix: &str,
+        marker: &str,
+        identity_key: &str,
+        max_len: usize,
+    ) -> String {
+        let fixed_len = Self::render_name(naming, prefix, marker).len();
+
+        let is_plain = identity_key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_plain && fixed_len + identity_key.len() <= max_len {
+            return Self::render_name(naming, prefix, &format!("{}{}", marker, identity_key));
+        }
+
+        let hash = format!("{:x}", Sha256::digest(identity_key.as_bytes()));
+        let hash = &hash[..NAME_HASH_LEN];
+
+        // 替换不安全字符并合并连续的下划线
+        let mut sanitized = String::new();
+        for c in identity_key.chars() {
+            let c = if c.is_ascii_alphanumeric() { c } else { '_' };
+            if c == '_' && (sanitized.is_empty() || sanitized.ends_with('_')) {
+                continue;
+            }
+            sanitized.push(c);
+        }
+        let budget = max_len.saturating_sub(fixed_len + NAME_HASH_LEN + 1);
+        sanitized.truncate(budget);
+        let sanitized = sanitized.trim_end_matches('_');
+
+        if sanitized.is_empty() {
+            Self::render_name(naming, prefix, &format!("{}{}", marker, hash))
+        } else {
+            Self::render_name(naming, prefix, &format!("{}{}_{}", marker, sanitized, hash))
+        }
+    }
+
+    /// 校验命名规则，确保任意合法的身份标识都能得到合法且互不冲突的名称
+    pub fn validate_naming(naming: &NamingConfig) -> Result<()> {
+        if naming.template.matches("{identity}").count() != 1 {
+            return Err(anyhow::anyhow!(
+                "命名模板必须包含且只能包含一个 {{identity}}"
+            ));
+        }
+
+        if naming.template.contains("{term}") && naming.term.is_none() {
+            return Err(anyhow::anyhow!(
+                "命名模板使用了 {{term}}，但未设置 NAMING_TERM"
+            ));
+        }
+
+        // 去掉占位符后只能剩下字母、数字和下划线
+        let literal = naming
+            .template
+            .replace("{prefix}", "")
+            .replace("{term}", "")
+            .replace("{identity}", "");
+        if !literal
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(anyhow::anyhow!(
+                "命名模板包含不支持的占位符或字符: {}",
+                naming.template
+            ));
+        }
+
+        for (name, value) in [
+            ("数据库前缀", naming.database_prefix.as_str()),
+            ("用户前缀", naming.username_prefix.as_str()),
+            ("学期", naming.term.as_deref().unwrap_or("")),
+        ] {
+            if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(anyhow::anyhow!(
+                    "{} 只能包含字母、数字和下划线: {}",
+                    name,
+                    value
+                ));
+            }
+        }
+
+        if naming.template.contains("{prefix}") && naming.database_prefix == naming.username_prefix
+        {
+            warn!("数据库前缀与用户前缀相同，数据库名和用户名将一致");
+        }
+
+        // 用典型、最长和含特殊字符的身份标识试算
+        let longest = "9".repeat(50);
+        for sample in ["2023010101", longest.as_str(), "学生-01"] {
+            let db_name = Self::database_name_for(naming, sample);
+            if !Self::is_valid_database_name(&db_name) {
+                return Err(anyhow::anyhow!("生成的数据库名不合法: {}", db_name));
+            }
+            let username = Self::username_for(naming, sample);
+            if !Self::is_valid_username(&username) {
+                return Err(anyhow::anyhow!("生成的用户名不合法: {}", username));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 获取申请记录中保存的数据库名和用户名（申请时创建的数据库，不含命名数据库）
+    async fn stored_names(&self, identity_key: &str) -> Result<Option<(String, String)>> {
+        let names = sqlx::query_as::<_, (String, String)>(
+            "SELECT db_name, db_user FROM applicants WHERE identity_key = ? AND db_suffix IS NULL AND status != 'failed' AND db_name != '' ORDER BY id DESC LIMIT 1",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+        Ok(names)
+    }
+
+    /// 获取身份标识对应的数据库名和用户名，优先使用申请记录中保存的名称
+    async fn resource_names(&self, identity_key: &str) -> Result<(String, String)> {
+        match self.stored_names(identity_key).await? {
+            Some(names) => Ok(names),
+            None => Ok((
+                Self::database_name_for(&self.naming, identity_key),
+                Self::username_for(&self.naming, identity_key),
+            )),
+        }
+    }
+
+    /// 检查名称是否已被其他身份标识占用
+    async fn is_name_taken(
+        &self,
+        identity_key: &str,
+        db_name: &str,
+        username: &str,
+    ) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT (SELECT COUNT(*) FROM applicants WHERE identity_key != ? AND status != 'failed' AND (db_name = ? OR db_user = ?))
+                 + (SELECT COUNT(*) FROM teachers WHERE db_user = ?)
+                 + (SELECT COUNT(*) FROM teams WHERE db_name = ?)
+                 + (SELECT COUNT(*) FROM team_members WHERE db_user = ?)
+            "#,
+        )
+        .bind(identity_key)
+        .bind(db_name)
+        .bind(username)
+        .bind(username)
+        .bind(db_name)
+        .bind(username)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        Ok(count > 0)
+    }
+
     // 安全验证函数
 
     /// 验证数据库标识符是否安全（防止SQL注入）
@@ -494,38 +1482,18 @@ impl DatabaseManager {
 
     /// 验证数据库名是否安全（更严格的验证）
     fn is_valid_database_name(db_name: &str) -> bool {
-        // 数据库名必须以 "db_" 开头，后面跟用户编号
-        if !db_name.starts_with("db_") {
-            return false;
-        }
-
-        let user_id = &db_name[3..]; // 去掉 "db_" 前缀
-
-        // 验证用户编号部分
-        if crate::auth::StudentValidator::validate_student_id_format(user_id).is_err() {
-            return false;
-        }
-
-        // 使用基础验证
-        Self::is_valid_identifier(db_name)
+        // 数据库名只能使用 ASCII 字符，长度需同时满足 MySQL 和 PostgreSQL 的限制
+        db_name.is_ascii()
+            && db_name.len() <= MAX_DATABASE_NAME_LEN
+            && Self::is_valid_identifier(db_name)
     }
 
     /// 验证用户名是否安全（更严格的验证）
     fn is_valid_username(username: &str) -> bool {
-        // 用户名必须以 "user_" 开头，后面跟用户编号
-        if !username.starts_with("user_") {
-            return false;
-        }
-
-        let user_id = &username[5..]; // 去掉 "user_" 前缀
-
-        // 验证用户编号部分
-        if crate::auth::StudentValidator::validate_student_id_format(user_id).is_err() {
-            return false;
-        }
-
-        // 使用基础验证
-        Self::is_valid_identifier(username)
+        // 用户名只能使用 ASCII 字符，且不能超过 MySQL 的 32 字符限制
+        username.is_ascii()
+            && username.len() <= MAX_USERNAME_LEN
+            && Self::is_valid_identifier(username)
     }
 
     /// 验证主机地址是否安全
@@ -599,55 +1567,71 @@ impl DatabaseManager {
         Ok(())
     }
 
-    /// 测试MySQL连接
+    /// 测试所有已配置供应后端的连接
     pub async fn test_mysql_connection(&self) -> Result<()> {
-        sqlx::query("SELECT 1").execute(&self.mysql_pool).await?;
+        for (engine, provisioner) in &self.provisioners {
+            provisioner
+                .ping()
+                .await
+                .map_err(|e| anyhow::anyhow!("{} 连接失败: {}", engine, e))?;
+        }
         Ok(())
     }
 
-    /// 统计总申请数量
-    pub async fn count_total_applications(&self) -> Result<i64> {
-        let count = sqlx::query_scalar("SELECT COUNT(*) FROM applicants")
-            .fetch_one(&self.sqlite_pool)
-            .await?;
+    /// 统计总申请数量，`cohort` 为教学班 ID，为空时统计所有申请
+    pub async fn count_total_applications(&self, cohort: Option<i64>) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM applicants WHERE (?1 IS NULL OR identity_key IN (SELECT student_id FROM student_ids WHERE cohort_id = ?1))",
+        )
+        .bind(cohort)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
         Ok(count)
     }
 
     /// 统计今日申请数量
-    pub async fn count_today_applications(&self) -> Result<i64> {
+    pub async fn count_today_applications(&self, cohort: Option<i64>) -> Result<i64> {
         let count = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM applicants WHERE DATE(created_at) = DATE('now')",
+            "SELECT COUNT(*) FROM applicants WHERE DATE(created_at) = DATE('now') AND (?1 IS NULL OR identity_key IN (SELECT student_id FROM student_ids WHERE cohort_id = ?1))",
         )
+        .bind(cohort)
         .fetch_one(&self.sqlite_pool)
         .await?;
         Ok(count)
     }
 
     /// 统计本周申请数量
-    pub async fn count_week_applications(&self) -> Result<i64> {
+    pub async fn count_week_applications(&self, cohort: Option<i64>) -> Result<i64> {
         let count = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM applicants WHERE created_at >= DATE('now', '-7 days')",
+            "SELECT COUNT(*) FROM applicants WHERE created_at >= DATE('now', '-7 days') AND (?1 IS NULL OR identity_key IN (SELECT student_id FROM student_ids WHERE cohort_id = ?1))",
         )
+        .bind(cohort)
         .fetch_one(&self.sqlite_pool)
         .await?;
         Ok(count)
     }
 
     /// 统计本月申请数量
-    pub async fn count_month_applications(&self) -> Result<i64> {
+    pub async fn count_month_applications(&self, cohort: Option<i64>) -> Result<i64> {
         let count = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM applicants WHERE created_at >= DATE('now', 'start of month')",
+            "SELECT COUNT(*) FROM applicants WHERE created_at >= DATE('now', 'start of month') AND (?1 IS NULL OR identity_key IN (SELECT student_id FROM student_ids WHERE cohort_id = ?1))",
         )
+        .bind(cohort)
         .fetch_one(&self.sqlite_pool)
         .await?;
         Ok(count)
     }
 
     /// 获取最近的申请记录
-    pub async fn get_recent_applications(&self, limit: i32) -> Result<Vec<Applicant>> {
+    pub async fn get_recent_applications(
+        &self,
+        limit: i32,
+        cohort: Option<i64>,
+    ) -> Result<Vec<Applicant>> {
         let applicants = sqlx::query_as::<_, Applicant>(
-            "SELECT id, identity_key, db_name, db_user, status, failure_reason, created_at, deleted_at, deletion_reason FROM applicants ORDER BY created_at DESC LIMIT ?"
+            "SELECT id, identity_key, db_name, db_user, db_engine, privilege_profile, status, failure_reason, created_at, deleted_at, deletion_reason FROM applicants WHERE (?1 IS NULL OR identity_key IN (SELECT student_id FROM student_ids WHERE cohort_id = ?1)) ORDER BY created_at DESC LIMIT ?2"
         )
+        .bind(cohort)
         .bind(limit)
         .fetch_all(&self.sqlite_pool)
         .await?;
@@ -695,18 +1679,27 @@ impl DatabaseManager {
         Ok(())
     }
 
-    /// 获取所有学号记录
+    /// 获取所有学号记录，`cohort` 为教学班 ID，为空时返回所有记录
     pub async fn get_all_student_ids(
         &self,
         limit: Option<i32>,
         offset: Option<i32>,
+        cohort: Option<i64>,
     ) -> Result<Vec<crate::models::StudentId>> {
         let limit = limit.unwrap_or(100);
         let offset = offset.unwrap_or(0);
 
         let student_ids = sqlx::query_as::<_, crate::models::StudentId>(
-            "SELECT id, student_id, student_name, class_info, has_applied, applied_db_name, created_at, updated_at FROM student_ids ORDER BY created_at DESC LIMIT ? OFFSET ?"
+            r#"
+            SELECT s.id, s.student_id, s.student_name, s.class_info, c.name AS cohort, s.has_applied, s.applied_db_name,
+                   s.db_engine, s.privilege_profile, s.schema_template, s.created_at, s.updated_at
+            FROM student_ids s
+            LEFT JOIN cohorts c ON c.id = s.cohort_id
+            WHERE ?1 IS NULL OR s.cohort_id = ?1
+            ORDER BY s.created_at DESC LIMIT ?2 OFFSET ?3
+            "#,
         )
+        .bind(cohort)
         .bind(limit)
         .bind(offset)
         .fetch_all(&self.sqlite_pool)
@@ -721,16 +1714,23 @@ impl DatabaseManager {
         student_id: &str,
         student_name: Option<&str>,
         class_info: Option<&str>,
+        db_engine: Option<DatabaseEngine>,
+        privilege_profile: Option<&str>,
     ) -> Result<()> {
         // 验证用户编号格式
         crate::auth::StudentValidator::validate_student_id_format(student_id)?;
+        self.ensure_engine_configured(db_engine)?;
+        self.ensure_profile_exists(privilege_profile)?;
+        self.ensure_no_team_collision(student_id).await?;
 
         sqlx::query(
-            "INSERT INTO student_ids (student_id, student_name, class_info) VALUES (?, ?, ?)",
+            "INSERT INTO student_ids (student_id, student_name, class_info, db_engine, privilege_profile) VALUES (?, ?, ?, ?, ?)",
         )
         .bind(student_id)
         .bind(student_name)
         .bind(class_info)
+        .bind(db_engine.map(|e| e.as_str()))
+        .bind(privilege_profile)
         .execute(&self.sqlite_pool)
         .await?;
 
@@ -743,12 +1743,19 @@ impl DatabaseManager {
         id: i32,
         student_name: Option<&str>,
         class_info: Option<&str>,
+        db_engine: Option<DatabaseEngine>,
+        privilege_profile: Option<&str>,
     ) -> Result<()> {
+        self.ensure_engine_configured(db_engine)?;
+        self.ensure_profile_exists(privilege_profile)?;
+
         sqlx::query(
-            "UPDATE student_ids SET student_name = ?, class_info = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+            "UPDATE student_ids SET student_name = ?, class_info = ?, db_engine = ?, privilege_profile = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
         )
         .bind(student_name)
         .bind(class_info)
+        .bind(db_engine.map(|e| e.as_str()))
+        .bind(privilege_profile)
         .bind(id)
         .execute(&self.sqlite_pool)
         .await?;
@@ -798,14 +1805,61 @@ impl DatabaseManager {
             } else {
                 None
             };
+            let db_engine = if parts.len() > 3 && !parts[3].is_empty() {
+                match parts[3].parse::<DatabaseEngine>() {
+                    Ok(engine) => Some(engine),
+                    Err(e) => {
+                        errors.push(format!("第{}行: {}", line_num + 1, e));
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
 
-            // 验证用户编号格式
-            if let Err(e) = crate::auth::StudentValidator::validate_student_id_format(student_id) {
-                errors.push(format!(
-                    "第{}行: 用户编号格式验证失败 '{}': {}",
-                    line_num + 1,
-                    student_id,
-                    e
+            let privilege_profile = if parts.len() > 4 && !parts[4].is_empty() {
+                Some(parts[4])
+            } else {
+                None
+            };
+
+            let schema_template = if parts.len() > 5 && !parts[5].is_empty() {
+                Some(parts[5])
+            } else {
+                None
+            };
+
+            let cohort = if parts.len() > 6 && !parts[6].is_empty() {
+                match self.open_cohort_id(parts[6]).await {
+                    Ok(id) => Some(id),
+                    Err(e) => {
+                        errors.push(format!("第{}行: {}", line_num + 1, e));
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+
+            if let Err(e) = self
+                .ensure_engine_configured(db_engine)
+                .and_then(|_| self.ensure_profile_exists(privilege_profile))
+            {
+                errors.push(format!("第{}行: {}", line_num + 1, e));
+                continue;
+            }
+            if let Err(e) = self.ensure_template_exists(schema_template).await {
+                errors.push(format!("第{}行: {}", line_num + 1, e));
+                continue;
+            }
+
+            // 验证用户编号格式
+            if let Err(e) = crate::auth::StudentValidator::validate_student_id_format(student_id) {
+                errors.push(format!(
+                    "第{}行: 用户编号格式验证失败 '{}': {}",
+                    line_num + 1,
+                    student_id,
+                    e
                 ));
                 continue;
             }
@@ -822,10 +1876,14 @@ impl DatabaseManager {
                 if overwrite_existing {
                     // 更新现有记录
                     if let Err(e) = sqlx::query(
-                        "UPDATE student_ids SET student_name = ?, class_info = ?, updated_at = CURRENT_TIMESTAMP WHERE student_id = ?"
+                        "UPDATE student_ids SET student_name = ?, class_info = ?, db_engine = ?, privilege_profile = ?, schema_template = ?, cohort_id = ?, updated_at = CURRENT_TIMESTAMP WHERE student_id = ?"
                     )
                     .bind(student_name)
                     .bind(class_info)
+                    .bind(db_engine.map(|e| e.as_str()))
+                    .bind(privilege_profile)
+                    .bind(schema_template)
+                    .bind(cohort)
                     .bind(student_id)
                     .execute(&self.sqlite_pool)
                     .await {
@@ -843,12 +1901,33 @@ impl DatabaseManager {
             } else {
                 // 插入新记录
                 if let Err(e) = self
-                    .add_student_id(student_id, student_name, class_info)
+                    .add_student_id(
+                        student_id,
+                        student_name,
+                        class_info,
+                        db_engine,
+                        privilege_profile,
+                    )
                     .await
                 {
                     errors.push(format!("第{}行: 插入失败 - {}", line_num + 1, e));
                 } else {
                     imported_count += 1;
+                    if schema_template.is_some()
+                        && let Err(e) = self.set_student_template(student_id, schema_template).await
+                    {
+                        errors.push(format!("第{}行: 设置结构模板失败 - {}", line_num + 1, e));
+                    }
+                    if let Some(cohort) = cohort
+                        && let Err(e) =
+                            sqlx::query("UPDATE student_ids SET cohort_id = ? WHERE student_id = ?")
+                                .bind(cohort)
+                                .bind(student_id)
+                                .execute(&self.sqlite_pool)
+                                .await
+                    {
+                        errors.push(format!("第{}行: 设置教学班失败 - {}", line_num + 1, e));
+                    }
                 }
             }
         }
@@ -856,16 +1935,24 @@ impl DatabaseManager {
         Ok((imported_count, updated_count, errors))
     }
 
-    /// 获取学号统计信息
-    pub async fn get_student_id_stats(&self) -> Result<crate::models::StudentIdStats> {
-        let total_count: i32 = sqlx::query_scalar("SELECT COUNT(*) FROM student_ids")
-            .fetch_one(&self.sqlite_pool)
-            .await?;
+    /// 获取学号统计信息，`cohort` 为教学班 ID，为空时统计所有记录
+    pub async fn get_student_id_stats(
+        &self,
+        cohort: Option<i64>,
+    ) -> Result<crate::models::StudentIdStats> {
+        let total_count: i32 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM student_ids WHERE ?1 IS NULL OR cohort_id = ?1",
+        )
+        .bind(cohort)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
 
-        let applied_count: i32 =
-            sqlx::query_scalar("SELECT COUNT(*) FROM student_ids WHERE has_applied = 1")
-                .fetch_one(&self.sqlite_pool)
-                .await?;
+        let applied_count: i32 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM student_ids WHERE has_applied = 1 AND (?1 IS NULL OR cohort_id = ?1)",
+        )
+        .bind(cohort)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
 
         let not_applied_count = total_count - applied_count;
 
@@ -877,17 +1964,21 @@ impl DatabaseManager {
     }
 
     /// 验证学号格式
-    #[allow(dead_code)]
+    #[cfg(test)]
     fn is_valid_student_id(student_id: &str) -> bool {
         // 学号应该是10位数字
         student_id.len() == 10 && student_id.chars().all(|c| c.is_ascii_digit())
     }
 
-    /// 获取所有已创建的用户和数据库信息
-    pub async fn get_all_users(&self) -> Result<Vec<crate::models::UserDatabaseInfo>> {
+    /// 获取所有已创建的用户和数据库信息，`cohort` 为教学班 ID，为空时返回所有用户
+    pub async fn get_all_users(
+        &self,
+        cohort: Option<i64>,
+    ) -> Result<Vec<crate::models::UserDatabaseInfo>> {
         let users = sqlx::query_as::<_, crate::models::UserDatabaseInfo>(
-            "SELECT id, identity_key, db_name, db_user, status, failure_reason, created_at, deleted_at, deletion_reason FROM applicants WHERE status = 'success' AND deleted_at IS NULL ORDER BY created_at DESC"
+            "SELECT id, identity_key, db_name, db_user, db_engine, privilege_profile, storage_bytes, storage_checked_at, quota_exceeded, expires_at, suspended_at, suspension_reason, status, failure_reason, created_at, deleted_at, deletion_reason FROM applicants WHERE status IN ('success', 'expired', 'suspended') AND deleted_at IS NULL AND (?1 IS NULL OR identity_key IN (SELECT student_id FROM student_ids WHERE cohort_id = ?1)) ORDER BY created_at DESC"
         )
+        .bind(cohort)
         .fetch_all(&self.sqlite_pool)
         .await?;
 
@@ -896,71 +1987,153 @@ impl DatabaseManager {
 
     // 错误处理和回滚机制
 
-    /// 回滚MySQL数据库创建操作
-    pub async fn rollback_database_creation(&self, identity_key: &str) -> Result<()> {
-        let db_name = format!("db_{}", identity_key);
-        let username = format!("user_{}", identity_key);
-        let allowed_host = self
-            .mysql_config
-            .allowed_host
-            .as_deref()
-            .unwrap_or("localhost");
+    /// 回滚本次申请创建的资源，只删除 `created` 中记录的数据库、用户和角色
+    async fn undo_created_resources(
+        &self,
+        provisioner: &dyn Provisioner,
+        db_name: &str,
+        username: &str,
+        created: &CreatedResources,
+    ) {
+        warn!("回滚未完成的申请: 数据库 {}, 用户 {}", db_name, username);
+        if created.user
+            && let Err(e) = provisioner.drop_user(username).await
+        {
+            error!("回滚删除用户 {} 失败: {}", username, e);
+        }
+        if let Some(ref role) = created.role
+            && let Err(e) = provisioner.drop_role(role).await
+        {
+            error!("回滚删除角色 {} 失败: {}", role, e);
+        }
+        if created.database
+            && let Err(e) = provisioner.drop_database(db_name).await
+        {
+            error!("回滚删除数据库 {} 失败: {}", db_name, e);
+        }
+        if let Err(e) = provisioner.flush_privileges().await {
+            error!("回滚刷新权限失败: {}", e);
+        }
+    }
+
+    /// 删除指定引擎上的数据库和用户，不读取 SQLite，因此 SQLite 不可用时也能执行
+    async fn rollback_resources(
+        &self,
+        identity_key: &str,
+        engine: DatabaseEngine,
+        db_name: &str,
+        username: &str,
+    ) -> Result<()> {
+        let provisioner = self.provisioner(engine)?;
 
-        warn!("开始回滚MySQL操作，身份标识: {}", identity_key);
+        warn!("开始回滚{}操作，身份标识: {}", engine, identity_key);
 
         // 删除用户（如果存在）
-        let drop_user_sql = format!("DROP USER IF EXISTS '{}'@'{}'", username, allowed_host);
-        if let Err(e) = sqlx::query(&drop_user_sql).execute(&self.mysql_pool).await {
+        if let Err(e) = provisioner.drop_user(username).await {
             error!("回滚删除用户失败: {}", e);
         } else {
             info!("成功删除用户: {}", username);
         }
 
         // 删除数据库（如果存在）
-        let drop_db_sql = format!("DROP DATABASE IF EXISTS `{}`", db_name);
-        if let Err(e) = sqlx::query(&drop_db_sql).execute(&self.mysql_pool).await {
+        if let Err(e) = provisioner.drop_database(db_name).await {
             error!("回滚删除数据库失败: {}", e);
         } else {
             info!("成功删除数据库: {}", db_name);
         }
 
+        // 删除数据库对应的角色（如果存在）
+        self.drop_profile_roles(provisioner.as_ref(), engine, db_name)
+            .await;
+
         // 刷新权限
-        if let Err(e) = sqlx::query("FLUSH PRIVILEGES")
-            .execute(&self.mysql_pool)
-            .await
-        {
+        if let Err(e) = provisioner.flush_privileges().await {
             error!("回滚刷新权限失败: {}", e);
         }
 
-        warn!("MySQL回滚操作完成，身份标识: {}", identity_key);
+        warn!("{}回滚操作完成，身份标识: {}", engine, identity_key);
         Ok(())
     }
 
     /// 事务性创建数据库和用户
+    ///
+    /// 申请窗口或容量不允许时返回 [`ApplicationRejection`] 错误。
     pub async fn provision_database_with_transaction(
         &self,
         identity_key: &str,
         password: &str,
     ) -> Result<DatabaseCredentials> {
+        let _guard = self.provision_lock.lock().await;
+        self.ensure_application_allowed(identity_key).await?;
+
         // 首先尝试创建MySQL资源
         let credentials = self.provision_database(identity_key, password).await?;
+        let profile = self.resolve_profile(identity_key).await?;
+        let role = self
+            .uses_roles(credentials.db_engine)
+            .then(|| Self::role_name_for(&profile, &credentials.db_name));
+        let template = self.resolve_template(identity_key).await?;
 
         // 然后尝试创建SQLite记录
         match self
-            .create_applicant(identity_key, &credentials.db_name, &credentials.username)
+            .create_applicant(
+                identity_key,
+                &credentials.db_name,
+                &credentials.username,
+                credentials.db_engine,
+                &profile,
+                role.as_deref(),
+                template.as_deref(),
+            )
             .await
         {
             Ok(_) => {
                 info!("事务性创建完成，身份标识: {}", identity_key);
+                if let Err(e) = self.apply_default_expiry(identity_key).await {
+                    warn!("设置数据库有效期失败: {} {}", identity_key, e);
+                }
+                if let Err(e) = self
+                    .grant_teacher_access(identity_key, credentials.db_engine, &credentials.db_name)
+                    .await
+                {
+                    warn!("授予任课教师查看权限失败: {} {}", identity_key, e);
+                }
+                // 之前的数据库留下的检查点不适用于新数据库
+                if let Err(e) = sqlx::query("DELETE FROM database_baselines WHERE identity_key = ?")
+                    .bind(identity_key)
+                    .execute(&self.sqlite_pool)
+                    .await
+                {
+                    warn!("清除旧的数据库检查点失败: {} {}", identity_key, e);
+                }
+                self.record_history(
+                    identity_key,
+                    "applied",
+                    Some(&format!(
+                        "数据库 {} ({}), 权限配置档 {}",
+                        credentials.db_name, credentials.db_engine, profile
+                    )),
+                )
+                .await;
                 Ok(credentials)
             }
             Err(e) => {
-                error!("SQLite记录创建失败，开始回滚MySQL操作: {}", e);
+                error!("SQLite记录创建失败，开始回滚本次创建的资源: {}", e);
 
-                // 回滚MySQL操作
-                if let Err(rollback_err) = self.rollback_database_creation(identity_key).await {
-                    error!("回滚操作也失败了: {}", rollback_err);
-                }
+                // provision_database 拒绝已存在的数据库和用户，两者都由本次申请创建
+                let created = CreatedResources {
+                    database: true,
+                    user: true,
+                    role,
+                };
+                let provisioner = self.provisioner(credentials.db_engine)?;
+                self.undo_created_resources(
+                    provisioner.as_ref(),
+                    &credentials.db_name,
+                    &credentials.username,
+                    &created,
+                )
+                .await;
 
                 Err(e)
             }
@@ -977,25 +2150,14 @@ impl DatabaseManager {
         }
 
         // 检查MySQL中是否存在对应的数据库和用户
-        let db_name = format!("db_{}", identity_key);
-        let username = format!("user_{}", identity_key);
+        let (db_name, username) = self.resource_names(identity_key).await?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
 
         // 检查数据库是否存在
-        let db_exists = sqlx::query_scalar::<_, i64>(
-            "SELECT COUNT(*) FROM INFORMATION_SCHEMA.SCHEMATA WHERE SCHEMA_NAME = ?",
-        )
-        .bind(&db_name)
-        .fetch_one(&self.mysql_pool)
-        .await?
-            > 0;
+        let db_exists = provisioner.database_exists(&db_name).await?;
 
         // 检查用户是否存在
-        let user_exists =
-            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM mysql.user WHERE User = ?")
-                .bind(&username)
-                .fetch_one(&self.mysql_pool)
-                .await?
-                > 0;
+        let user_exists = provisioner.user_exists(&username).await?;
 
         let is_consistent = db_exists && user_exists;
 
@@ -1010,98 +2172,4479 @@ impl DatabaseManager {
     }
 
     /// 修复数据不一致问题
+    ///
+    /// 只处理申请时创建的数据库对应的有效记录：按记录中保存的名称删除残留的数据库和用户，
+    /// 再删除这一条记录。已删除（可能仍有隔离库）、已归档的记录和命名数据库的记录不受影响。
     pub async fn repair_data_inconsistency(&self, identity_key: &str) -> Result<()> {
         info!("开始修复数据不一致，身份标识: {}", identity_key);
 
-        let sqlite_exists = self.check_identity_exists(identity_key).await?;
+        let record = sqlx::query_as::<_, (i64, String, String, String)>(
+            r#"
+            SELECT id, db_name, db_user, db_engine FROM applicants
+            WHERE identity_key = ? AND db_suffix IS NULL AND db_name != ''
+              AND status IN ('success', 'suspended', 'expired')
+            ORDER BY id DESC LIMIT 1
+            "#,
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+
+        if let Some((id, db_name, username, engine)) = record {
+            // 清理可能残留的数据库资源（需在删除记录前进行，记录中保存了所用引擎和名称）
+            let _ = self
+                .rollback_resources(identity_key, engine.parse()?, &db_name, &username)
+                .await;
 
-        if sqlite_exists {
-            // 如果SQLite中存在记录，但MySQL中资源不完整，则删除SQLite记录
             warn!("删除不一致的SQLite记录，身份标识: {}", identity_key);
-            sqlx::query("DELETE FROM applicants WHERE identity_key = ?")
-                .bind(identity_key)
+            sqlx::query("DELETE FROM applicants WHERE id = ?")
+                .bind(id)
                 .execute(&self.sqlite_pool)
                 .await?;
         }
 
-        // 清理可能存在的MySQL资源
-        let _ = self.rollback_database_creation(identity_key).await;
-
         info!("数据不一致修复完成，身份标识: {}", identity_key);
         Ok(())
     }
 
     /// 统计成功申请数量
-    pub async fn count_successful_applications(&self) -> Result<i64> {
-        let count = sqlx::query_scalar("SELECT COUNT(*) FROM applicants WHERE status = 'success'")
-            .fetch_one(&self.sqlite_pool)
-            .await?;
+    pub async fn count_successful_applications(&self, cohort: Option<i64>) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM applicants WHERE status = 'success' AND (?1 IS NULL OR identity_key IN (SELECT student_id FROM student_ids WHERE cohort_id = ?1))",
+        )
+        .bind(cohort)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
         Ok(count)
     }
 
     /// 统计失败申请数量
-    pub async fn count_failed_applications(&self) -> Result<i64> {
-        let count = sqlx::query_scalar("SELECT COUNT(*) FROM applicants WHERE status = 'failed'")
-            .fetch_one(&self.sqlite_pool)
-            .await?;
+    pub async fn count_failed_applications(&self, cohort: Option<i64>) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM applicants WHERE status = 'failed' AND (?1 IS NULL OR identity_key IN (SELECT student_id FROM student_ids WHERE cohort_id = ?1))",
+        )
+        .bind(cohort)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
         Ok(count)
     }
 
     /// 统计已删除申请数量
-    pub async fn count_deleted_applications(&self) -> Result<i64> {
-        let count = sqlx::query_scalar("SELECT COUNT(*) FROM applicants WHERE status = 'deleted'")
-            .fetch_one(&self.sqlite_pool)
-            .await?;
+    pub async fn count_deleted_applications(&self, cohort: Option<i64>) -> Result<i64> {
+        let count = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM applicants WHERE status = 'deleted' AND (?1 IS NULL OR identity_key IN (SELECT student_id FROM student_ids WHERE cohort_id = ?1))",
+        )
+        .bind(cohort)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
         Ok(count)
     }
 
     /// 管理员删除用户数据库和用户
     pub async fn admin_delete_user(&self, identity_key: &str, reason: &str) -> Result<()> {
-        let db_name = format!("db_{}", identity_key);
-        let username = format!("user_{}", identity_key);
-        let allowed_host = self
-            .mysql_config
-            .allowed_host
-            .as_deref()
-            .unwrap_or("localhost");
+        let (db_name, username) = self.resource_names(identity_key).await?;
+        let engine = self.resolve_engine(identity_key).await?;
+        let provisioner = self.provisioner(engine)?;
 
         info!("管理员删除用户: {}, 原因: {}", identity_key, reason);
 
-        // 1. 删除 MySQL 用户
-        let drop_user_sql = format!("DROP USER IF EXISTS '{}'@'{}'", username, allowed_host);
-        if let Err(e) = sqlx::query(&drop_user_sql).execute(&self.mysql_pool).await {
+        // 只处理当前的申请记录，已删除记录上的隔离库名不会被覆盖
+        let record_id: Option<i64> = sqlx::query_scalar(
+            "SELECT id FROM applicants WHERE identity_key = ? AND db_suffix IS NULL AND status NOT IN ('deleted', 'failed') ORDER BY id DESC LIMIT 1",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+
+        // 1. 保留期内先将数据库移入隔离库，移动失败时中止删除，避免数据丢失
+        let quarantine = match record_id {
+            Some(id)
+                if self.lifecycle.deletion_retention_days > 0
+                    && provisioner.database_exists(&db_name).await? =>
+            {
+                let quarantine = Self::quarantine_name_for(&db_name, id);
+                self.quarantine_database(provisioner.as_ref(), id, &db_name, &quarantine, reason)
+                    .await?;
+                info!(
+                    "数据库 {} 已移入隔离库 {}，保留 {} 天",
+                    db_name, quarantine, self.lifecycle.deletion_retention_days
+                );
+                Some(quarantine)
+            }
+            _ => None,
+        };
+
+        // 2. 命名数据库与申请时创建的数据库一同删除（保留期内同样移入隔离库）
+        self.delete_named_databases(identity_key, reason).await?;
+
+        // 3. 删除 MySQL 用户及其在团队数据库上的用户
+        if let Err(e) = provisioner.drop_user(&username).await {
             error!("删除用户失败: {}", e);
         } else {
             info!("成功删除用户: {}", username);
         }
+        if let Err(e) = self.leave_all_teams(identity_key, "已被删除").await {
+            error!("将用户 {} 移出团队失败: {}", identity_key, e);
+        }
 
-        // 2. 删除 MySQL 数据库
-        let drop_db_sql = format!("DROP DATABASE IF EXISTS `{}`", db_name);
-        if let Err(e) = sqlx::query(&drop_db_sql).execute(&self.mysql_pool).await {
-            error!("删除数据库失败: {}", e);
-        } else {
-            info!("成功删除数据库: {}", db_name);
+        // 4. 删除 MySQL 数据库及其角色
+        if quarantine.is_none() {
+            if let Err(e) = provisioner.drop_database(&db_name).await {
+                error!("删除数据库失败: {}", e);
+            } else {
+                info!("成功删除数据库: {}", db_name);
+            }
         }
+        self.drop_profile_roles(provisioner.as_ref(), engine, &db_name)
+            .await;
 
-        // 3. 刷新权限
-        if let Err(e) = sqlx::query("FLUSH PRIVILEGES")
-            .execute(&self.mysql_pool)
-            .await
-        {
+        // 5. 刷新权限
+        if let Err(e) = provisioner.flush_privileges().await {
             error!("刷新权限失败: {}", e);
         }
 
-        // 4. 更新 SQLite 记录状态
+        // 6. 更新 SQLite 记录状态（移入隔离库时已经更新）
+        if let Some(id) = record_id
+            && quarantine.is_none()
+        {
+            self.mark_deleted(id, reason, None).await?;
+        }
+
+        self.record_history(identity_key, "deleted", Some(reason))
+            .await;
+        info!("用户 {} 已被管理员删除", identity_key);
+        Ok(())
+    }
+
+    // 权限配置档管理
+
+    /// 列出所有权限配置档
+    pub fn list_privilege_profiles(&self) -> Vec<PrivilegeProfile> {
+        let config = self.privileges.read().unwrap();
+        config
+            .profiles
+            .iter()
+            .map(|(name, privileges)| PrivilegeProfile {
+                name: name.clone(),
+                privileges: privileges.clone(),
+                is_default: *name == config.default_profile,
+            })
+            .collect()
+    }
+
+    /// 加载管理员保存的配置档，覆盖内置配置档和 `PRIVILEGE_PROFILES` 中的同名配置档
+    async fn load_saved_profiles(&self) -> Result<()> {
+        let saved = sqlx::query_as::<_, (String, String)>(
+            "SELECT profile, privileges FROM saved_privilege_profiles",
+        )
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        let mut config = self.privileges.write().unwrap();
+        for (profile, privileges) in saved {
+            config
+                .profiles
+                .insert(profile, privileges.split(',').map(str::to_string).collect());
+        }
+        config.validate()
+    }
+
+    /// 创建或修改权限配置档，并立即应用到使用该配置档的所有数据库
+    ///
+    /// 通过角色授权的数据库更新角色的权限（每个数据库一个角色，见 [`Self::role_name_for`]），
+    /// 直接授权的用户和团队成员按新权限重新授权。配置档保存在 SQLite 中，重启后仍然有效。
+    /// 返回更新的角色和用户数及错误列表。
+    pub async fn save_privilege_profile(
+        &self,
+        name: &str,
+        privileges: &[String],
+    ) -> Result<(i32, Vec<String>)> {
+        let privileges: Vec<String> = privileges
+            .iter()
+            .map(|p| p.trim().to_ascii_uppercase())
+            .filter(|p| !p.is_empty())
+            .collect();
+        let mut updated = self.privileges.read().unwrap().clone();
+        updated
+            .profiles
+            .insert(name.to_string(), privileges.clone());
+        updated.validate()?;
+
         sqlx::query(
-            "UPDATE applicants SET status = 'deleted', deleted_at = CURRENT_TIMESTAMP, deletion_reason = ? WHERE identity_key = ?"
+            r#"
+            INSERT INTO saved_privilege_profiles (profile, privileges) VALUES (?, ?)
+            ON CONFLICT(profile) DO UPDATE SET
+                privileges = excluded.privileges,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
         )
-        .bind(reason)
+        .bind(name)
+        .bind(privileges.join(","))
+        .execute(&self.sqlite_pool)
+        .await?;
+        *self.privileges.write().unwrap() = updated;
+        info!("权限配置档 {} 已保存: {}", name, privileges.join(", "));
+
+        let (mut updated_count, mut errors) = self.sync_profile_roles().await?;
+
+        let direct = sqlx::query_scalar::<_, String>(
+            "SELECT DISTINCT identity_key FROM applicants WHERE status IN ('success', 'suspended', 'expired') AND privilege_profile = ? AND privilege_role IS NULL",
+        )
+        .bind(name)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+        for identity_key in direct {
+            match self.regrant_profile(&identity_key, name).await {
+                Ok(true) => updated_count += 1,
+                Ok(false) => {}
+                Err(e) => errors.push(format!("{}: {}", identity_key, e)),
+            }
+        }
+
+        let (team_count, team_errors) = self.regrant_team_members(name).await?;
+        updated_count += team_count;
+        errors.extend(team_errors);
+
+        Ok((updated_count, errors))
+    }
+
+    /// 按配置档重新授予已创建用户的权限，用户尚未申请数据库时返回 false
+    ///
+    /// 用户拥有的每个数据库（包括命名数据库）都按新配置档重新授权。停用或到期锁定的用户同样重新授权，
+    /// 锁定期间无法登录，恢复或延期后直接使用新的权限。
+    async fn regrant_profile(&self, identity_key: &str, profile: &str) -> Result<bool> {
+        let privileges = self.profile_privileges(profile)?;
+
+        let active = sqlx::query_as::<_, (String, String, String, Option<String>, bool)>(
+            "SELECT db_name, db_user, db_engine, privilege_role, quota_exceeded FROM applicants WHERE identity_key = ? AND status IN ('success', 'suspended', 'expired') ORDER BY id",
+        )
+        .bind(identity_key)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        if active.is_empty() {
+            return Ok(false);
+        }
+
+        for (db_name, username, engine, old_role, quota_exceeded) in active {
+            let privileges = Self::quota_privileges(privileges.clone(), quota_exceeded);
+            let engine: DatabaseEngine = engine.parse()?;
+            let provisioner = self.provisioner(engine)?;
+
+            let new_role = if self.uses_roles(engine) {
+                // 先授予新配置档的角色，再撤销原有角色或直接授予的权限
+                let role = Self::role_name_for(profile, &db_name);
+                self.set_role_privileges(provisioner.as_ref(), &db_name, &role, &privileges)
+                    .await?;
+                provisioner.grant_role(&role, &username).await?;
+                if old_role.is_none() {
+                    self.revoke_direct_privileges(provisioner.as_ref(), &db_name, &username)
+                        .await;
+                }
+                Some(role)
+            } else {
+                // 先撤销用户在该数据库上的所有权限，再按新配置档授权
+                self.revoke_direct_privileges(provisioner.as_ref(), &db_name, &username)
+                    .await;
+                provisioner
+                    .grant_privileges(&db_name, &username, &privileges)
+                    .await?;
+                None
+            };
+
+            if let Some(old_role) = old_role.filter(|old| Some(old) != new_role.as_ref())
+                && let Err(e) = provisioner.drop_role(&old_role).await
+            {
+                warn!("删除原有角色 {} 时出现警告: {}", old_role, e);
+            }
+            provisioner.flush_privileges().await?;
+
+            sqlx::query(
+                "UPDATE applicants SET privilege_profile = ?, privilege_role = ? WHERE identity_key = ? AND db_name = ? AND status IN ('success', 'suspended', 'expired')",
+            )
+            .bind(profile)
+            .bind(new_role.as_deref())
+            .bind(identity_key)
+            .bind(&db_name)
+            .execute(&self.sqlite_pool)
+            .await?;
+
+            info!(
+                "用户 {} 在数据库 {} 上已按权限配置档 {} 重新授权: {}",
+                username,
+                db_name,
+                profile,
+                privileges.join(", ")
+            );
+        }
+
+        self.record_history(identity_key, "profile_changed", Some(profile))
+            .await;
+        Ok(true)
+    }
+
+    /// 超出存储配额的用户不授予写入权限
+    fn quota_privileges(privileges: Vec<&str>, quota_exceeded: bool) -> Vec<&str> {
+        if !quota_exceeded {
+            return privileges;
+        }
+        privileges
+            .into_iter()
+            .filter(|p| !QUOTA_RESTRICTED_PRIVILEGES.contains(p))
+            .collect()
+    }
+
+    /// 撤销用户在自己数据库上直接获得的所有权限
+    async fn revoke_direct_privileges(
+        &self,
+        provisioner: &dyn Provisioner,
+        db_name: &str,
+        username: &str,
+    ) {
+        if let Err(e) = provisioner
+            .revoke_privileges(Some(db_name), username, DATABASE_PRIVILEGES)
+            .await
+        {
+            warn!("撤销用户 {} 的原有权限时出现警告: {}", username, e);
+        }
+    }
+
+    /// 创建角色并将其权限设置为配置档的权限
+    async fn set_role_privileges(
+        &self,
+        provisioner: &dyn Provisioner,
+        db_name: &str,
+        role: &str,
+        privileges: &[&str],
+    ) -> Result<()> {
+        provisioner.create_role(role).await?;
+        // 角色可能已存在且带有旧的权限，先全部撤销（角色没有任何权限时撤销会失败，可忽略）
+        let _ = provisioner
+            .revoke_privileges(Some(db_name), role, DATABASE_PRIVILEGES)
+            .await;
+        provisioner
+            .grant_privileges(db_name, role, privileges)
+            .await
+    }
+
+    /// 删除数据库上所有配置档对应的角色
+    async fn drop_profile_roles(
+        &self,
+        provisioner: &dyn Provisioner,
+        engine: DatabaseEngine,
+        db_name: &str,
+    ) {
+        if !self.uses_roles(engine) {
+            return;
+        }
+        let profiles: Vec<String> = self
+            .privileges
+            .read()
+            .unwrap()
+            .profiles
+            .keys()
+            .cloned()
+            .collect();
+        for profile in &profiles {
+            let role = Self::role_name_for(profile, db_name);
+            if let Err(e) = provisioner.drop_role(&role).await {
+                warn!("删除角色 {} 失败: {}", role, e);
+            }
+        }
+    }
+
+    /// 将配置档权限的变化同步到已有的角色
+    ///
+    /// 与上次同步时记录的配置档权限比较，对权限发生变化的配置档，
+    /// 重新设置其所有角色的权限，使用该配置档的用户（包括停用和到期锁定的用户）一次性全部更新。
+    /// 返回更新的角色数和错误列表；有错误的配置档不记录新权限，下次启动时重试。
+    pub async fn sync_profile_roles(&self) -> Result<(i32, Vec<String>)> {
+        let mut updated_count = 0;
+        let mut errors = Vec::new();
+
+        let profiles = self.privileges.read().unwrap().profiles.clone();
+        for (profile, privileges) in &profiles {
+            let definition = privileges.join(",");
+            let recorded: Option<String> = sqlx::query_scalar(
+                "SELECT privileges FROM privilege_profile_definitions WHERE profile = ?",
+            )
+            .bind(profile)
+            .fetch_optional(&self.sqlite_pool)
+            .await?;
+
+            if recorded.as_deref() == Some(definition.as_str()) {
+                continue;
+            }
+
+            // 团队数据库的角色由所有成员共用，同样随配置档更新
+            let roles = sqlx::query_as::<_, (String, String, String, bool)>(
+                r#"
+                SELECT db_name, db_engine, privilege_role, quota_exceeded FROM applicants
+                WHERE status IN ('success', 'suspended', 'expired') AND privilege_profile = ?1 AND privilege_role IS NOT NULL
+                UNION
+                SELECT t.db_name, t.db_engine, m.privilege_role, FALSE FROM team_members m
+                JOIN teams t ON t.name = m.team
+                WHERE t.privilege_profile = ?1 AND m.privilege_role IS NOT NULL
+                "#,
+            )
+            .bind(profile)
+            .fetch_all(&self.sqlite_pool)
+            .await?;
+
+            if recorded.is_some() {
+                info!(
+                    "权限配置档 {} 已变化，更新 {} 个角色: {}",
+                    profile,
+                    roles.len(),
+                    definition
+                );
+            }
+
+            let privileges: Vec<&str> = privileges.iter().map(String::as_str).collect();
+            let mut profile_errors = 0;
+            for (db_name, engine, role, quota_exceeded) in roles {
+                let privileges = Self::quota_privileges(privileges.clone(), quota_exceeded);
+                let result = match engine.parse().and_then(|e| self.provisioner(e)) {
+                    Ok(provisioner) => {
+                        self.set_role_privileges(provisioner.as_ref(), &db_name, &role, &privileges)
+                            .await
+                    }
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(_) => updated_count += 1,
+                    Err(e) => {
+                        profile_errors += 1;
+                        errors.push(format!("{}: {}", role, e));
+                    }
+                }
+            }
+
+            if profile_errors == 0 {
+                sqlx::query(
+                    r#"
+                    INSERT INTO privilege_profile_definitions (profile, privileges) VALUES (?, ?)
+                    ON CONFLICT(profile) DO UPDATE SET
+                        privileges = excluded.privileges,
+                        updated_at = CURRENT_TIMESTAMP
+                    "#,
+                )
+                .bind(profile)
+                .bind(&definition)
+                .execute(&self.sqlite_pool)
+                .await?;
+            }
+        }
+
+        if updated_count > 0 {
+            for provisioner in self.provisioners.values() {
+                provisioner.flush_privileges().await?;
+            }
+        }
+
+        Ok((updated_count, errors))
+    }
+
+    /// 修改用户的权限配置档，已创建数据库的用户会立即重新授权
+    ///
+    /// 返回是否对已有数据库重新授权。
+    pub async fn change_privilege_profile(
+        &self,
+        identity_key: &str,
+        profile: &str,
+    ) -> Result<bool> {
+        self.profile_privileges(profile)?;
+
+        let updated = sqlx::query(
+            "UPDATE student_ids SET privilege_profile = ?, updated_at = CURRENT_TIMESTAMP WHERE student_id = ?",
+        )
+        .bind(profile)
         .bind(identity_key)
         .execute(&self.sqlite_pool)
+        .await?
+        .rows_affected();
+
+        let regranted = self.regrant_profile(identity_key, profile).await?;
+
+        if updated == 0 && !regranted {
+            return Err(anyhow::anyhow!("用户 {} 不存在", identity_key));
+        }
+
+        Ok(regranted)
+    }
+
+    /// 为班级分配权限配置档
+    ///
+    /// 该班级中未单独指定配置档的用户之后申请时使用此配置档，已申请的用户会立即重新授权。
+    /// 返回重新授权成功的用户数和错误列表。
+    pub async fn assign_class_profile(
+        &self,
+        class_info: &str,
+        profile: &str,
+    ) -> Result<(i32, Vec<String>)> {
+        self.profile_privileges(profile)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO class_privilege_profiles (class_info, privilege_profile) VALUES (?, ?)
+            ON CONFLICT(class_info) DO UPDATE SET
+                privilege_profile = excluded.privilege_profile,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(class_info)
+        .bind(profile)
+        .execute(&self.sqlite_pool)
         .await?;
 
-        info!("用户 {} 已被管理员删除", identity_key);
-        Ok(())
+        let members: Vec<String> = sqlx::query_scalar(
+            "SELECT student_id FROM student_ids WHERE class_info = ? AND has_applied = 1 AND (privilege_profile IS NULL OR privilege_profile = '')",
+        )
+        .bind(class_info)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        let mut regranted_count = 0;
+        let mut errors = Vec::new();
+        for identity_key in members {
+            match self.regrant_profile(&identity_key, profile).await {
+                Ok(true) => regranted_count += 1,
+                Ok(false) => {}
+                Err(e) => errors.push(format!("{}: {}", identity_key, e)),
+            }
+        }
+
+        info!(
+            "班级 {} 已分配权限配置档 {}，重新授权 {} 个用户，失败 {} 个",
+            class_info,
+            profile,
+            regranted_count,
+            errors.len()
+        );
+        Ok((regranted_count, errors))
+    }
+
+    /// 删除用户的所有命名数据库，保留期内移入隔离库以便随用户一同恢复
+    async fn delete_named_databases(&self, identity_key: &str, reason: &str) -> Result<()> {
+        let named = sqlx::query_as::<_, (i64, String, String)>(
+            "SELECT id, db_name, db_engine FROM applicants WHERE identity_key = ? AND db_suffix IS NOT NULL AND status NOT IN ('deleted', 'failed')",
+        )
+        .bind(identity_key)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        for (id, db_name, engine) in named {
+            let engine: DatabaseEngine = engine.parse()?;
+            self.remove_named_database(
+                self.provisioner(engine)?.as_ref(),
+                engine,
+                id,
+                &db_name,
+                reason,
+            )
+            .await?;
+            info!("用户 {} 的命名数据库 {} 已删除", identity_key, db_name);
+        }
+        Ok(())
+    }
+
+    /// 删除一个命名数据库，保留期内与申请时创建的数据库一样移入隔离库
+    async fn remove_named_database(
+        &self,
+        provisioner: &dyn Provisioner,
+        engine: DatabaseEngine,
+        id: i64,
+        db_name: &str,
+        reason: &str,
+    ) -> Result<()> {
+        if self.lifecycle.deletion_retention_days > 0
+            && provisioner.database_exists(db_name).await?
+        {
+            let quarantine = Self::quarantine_name_for(db_name, id);
+            self.quarantine_database(provisioner, id, db_name, &quarantine, reason)
+                .await?;
+        } else {
+            if let Err(e) = provisioner.drop_database(db_name).await {
+                error!("删除数据库 {} 失败: {}", db_name, e);
+            }
+            self.mark_deleted(id, reason, None).await?;
+        }
+        self.drop_profile_roles(provisioner, engine, db_name).await;
+        Ok(())
+    }
+
+    /// 将数据库移入隔离库并立即记录隔离库名
+    ///
+    /// 记录失败时把数据库移回原处，不会留下没有记录、无法恢复也无法清除的隔离库。
+    async fn quarantine_database(
+        &self,
+        provisioner: &dyn Provisioner,
+        id: i64,
+        db_name: &str,
+        quarantine: &str,
+        reason: &str,
+    ) -> Result<()> {
+        provisioner.move_database(db_name, quarantine).await?;
+        if let Err(e) = self.mark_deleted(id, reason, Some(quarantine)).await {
+            if let Err(undo_error) = provisioner.move_database(quarantine, db_name).await {
+                error!(
+                    "记录隔离库失败后无法移回数据库 {}，数据仍在 {} 中: {}",
+                    db_name, quarantine, undo_error
+                );
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// 将一条申请记录标记为已删除，已删除或失败的记录保持不变
+    ///
+    /// 未移入隔离库时保留原有的归档库名，已归档的用户被删除后仍可从归档库恢复。
+    async fn mark_deleted(&self, id: i64, reason: &str, archive_name: Option<&str>) -> Result<()> {
+        sqlx::query(
+            "UPDATE applicants SET status = 'deleted', deleted_at = CURRENT_TIMESTAMP, deletion_reason = ?, archive_name = COALESCE(?, archive_name) WHERE id = ? AND status NOT IN ('deleted', 'failed')",
+        )
+        .bind(reason)
+        .bind(archive_name)
+        .bind(id)
+        .execute(&self.sqlite_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 恢复用户仍在隔离库或归档库中的命名数据库，恢复失败的跳过并记录警告
+    ///
+    /// 同一个命名数据库有多条可恢复的记录时只恢复最近的一条；单独删除的命名数据库留在隔离库中，不随用户恢复。
+    async fn restore_named_databases(
+        &self,
+        identity_key: &str,
+        provisioner: &dyn Provisioner,
+    ) -> Result<usize> {
+        let named = sqlx::query_as::<_, (i64, String, String)>(
+            "SELECT id, db_name, archive_name FROM applicants WHERE identity_key = ? AND db_suffix IS NOT NULL AND status IN ('deleted', 'archived') AND archive_name IS NOT NULL AND deletion_reason IS NOT ? ORDER BY id DESC",
+        )
+        .bind(identity_key)
+        .bind(NAMED_DATABASE_DELETION_REASON)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        let mut seen = std::collections::HashSet::new();
+        let mut restored = 0;
+        for (id, db_name, archive_name) in named {
+            if !seen.insert(db_name.clone()) {
+                continue;
+            }
+            if let Err(e) = provisioner.move_database(&archive_name, &db_name).await {
+                warn!("恢复命名数据库 {} 失败: {}", db_name, e);
+                continue;
+            }
+            if let Err(e) = self.mark_restored(id).await {
+                // 记录仍指向归档库，将数据库移回去，之后可以再次恢复
+                warn!("保存命名数据库 {} 的恢复记录失败: {}", db_name, e);
+                if let Err(move_err) = provisioner.move_database(&db_name, &archive_name).await {
+                    error!(
+                        "将数据库 {} 移回 {} 失败: {}",
+                        db_name, archive_name, move_err
+                    );
+                }
+                continue;
+            }
+            info!("用户 {} 的命名数据库 {} 已恢复", identity_key, db_name);
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    /// 将记录标记为已恢复
+    async fn mark_restored(&self, id: i64) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE applicants SET
+                status = 'success', deleted_at = NULL, deletion_reason = NULL,
+                archive_name = NULL, archived_at = NULL, locked_at = NULL, expires_at = NULL,
+                privilege_role = NULL, quota_exceeded = FALSE
+            WHERE id = ?
+            "#,
+        )
+        .bind(id)
+        .execute(&self.sqlite_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 从隔离库或归档库恢复已删除或已归档的用户，保持原有的数据库名和用户名
+    ///
+    /// 同一用户有多条可恢复的记录时恢复最近的一条。原密码无法恢复，使用新密码重新创建用户，
+    /// 并按原权限配置档重新授权。恢复计入数据库服务器的容量；任一步骤失败时数据库移回归档库，
+    /// 删除本次创建的用户，记录保持原样，之后可以再次恢复。
+    pub async fn restore_user(
+        &self,
+        identity_key: &str,
+        password: &str,
+    ) -> Result<DatabaseCredentials> {
+        let record = sqlx::query_as::<_, (i64, String, String, String, String, Option<String>)>(
+            "SELECT id, db_name, db_user, db_engine, archive_name, privilege_profile FROM applicants WHERE identity_key = ? AND db_suffix IS NULL AND status IN ('deleted', 'archived') AND archive_name IS NOT NULL ORDER BY id DESC LIMIT 1",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+        let Some((id, db_name, username, engine, archive_name, profile)) = record else {
+            return Err(anyhow::anyhow!(
+                "用户 {} 不存在或没有可恢复的数据库",
+                identity_key
+            ));
+        };
+
+        let live: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM applicants WHERE identity_key = ? AND db_suffix IS NULL AND status IN ('success', 'suspended', 'expired')",
+        )
+        .bind(identity_key)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        if live > 0 {
+            return Err(anyhow::anyhow!(
+                "用户 {} 已有数据库，无法恢复",
+                identity_key
+            ));
+        }
+        let engine: DatabaseEngine = engine.parse()?;
+        let provisioner = self.provisioner(engine)?;
+
+        let _guard = self.provision_lock.lock().await;
+        if let Some(rejection) = self.check_server_capacity(engine).await? {
+            return Err(rejection.into());
+        }
+        if provisioner.database_exists(&db_name).await? {
+            return Err(anyhow::anyhow!("数据库 {} 已存在，无法恢复", db_name));
+        }
+        if provisioner.user_exists(&username).await? {
+            return Err(anyhow::anyhow!("用户 {} 已存在，无法恢复", username));
+        }
+
+        // 失败时按原样写回记录
+        let snapshot = sqlx::query_as::<
+            _,
+            (
+                String,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+                bool,
+            ),
+        >(
+            "SELECT status, deleted_at, deletion_reason, archived_at, locked_at, expires_at, quota_exceeded FROM applicants WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+
+        provisioner.move_database(&archive_name, &db_name).await?;
+        let profile = profile.unwrap_or_else(|| self.default_profile());
+        let mut user_created = false;
+        let restored = async {
+            let limits = self.record_resource_limits(id).await?;
+            provisioner
+                .create_user(&username, password, &limits)
+                .await?;
+            user_created = true;
+            if let Err(e) = provisioner
+                .revoke_privileges(None, &username, DANGEROUS_PRIVILEGES)
+                .await
+            {
+                warn!("撤销危险权限时出现警告 (可忽略): {}", e);
+            }
+            self.mark_restored(id).await?;
+            self.regrant_profile(identity_key, &profile).await?;
+            anyhow::Ok(())
+        }
+        .await;
+
+        if let Err(e) = restored {
+            error!(
+                "恢复用户 {} 失败，数据库移回 {}: {}",
+                identity_key, archive_name, e
+            );
+            if user_created && let Err(drop_err) = provisioner.drop_user(&username).await {
+                error!("删除用户 {} 失败: {}", username, drop_err);
+            }
+            self.drop_profile_roles(provisioner.as_ref(), engine, &db_name)
+                .await;
+            if let Err(move_err) = provisioner.move_database(&db_name, &archive_name).await {
+                error!(
+                    "将数据库 {} 移回 {} 失败: {}",
+                    db_name, archive_name, move_err
+                );
+            }
+            let (status, deleted_at, deletion_reason, archived_at, locked_at, expires_at, quota) =
+                snapshot;
+            if let Err(record_err) = sqlx::query(
+                r#"
+                UPDATE applicants SET
+                    status = ?, deleted_at = ?, deletion_reason = ?, archive_name = ?,
+                    archived_at = ?, locked_at = ?, expires_at = ?, privilege_role = NULL,
+                    quota_exceeded = ?
+                WHERE id = ?
+                "#,
+            )
+            .bind(status)
+            .bind(deleted_at)
+            .bind(deletion_reason)
+            .bind(&archive_name)
+            .bind(archived_at)
+            .bind(locked_at)
+            .bind(expires_at)
+            .bind(quota)
+            .bind(id)
+            .execute(&self.sqlite_pool)
+            .await
+            {
+                error!("写回用户 {} 的记录失败: {}", identity_key, record_err);
+            }
+            return Err(e);
+        }
+
+        // 申请时创建的数据库已恢复，命名数据库逐个恢复，失败的保留在归档库中
+        let named = self
+            .restore_named_databases(identity_key, provisioner.as_ref())
+            .await?;
+        if named > 0
+            && let Err(e) = self.regrant_profile(identity_key, &profile).await
+        {
+            warn!("用户 {} 的命名数据库恢复后授权失败: {}", identity_key, e);
+        }
+        self.apply_default_expiry(identity_key).await?;
+
+        self.record_history(
+            identity_key,
+            "restored",
+            Some(&format!("{} 已从 {} 恢复", db_name, archive_name)),
+        )
+        .await;
+        info!("用户 {} 已从 {} 恢复", identity_key, archive_name);
+        Ok(provisioner.credentials(&db_name, &username, password))
+    }
+
+    /// 清除保留期已结束的已删除数据库
+    async fn purge_deleted(&self, result: &mut LifecycleResult) -> Result<()> {
+        let retention = format!("-{} days", self.lifecycle.deletion_retention_days);
+        let purgeable = sqlx::query_as::<_, (String, String)>(
+            "SELECT identity_key, archive_name FROM applicants WHERE status = 'deleted' AND archive_name IS NOT NULL AND deleted_at <= datetime('now', ?)",
+        )
+        .bind(&retention)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        for (identity_key, archive_name) in purgeable {
+            let purged = async {
+                let provisioner = self.provisioner(self.resolve_engine(&identity_key).await?)?;
+                provisioner.drop_database(&archive_name).await?;
+                sqlx::query(
+                    "UPDATE applicants SET archive_name = NULL WHERE identity_key = ? AND archive_name = ? AND status = 'deleted'",
+                )
+                .bind(&identity_key)
+                .bind(&archive_name)
+                .execute(&self.sqlite_pool)
+                .await?;
+                anyhow::Ok(())
+            }
+            .await;
+
+            match purged {
+                Ok(()) => {
+                    info!("用户 {} 的数据库 {} 已清除", identity_key, archive_name);
+                    self.record_history(&identity_key, "purged", Some(&archive_name))
+                        .await;
+                    result.purged.push(identity_key);
+                }
+                Err(e) => result.errors.push(format!("{}: {}", identity_key, e)),
+            }
+        }
+        Ok(())
+    }
+
+    // 账号停用
+
+    /// 停用用户：锁定账号（包括团队数据库上的用户）并断开所有会话，数据库和权限保持不变，返回断开的会话数
+    ///
+    /// 用于违规调查，停用期间不参与有效期和存储配额检查。
+    pub async fn suspend_user(&self, identity_key: &str, reason: &str) -> Result<u32> {
+        let username: Option<String> = sqlx::query_scalar(
+            "SELECT db_user FROM applicants WHERE identity_key = ? AND status = 'success'",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+        let Some(username) = username else {
+            return Err(anyhow::anyhow!(
+                "用户 {} 不存在或不是正常状态",
+                identity_key
+            ));
+        };
+
+        // 先锁定再断开会话，避免断开后立即重新登录
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        provisioner.lock_user(&username).await?;
+        let killed = provisioner.kill_sessions(&username).await?
+            + self.lock_team_member_users(identity_key).await?;
+
+        sqlx::query(
+            "UPDATE applicants SET status = 'suspended', suspended_at = CURRENT_TIMESTAMP, suspension_reason = ? WHERE identity_key = ? AND status = 'success'",
+        )
+        .bind(reason)
+        .bind(identity_key)
+        .execute(&self.sqlite_pool)
+        .await?;
+
+        self.record_history(identity_key, "suspended", Some(reason))
+            .await;
+        info!(
+            "用户 {} 已停用，断开 {} 个会话，原因: {}",
+            identity_key, killed, reason
+        );
+        Ok(killed)
+    }
+
+    /// 恢复已停用的用户
+    pub async fn unsuspend_user(&self, identity_key: &str) -> Result<()> {
+        let username: Option<String> = sqlx::query_scalar(
+            "SELECT db_user FROM applicants WHERE identity_key = ? AND status = 'suspended'",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+        let Some(username) = username else {
+            return Err(anyhow::anyhow!("用户 {} 不存在或未被停用", identity_key));
+        };
+
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        provisioner.unlock_user(&username).await?;
+        self.unlock_team_member_users(identity_key).await?;
+
+        sqlx::query(
+            "UPDATE applicants SET status = 'success', suspended_at = NULL, suspension_reason = NULL WHERE identity_key = ? AND status = 'suspended'",
+        )
+        .bind(identity_key)
+        .execute(&self.sqlite_pool)
+        .await?;
+
+        self.record_history(identity_key, "unsuspended", None).await;
+        info!("用户 {} 已恢复", identity_key);
+        Ok(())
+    }
+
+    // 数据库有效期
+
+    /// 归档库名称，超长时截断并追加哈希后缀
+    ///
+    /// 与隔离库一样带上申请记录的 id，再次申请后归档不会与仍在保留期内的旧归档库重名。
+    pub fn archive_name_for(db_name: &str, id: i64) -> String {
+        Self::retained_name_for("arc", &format!("{}_{}", db_name, id))
+    }
+
+    /// 删除后保留期内的隔离库名称
+    ///
+    /// 带上申请记录的 id，同一用户在保留期内再次申请并删除时不会与上一次的隔离库重名。
+    pub fn quarantine_name_for(db_name: &str, id: i64) -> String {
+        Self::retained_name_for("del", &format!("{}_{}", db_name, id))
+    }
+
+    fn retained_name_for(prefix: &str, db_name: &str) -> String {
+        let name = format!("{}_{}", prefix, db_name);
+        if name.len() <= MAX_DATABASE_NAME_LEN {
+            return name;
+        }
+
+        let hash = format!("{:x}", Sha256::digest(db_name.as_bytes()));
+        let mut label = name;
+        label.truncate(MAX_DATABASE_NAME_LEN - NAME_HASH_LEN - 1);
+        format!("{}_{}", label, &hash[..NAME_HASH_LEN])
+    }
+
+    /// 按班级或部署默认值为新申请的数据库设置到期时间
+    async fn apply_default_expiry(&self, identity_key: &str) -> Result<()> {
+        let days = self.resolve_lifetime_days(identity_key).await?;
+        if days == 0 {
+            return Ok(());
+        }
+
+        sqlx::query(
+            "UPDATE applicants SET expires_at = datetime('now', ?) WHERE identity_key = ? AND status = 'success'",
+        )
+        .bind(format!("+{} days", days))
+        .bind(identity_key)
+        .execute(&self.sqlite_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 为班级设置数据库有效期，只影响之后申请的用户
+    pub async fn set_class_expiry(&self, class_info: &str, lifetime_days: u32) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO class_expiry_policies (class_info, lifetime_days) VALUES (?, ?)
+            ON CONFLICT(class_info) DO UPDATE SET
+                lifetime_days = excluded.lifetime_days,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(class_info)
+        .bind(lifetime_days as i64)
+        .execute(&self.sqlite_pool)
+        .await?;
+
+        info!(
+            "班级 {} 的数据库有效期已设置为 {} 天",
+            class_info, lifetime_days
+        );
+        Ok(())
+    }
+
+    /// 延长用户的数据库有效期，`days` 为 0 时取消有效期
+    ///
+    /// 已到期锁定但尚未归档的用户会被解锁并恢复为正常状态。
+    pub async fn extend_expiry(&self, identity_key: &str, days: u32) -> Result<ExpiryInfo> {
+        let record = sqlx::query_as::<_, (String, String)>(
+            "SELECT db_user, status FROM applicants WHERE identity_key = ? AND status IN ('success', 'expired')",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+        let Some((username, status)) = record else {
+            return Err(anyhow::anyhow!(
+                "用户 {} 不存在或数据库已归档",
+                identity_key
+            ));
+        };
+
+        if status == "expired" {
+            let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+            provisioner.unlock_user(&username).await?;
+            self.unlock_team_member_users(identity_key).await?;
+            info!("用户 {} 已延期并解锁", username);
+        }
+
+        let expires_at: Option<String> = if days == 0 {
+            sqlx::query_scalar(
+                "UPDATE applicants SET expires_at = NULL, locked_at = NULL, status = 'success' WHERE identity_key = ? AND status IN ('success', 'expired') RETURNING expires_at",
+            )
+            .bind(identity_key)
+            .fetch_one(&self.sqlite_pool)
+            .await?
+        } else {
+            sqlx::query_scalar(
+                r#"
+                UPDATE applicants SET
+                    expires_at = datetime(MAX(COALESCE(expires_at, datetime('now')), datetime('now')), ?),
+                    locked_at = NULL,
+                    status = 'success'
+                WHERE identity_key = ? AND status IN ('success', 'expired')
+                RETURNING expires_at
+                "#,
+            )
+            .bind(format!("+{} days", days))
+            .bind(identity_key)
+            .fetch_one(&self.sqlite_pool)
+            .await?
+        };
+
+        let detail = match &expires_at {
+            Some(expires_at) => format!("有效期延长至 {}", expires_at),
+            None => "已取消有效期".to_string(),
+        };
+        self.record_history(identity_key, "expiry_extended", Some(&detail))
+            .await;
+        info!("用户 {} {}", identity_key, detail);
+
+        Ok(ExpiryInfo {
+            identity_key: identity_key.to_string(),
+            status: "success".to_string(),
+            expires_at,
+        })
+    }
+
+    /// 处理到期的数据库
+    ///
+    /// 到期的用户账号被锁定并标记为 expired；锁定超过宽限期的数据库被归档，
+    /// 随后删除用户和原数据库并标记为 archived。每个用户单独处理，失败不影响其他用户。
+    pub async fn process_expirations(&self) -> Result<LifecycleResult> {
+        let mut result = LifecycleResult::default();
+
+        // 1. 锁定到期的用户
+        let due = sqlx::query_as::<_, (String, String)>(
+            "SELECT identity_key, db_user FROM applicants WHERE status = 'success' AND db_suffix IS NULL AND expires_at IS NOT NULL AND expires_at <= datetime('now')",
+        )
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        for (identity_key, username) in due {
+            let locked = async {
+                let provisioner = self.provisioner(self.resolve_engine(&identity_key).await?)?;
+                provisioner.lock_user(&username).await?;
+                self.lock_team_member_users(&identity_key).await?;
+                sqlx::query(
+                    "UPDATE applicants SET status = 'expired', locked_at = CURRENT_TIMESTAMP WHERE identity_key = ? AND status = 'success'",
+                )
+                .bind(&identity_key)
+                .execute(&self.sqlite_pool)
+                .await?;
+                anyhow::Ok(())
+            }
+            .await;
+
+            match locked {
+                Ok(()) => {
+                    info!("用户 {} 的数据库已到期，账号已锁定", identity_key);
+                    self.record_history(&identity_key, "expired", Some("账号已锁定"))
+                        .await;
+                    result.locked.push(identity_key);
+                }
+                Err(e) => result.errors.push(format!("{}: {}", identity_key, e)),
+            }
+        }
+
+        // 2. 归档宽限期已结束的数据库
+        let grace = format!("-{} days", self.lifecycle.grace_period_days);
+        // 命名数据库排在申请时创建的数据库之前，用户在最后一个数据库归档时删除
+        let archivable = sqlx::query_as::<_, (i64, String, String, String, bool)>(
+            "SELECT id, identity_key, db_name, db_user, db_suffix IS NULL FROM applicants WHERE status = 'expired' AND expires_at <= datetime('now', ?) ORDER BY identity_key, db_suffix IS NULL",
+        )
+        .bind(&grace)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        for (id, identity_key, db_name, username, primary) in archivable {
+            match self
+                .archive_applicant_database(id, &identity_key, &db_name, &username, primary)
+                .await
+            {
+                Ok(()) => {
+                    if primary {
+                        result.archived.push(identity_key);
+                    }
+                }
+                Err(e) => result.errors.push(format!("{}: {}", identity_key, e)),
+            }
+        }
+
+        // 3. 清除删除保留期已结束的数据库
+        self.purge_deleted(&mut result).await?;
+
+        if !result.locked.is_empty()
+            || !result.archived.is_empty()
+            || !result.purged.is_empty()
+            || !result.errors.is_empty()
+        {
+            info!(
+                "有效期检查完成: 锁定 {} 个, 归档 {} 个, 清除 {} 个, 失败 {} 个",
+                result.locked.len(),
+                result.archived.len(),
+                result.purged.len(),
+                result.errors.len()
+            );
+        }
+        Ok(result)
+    }
+
+    /// 将数据库移入归档库并标记为 archived，申请时创建的数据库（`primary`）同时删除用户并将其移出团队
+    ///
+    /// 归档库名在删除用户之前记录，记录失败时把数据库移回原处，不会留下没有记录的归档库。
+    async fn archive_applicant_database(
+        &self,
+        id: i64,
+        identity_key: &str,
+        db_name: &str,
+        username: &str,
+        primary: bool,
+    ) -> Result<()> {
+        let archive_name = Self::archive_name_for(db_name, id);
+        let engine = self.resolve_engine(identity_key).await?;
+        let provisioner = self.provisioner(engine)?;
+        provisioner.move_database(db_name, &archive_name).await?;
+        let recorded = sqlx::query(
+            "UPDATE applicants SET status = 'archived', archived_at = CURRENT_TIMESTAMP, archive_name = ? WHERE id = ? AND status IN ('success', 'suspended', 'expired')",
+        )
+        .bind(&archive_name)
+        .bind(id)
+        .execute(&self.sqlite_pool)
+        .await;
+        if let Err(e) = recorded {
+            if let Err(undo_error) = provisioner.move_database(&archive_name, db_name).await {
+                error!(
+                    "记录归档库失败后无法移回数据库 {}，数据仍在 {} 中: {}",
+                    db_name, archive_name, undo_error
+                );
+            }
+            return Err(e.into());
+        }
+
+        if primary {
+            provisioner.drop_user(username).await?;
+            self.leave_all_teams(identity_key, "的数据库已归档").await?;
+        }
+        self.drop_profile_roles(provisioner.as_ref(), engine, db_name)
+            .await;
+        provisioner.flush_privileges().await?;
+
+        info!(
+            "用户 {} 的数据库 {} 已归档到 {}",
+            identity_key, db_name, archive_name
+        );
+        self.record_history(
+            identity_key,
+            "archived",
+            Some(&format!("{} 已归档到 {}", db_name, archive_name)),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// 定期有效期检查的间隔，未启用时返回 None
+    pub fn lifecycle_check_interval(&self) -> Option<std::time::Duration> {
+        match self.lifecycle.check_interval_secs {
+            0 => None,
+            secs => Some(std::time::Duration::from_secs(secs as u64)),
+        }
+    }
+
+    // 资源限制管理
+
+    /// 获取用户当前生效的资源限制（未单独设置的项使用默认值）
+    pub async fn get_resource_limits(&self, identity_key: &str) -> Result<ResourceLimits> {
+        let id: i64 = sqlx::query_scalar(
+            "SELECT id FROM applicants WHERE identity_key = ? AND status = 'success' AND db_suffix IS NULL ORDER BY id DESC LIMIT 1",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        self.record_resource_limits(id).await
+    }
+
+    /// 读取一条申请记录中保存的资源限制，未设置的项使用默认值
+    async fn record_resource_limits(&self, id: i64) -> Result<ResourceLimits> {
+        let row = sqlx::query_as::<_, (Option<i64>, Option<i64>, Option<i64>)>(
+            "SELECT max_user_connections, max_queries_per_hour, max_updates_per_hour FROM applicants WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+
+        let value = |stored: Option<i64>, default: u32| {
+            stored
+                .and_then(|v| u32::try_from(v).ok())
+                .unwrap_or(default)
+        };
+        Ok(ResourceLimits {
+            max_user_connections: value(row.0, self.resource_limits.max_user_connections),
+            max_queries_per_hour: value(row.1, self.resource_limits.max_queries_per_hour),
+            max_updates_per_hour: value(row.2, self.resource_limits.max_updates_per_hour),
+        })
+    }
+
+    /// 修改用户的资源限制，未提供的项保持当前值
+    pub async fn update_resource_limits(
+        &self,
+        identity_key: &str,
+        request: &UpdateResourceLimitsRequest,
+    ) -> Result<ResourceLimits> {
+        let current = self.get_resource_limits(identity_key).await?;
+        let limits = ResourceLimits {
+            max_user_connections: request
+                .max_user_connections
+                .unwrap_or(current.max_user_connections),
+            max_queries_per_hour: request
+                .max_queries_per_hour
+                .unwrap_or(current.max_queries_per_hour),
+            max_updates_per_hour: request
+                .max_updates_per_hour
+                .unwrap_or(current.max_updates_per_hour),
+        };
+
+        let (_, username) = self.resource_names(identity_key).await?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        provisioner
+            .alter_resource_limits(&username, &limits)
+            .await?;
+
+        sqlx::query(
+            "UPDATE applicants SET max_user_connections = ?, max_queries_per_hour = ?, max_updates_per_hour = ? WHERE identity_key = ? AND status = 'success'",
+        )
+        .bind(limits.max_user_connections as i64)
+        .bind(limits.max_queries_per_hour as i64)
+        .bind(limits.max_updates_per_hour as i64)
+        .bind(identity_key)
+        .execute(&self.sqlite_pool)
+        .await?;
+
+        let detail = format!(
+            "最大连接数 {}, 每小时查询 {}, 每小时更新 {}",
+            limits.max_user_connections, limits.max_queries_per_hour, limits.max_updates_per_hour
+        );
+        self.record_history(identity_key, "limits_changed", Some(&detail))
+            .await;
+        info!("用户 {} 的资源限制已修改: {}", username, detail);
+        Ok(limits)
+    }
+
+    // 申请记录历史
+
+    /// 记录申请记录的历史事件，写入失败只记录警告，不影响主流程
+    pub async fn record_history(&self, identity_key: &str, event: &str, detail: Option<&str>) {
+        if let Err(e) = sqlx::query(
+            "INSERT INTO applicant_history (identity_key, event, detail) VALUES (?, ?, ?)",
+        )
+        .bind(identity_key)
+        .bind(event)
+        .bind(detail)
+        .execute(&self.sqlite_pool)
+        .await
+        {
+            warn!("记录历史事件失败: {} {}: {}", identity_key, event, e);
+        }
+    }
+
+    /// 获取身份标识的历史事件（按时间顺序）
+    pub async fn get_applicant_history(
+        &self,
+        identity_key: &str,
+    ) -> Result<Vec<ApplicantHistoryEntry>> {
+        let entries = sqlx::query_as::<_, ApplicantHistoryEntry>(
+            "SELECT id, identity_key, event, detail, created_at FROM applicant_history WHERE identity_key = ? ORDER BY id",
+        )
+        .bind(identity_key)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+        Ok(entries)
+    }
+
+    /// 用给定密码重新生成身份标识的连接信息，不修改数据库中的任何内容
+    pub async fn credentials_for(
+        &self,
+        identity_key: &str,
+        password: &str,
+    ) -> Result<DatabaseCredentials> {
+        let (db_name, username) = self
+            .stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        Ok(provisioner.credentials(&db_name, &username, password))
+    }
+
+    // 数据库导出
+
+    /// 导出身份标识对应的数据库
+    ///
+    /// SQL 片段在后台任务中生成，调用方从返回的接收端按顺序读取；
+    /// 导出出错时接收端收到错误后结束。同时进行的导出数和单次导出的时长都有上限，
+    /// 避免慢速或大量下载长期占用数据库连接。
+    pub async fn dump_database(&self, identity_key: &str) -> Result<DatabaseDump> {
+        let (db_name, _) = self
+            .stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let provisioner = Arc::clone(self.provisioner(self.resolve_engine(identity_key).await?)?);
+        if !provisioner.database_exists(&db_name).await? {
+            return Err(anyhow::anyhow!("数据库 {} 不存在", db_name));
+        }
+
+        let slot = Arc::clone(&self.dump_slots)
+            .try_acquire_owned()
+            .map_err(|_| anyhow::anyhow!("同时进行的导出过多，请稍后再试"))?;
+
+        let (sender, chunks) = tokio::sync::mpsc::channel(DUMP_CHANNEL_CAPACITY);
+        let task_db_name = db_name.clone();
+        tokio::spawn(async move {
+            let max_duration = std::time::Duration::from_secs(dump::DUMP_MAX_DURATION_SECS);
+            let result = tokio::time::timeout(
+                max_duration,
+                provisioner.dump_database(&task_db_name, &sender),
+            )
+            .await
+            .unwrap_or_else(|_| {
+                Err(anyhow::anyhow!(
+                    "导出超过 {} 秒，已终止",
+                    dump::DUMP_MAX_DURATION_SECS
+                ))
+            });
+            drop(slot);
+            if let Err(e) = result {
+                error!("导出数据库 {} 失败: {}", task_db_name, e);
+                let send_timeout = std::time::Duration::from_secs(dump::DUMP_SEND_TIMEOUT_SECS);
+                let _ = tokio::time::timeout(send_timeout, sender.send(Err(e))).await;
+            }
+        });
+
+        self.record_history(identity_key, "dumped", Some(&db_name))
+            .await;
+        info!("开始导出用户 {} 的数据库 {}", identity_key, db_name);
+        Ok(DatabaseDump {
+            file_name: format!(
+                "{}_{}.sql",
+                db_name,
+                chrono::Utc::now().format("%Y%m%d%H%M%S")
+            ),
+            chunks,
+        })
+    }
+
+    /// 学生导出自己的数据库，以学生自己的用户名和密码登录校验身份
+    ///
+    /// 密码错误时返回 `Ok(None)`。
+    pub async fn dump_own_database(
+        &self,
+        identity_key: &str,
+        password: &str,
+    ) -> Result<Option<DatabaseDump>> {
+        let (db_name, username) = self
+            .stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        if !provisioner
+            .verify_password(&db_name, &username, password)
+            .await?
+        {
+            warn!("用户 {} 导出数据库时密码校验失败", identity_key);
+            return Ok(None);
+        }
+        self.dump_database(identity_key).await.map(Some)
+    }
+
+    // SQL 导入
+
+    /// 上传 SQL 文件的大小上限（字节）
+    pub fn import_upload_limit(&self) -> usize {
+        self.import.max_upload_mb as usize * 1024 * 1024
+    }
+
+    /// 将上传的 SQL 文件导入学生自己的数据库
+    ///
+    /// 语句以学生自己的用户名和密码执行，权限配置档照常生效。密码错误时返回 `Ok(None)`。
+    pub async fn import_sql(
+        &self,
+        identity_key: &str,
+        password: &str,
+        data: &[u8],
+    ) -> Result<Option<ImportReport>> {
+        let (db_name, username) = self
+            .stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        if !provisioner
+            .verify_password(&db_name, &username, password)
+            .await?
+        {
+            warn!("用户 {} 导入 SQL 时密码校验失败", identity_key);
+            return Ok(None);
+        }
+
+        let sql = import::decode_upload(data, self.import.max_sql_mb as usize * 1024 * 1024)?;
+        let statements = import::split_statements(&sql);
+        let max_errors = (self.import.max_errors as usize).max(1);
+        let results = provisioner
+            .execute_as_user(&db_name, &username, password, &statements, max_errors)
+            .await?;
+
+        let errors: Vec<ImportStatementError> = results
+            .iter()
+            .zip(&statements)
+            .enumerate()
+            .filter_map(|(index, (result, statement))| {
+                result.as_ref().err().map(|error| ImportStatementError {
+                    index: index + 1,
+                    line: statement.line,
+                    statement: statement.summary(),
+                    error: error.clone(),
+                })
+            })
+            .collect();
+        let report = ImportReport {
+            total_statements: statements.len(),
+            succeeded: results.len() - errors.len(),
+            failed: errors.len(),
+            aborted: results.len() < statements.len(),
+            errors,
+        };
+
+        self.record_history(
+            identity_key,
+            "imported",
+            Some(&format!(
+                "{} 条语句, 成功 {}, 失败 {}",
+                report.total_statements, report.succeeded, report.failed
+            )),
+        )
+        .await;
+        info!(
+            "用户 {} 导入 SQL 完成: 共 {} 条语句, 成功 {}, 失败 {}{}",
+            identity_key,
+            report.total_statements,
+            report.succeeded,
+            report.failed,
+            if report.aborted {
+                "（失败过多，已停止）"
+            } else {
+                ""
+            }
+        );
+        Ok(Some(report))
+    }
+
+    // 结构模板
+
+    /// 模板、作业等的名称只允许字母、数字、下划线和连字符
+    fn is_valid_catalog_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= 64
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    async fn ensure_template_exists(&self, template: Option<&str>) -> Result<()> {
+        if let Some(template) = template {
+            let exists: i64 =
+                sqlx::query_scalar("SELECT COUNT(*) FROM schema_templates WHERE name = ?")
+                    .bind(template)
+                    .fetch_one(&self.sqlite_pool)
+                    .await?;
+            if exists == 0 {
+                return Err(anyhow::anyhow!("结构模板 {} 不存在", template));
+            }
+        }
+        Ok(())
+    }
+
+    /// 获取所有结构模板（不含脚本内容）
+    pub async fn list_schema_templates(&self) -> Result<Vec<SchemaTemplateSummary>> {
+        let templates = sqlx::query_as::<_, SchemaTemplateSummary>(
+            "SELECT name, description, statement_count, created_at, updated_at FROM schema_templates ORDER BY name",
+        )
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+        Ok(templates)
+    }
+
+    /// 获取结构模板
+    pub async fn get_schema_template(&self, name: &str) -> Result<SchemaTemplate> {
+        sqlx::query_as::<_, SchemaTemplate>(
+            "SELECT name, description, script, statement_count, created_at, updated_at FROM schema_templates WHERE name = ?",
+        )
+        .bind(name)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("结构模板 {} 不存在", name))
+    }
+
+    /// 创建或更新结构模板，只影响之后创建的数据库
+    pub async fn save_schema_template(
+        &self,
+        name: &str,
+        description: Option<&str>,
+        script: &str,
+    ) -> Result<SchemaTemplateSummary> {
+        if !Self::is_valid_catalog_name(name) {
+            return Err(anyhow::anyhow!(
+                "模板名称无效: {}（只允许字母、数字、下划线和连字符）",
+                name
+            ));
+        }
+        let statement_count = import::split_statements(script).len();
+        if statement_count == 0 {
+            return Err(anyhow::anyhow!("模板脚本无效: 没有可执行的语句"));
+        }
+
+        let template = sqlx::query_as::<_, SchemaTemplateSummary>(
+            r#"
+            INSERT INTO schema_templates (name, description, script, statement_count) VALUES (?, ?, ?, ?)
+            ON CONFLICT(name) DO UPDATE SET
+                description = excluded.description,
+                script = excluded.script,
+                statement_count = excluded.statement_count,
+                updated_at = CURRENT_TIMESTAMP
+            RETURNING name, description, statement_count, created_at, updated_at
+            "#,
+        )
+        .bind(name)
+        .bind(description)
+        .bind(script)
+        .bind(statement_count as i64)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+
+        info!("结构模板 {} 已保存，共 {} 条语句", name, statement_count);
+        Ok(template)
+    }
+
+    /// 删除结构模板，仍被用户或班级引用时拒绝删除
+    pub async fn delete_schema_template(&self, name: &str) -> Result<()> {
+        let references: i64 = sqlx::query_scalar(
+            r#"
+            SELECT (SELECT COUNT(*) FROM student_ids WHERE schema_template = ?)
+                 + (SELECT COUNT(*) FROM class_schema_templates WHERE template_name = ?)
+            "#,
+        )
+        .bind(name)
+        .bind(name)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        if references > 0 {
+            return Err(anyhow::anyhow!(
+                "结构模板 {} 仍被 {} 个用户或班级使用",
+                name,
+                references
+            ));
+        }
+
+        let deleted = sqlx::query("DELETE FROM schema_templates WHERE name = ?")
+            .bind(name)
+            .execute(&self.sqlite_pool)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(anyhow::anyhow!("结构模板 {} 不存在", name));
+        }
+
+        info!("结构模板 {} 已删除", name);
+        Ok(())
+    }
+
+    /// 为白名单中的用户指定结构模板，`None` 表示清除，之后使用班级的模板
+    pub async fn set_student_template(
+        &self,
+        identity_key: &str,
+        template: Option<&str>,
+    ) -> Result<()> {
+        self.ensure_template_exists(template).await?;
+
+        let updated = sqlx::query(
+            "UPDATE student_ids SET schema_template = ?, updated_at = CURRENT_TIMESTAMP WHERE student_id = ?",
+        )
+        .bind(template)
+        .bind(identity_key)
+        .execute(&self.sqlite_pool)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(anyhow::anyhow!("用户 {} 不存在", identity_key));
+        }
+        Ok(())
+    }
+
+    /// 为班级指定结构模板，`None` 表示清除，只影响之后申请的用户
+    pub async fn set_class_template(&self, class_info: &str, template: Option<&str>) -> Result<()> {
+        match template {
+            Some(template) => {
+                self.ensure_template_exists(Some(template)).await?;
+                sqlx::query(
+                    r#"
+                    INSERT INTO class_schema_templates (class_info, template_name) VALUES (?, ?)
+                    ON CONFLICT(class_info) DO UPDATE SET
+                        template_name = excluded.template_name,
+                        updated_at = CURRENT_TIMESTAMP
+                    "#,
+                )
+                .bind(class_info)
+                .bind(template)
+                .execute(&self.sqlite_pool)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM class_schema_templates WHERE class_info = ?")
+                    .bind(class_info)
+                    .execute(&self.sqlite_pool)
+                    .await?;
+            }
+        }
+        info!("班级 {} 的结构模板已设置为 {:?}", class_info, template);
+        Ok(())
+    }
+
+    /// 解析身份标识应使用的结构模板，优先使用白名单记录中指定的模板，其次是班级的模板
+    async fn resolve_template(&self, identity_key: &str) -> Result<Option<String>> {
+        let template: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(NULLIF(s.schema_template, ''), c.template_name)
+            FROM student_ids s
+            LEFT JOIN class_schema_templates c ON c.class_info = s.class_info
+            WHERE s.student_id = ?
+            "#,
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .flatten();
+        Ok(template)
+    }
+
+    /// 在数据库中执行结构模板的脚本
+    async fn apply_schema_template(
+        &self,
+        provisioner: &dyn Provisioner,
+        db_name: &str,
+        template: &str,
+    ) -> Result<()> {
+        let template = self.get_schema_template(template).await?;
+        let statements = import::split_statements(&template.script);
+        provisioner.execute_script(db_name, &statements).await
+    }
+
+    // 作业评分
+
+    fn assignment_from_row(
+        (name, title, description, checks, created_at, updated_at): (
+            String,
+            String,
+            Option<String>,
+            String,
+            String,
+            String,
+        ),
+    ) -> Result<Assignment> {
+        let checks: Vec<AssignmentCheck> = serde_json::from_str(&checks)?;
+        let total_points = checks.iter().map(|check| check.points).sum();
+        Ok(Assignment {
+            name,
+            title,
+            description,
+            checks,
+            total_points,
+            created_at,
+            updated_at,
+        })
+    }
+
+    /// 获取所有作业
+    pub async fn list_assignments(&self) -> Result<Vec<Assignment>> {
+        let rows = sqlx::query_as::<_, (String, String, Option<String>, String, String, String)>(
+            "SELECT name, title, description, checks, created_at, updated_at FROM assignments ORDER BY created_at, name",
+        )
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+        rows.into_iter().map(Self::assignment_from_row).collect()
+    }
+
+    /// 获取作业
+    pub async fn get_assignment(&self, name: &str) -> Result<Assignment> {
+        let row = sqlx::query_as::<_, (String, String, Option<String>, String, String, String)>(
+            "SELECT name, title, description, checks, created_at, updated_at FROM assignments WHERE name = ?",
+        )
+        .bind(name)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("作业 {} 不存在", name))?;
+        Self::assignment_from_row(row)
+    }
+
+    /// 创建或更新作业，已有的成绩保留到下次评分
+    pub async fn save_assignment(
+        &self,
+        name: &str,
+        title: &str,
+        description: Option<&str>,
+        checks: &[AssignmentCheck],
+    ) -> Result<Assignment> {
+        if !Self::is_valid_catalog_name(name) {
+            return Err(anyhow::anyhow!(
+                "作业名称无效: {}（只允许字母、数字、下划线和连字符）",
+                name
+            ));
+        }
+        if title.trim().is_empty() {
+            return Err(anyhow::anyhow!("作业标题无效: 标题不能为空"));
+        }
+        grader::validate_checks(checks)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO assignments (name, title, description, checks) VALUES (?, ?, ?, ?)
+            ON CONFLICT(name) DO UPDATE SET
+                title = excluded.title,
+                description = excluded.description,
+                checks = excluded.checks,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(name)
+        .bind(title.trim())
+        .bind(description)
+        .bind(serde_json::to_string(checks)?)
+        .execute(&self.sqlite_pool)
+        .await?;
+
+        info!("作业 {} 已保存，共 {} 个检查项", name, checks.len());
+        self.get_assignment(name).await
+    }
+
+    /// 删除作业及其成绩
+    pub async fn delete_assignment(&self, name: &str) -> Result<()> {
+        let mut tx = self.sqlite_pool.begin().await?;
+        let deleted = sqlx::query("DELETE FROM assignments WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(anyhow::anyhow!("作业 {} 不存在", name));
+        }
+        sqlx::query("DELETE FROM assignment_results WHERE assignment_name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        info!("作业 {} 已删除", name);
+        Ok(())
+    }
+
+    /// 以只有 SELECT 权限的临时用户在学生的数据库中执行检查，结束后删除该用户
+    ///
+    /// 评分查询不以管理员身份执行，读取服务器文件、访问其他数据库等都会被数据库拒绝；
+    /// [`grader::validate_query`] 只作为快速的预先检查。
+    async fn run_checks_as_grader(
+        &self,
+        provisioner: &dyn Provisioner,
+        db_name: &str,
+        checks: &[AssignmentCheck],
+    ) -> Result<Vec<CheckResult>> {
+        let username = format!(
+            "{}{}",
+            GRADER_USERNAME_PREFIX,
+            crate::utils::generate_reset_code(GRADER_USERNAME_RANDOM_LEN).to_ascii_lowercase()
+        );
+        if provisioner.user_exists(&username).await? {
+            return Err(anyhow::anyhow!("评分用户 {} 已存在", username));
+        }
+        let password = crate::utils::generate_secure_password(24);
+        provisioner
+            .create_user(&username, &password, &self.resource_limits)
+            .await?;
+
+        let results = async {
+            provisioner
+                .grant_privileges(db_name, &username, GRADER_PRIVILEGES)
+                .await?;
+            provisioner.flush_privileges().await?;
+            anyhow::Ok(Self::run_checks(provisioner, db_name, &username, &password, checks).await)
+        }
+        .await;
+
+        if let Err(e) = provisioner.drop_user(&username).await {
+            error!("删除评分用户 {} 失败: {}", username, e);
+        }
+        if let Err(e) = provisioner.flush_privileges().await {
+            warn!("刷新权限时出现警告: {}", e);
+        }
+        results
+    }
+
+    /// 在学生的数据库中逐项执行检查，查询以 `username` 的身份执行
+    async fn run_checks(
+        provisioner: &dyn Provisioner,
+        db_name: &str,
+        username: &str,
+        password: &str,
+        checks: &[AssignmentCheck],
+    ) -> Vec<CheckResult> {
+        let mut results = Vec::with_capacity(checks.len());
+        for check in checks {
+            let outcome = match (&check.query, &check.table) {
+                (Some(query), _) => match grader::validate_query(query) {
+                    Err(reason) => Err(format!("查询被拒绝: 查询{}", reason)),
+                    Ok(()) => match provisioner
+                        .query_rows(db_name, username, password, query)
+                        .await
+                    {
+                        Ok(rows) => grader::compare_rows(
+                            &rows,
+                            check.expected_rows.as_ref().unwrap_or(&Vec::new()),
+                            check.ordered,
+                        ),
+                        Err(e) => Err(format!("查询失败: {}", e)),
+                    },
+                },
+                (None, Some(table)) => match provisioner.table_columns(db_name, table).await {
+                    Ok(columns) => grader::check_columns(table, columns.as_deref(), &check.columns),
+                    Err(e) => Err(format!("读取表结构失败: {}", e)),
+                },
+                (None, None) => Err("检查项没有查询或表".to_string()),
+            };
+            results.push(grader::check_result(check, outcome));
+        }
+        results
+    }
+
+    /// 为学生评分并保存成绩，可以按身份标识或班级筛选
+    ///
+    /// 查询以只有 SELECT 权限的临时用户在只读事务中执行，重新评分会覆盖之前的成绩。
+    pub async fn grade_assignment(
+        &self,
+        name: &str,
+        identity_key: Option<&str>,
+        class_info: Option<&str>,
+    ) -> Result<GradingResult> {
+        let assignment = self.get_assignment(name).await?;
+        let max_score = assignment.total_points;
+
+        let targets = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT a.identity_key, a.db_name FROM applicants a
+            LEFT JOIN student_ids s ON s.student_id = a.identity_key
+            WHERE a.status IN ('success', 'suspended', 'expired') AND a.db_name != '' AND a.db_suffix IS NULL
+              AND (?1 IS NULL OR a.identity_key = ?1)
+              AND (?2 IS NULL OR s.class_info = ?2)
+            ORDER BY a.identity_key
+            "#,
+        )
+        .bind(identity_key)
+        .bind(class_info)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+        if let Some(identity_key) = identity_key
+            && targets.is_empty()
+        {
+            return Err(anyhow::anyhow!(
+                "用户 {} 不存在或尚未创建数据库",
+                identity_key
+            ));
+        }
+
+        let mut graded_count = 0;
+        let mut errors = Vec::new();
+        for (identity_key, db_name) in targets {
+            let provisioner = match self.resolve_engine(&identity_key).await {
+                Ok(engine) => match self.provisioner(engine) {
+                    Ok(provisioner) => provisioner,
+                    Err(e) => {
+                        errors.push(format!("{}: {}", identity_key, e));
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    errors.push(format!("{}: {}", identity_key, e));
+                    continue;
+                }
+            };
+            match provisioner.database_exists(&db_name).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    errors.push(format!("{}: 数据库 {} 不存在", identity_key, db_name));
+                    continue;
+                }
+                Err(e) => {
+                    errors.push(format!("{}: {}", identity_key, e));
+                    continue;
+                }
+            }
+
+            let results = match self
+                .run_checks_as_grader(provisioner.as_ref(), &db_name, &assignment.checks)
+                .await
+            {
+                Ok(results) => results,
+                Err(e) => {
+                    errors.push(format!("{}: {}", identity_key, e));
+                    continue;
+                }
+            };
+            let score: u32 = results.iter().map(|result| result.points).sum();
+            let saved = sqlx::query(
+                r#"
+                INSERT INTO assignment_results (assignment_name, identity_key, score, max_score, details)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT(assignment_name, identity_key) DO UPDATE SET
+                    score = excluded.score,
+                    max_score = excluded.max_score,
+                    details = excluded.details,
+                    graded_at = CURRENT_TIMESTAMP
+                "#,
+            )
+            .bind(name)
+            .bind(&identity_key)
+            .bind(score)
+            .bind(max_score)
+            .bind(serde_json::to_string(&results)?)
+            .execute(&self.sqlite_pool)
+            .await;
+            if let Err(e) = saved {
+                errors.push(format!("{}: 保存成绩失败: {}", identity_key, e));
+                continue;
+            }
+
+            self.record_history(
+                &identity_key,
+                "graded",
+                Some(&format!("作业 {}: {}/{}", name, score, max_score)),
+            )
+            .await;
+            graded_count += 1;
+        }
+
+        info!(
+            "作业 {} 评分完成: {} 名学生, {} 个错误",
+            name,
+            graded_count,
+            errors.len()
+        );
+        Ok(GradingResult {
+            graded_count,
+            errors,
+        })
+    }
+
+    /// 获取作业的成绩单，按身份标识排序
+    pub async fn get_gradebook(&self, name: &str) -> Result<Vec<GradeEntry>> {
+        self.get_assignment(name).await?;
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                Option<String>,
+                Option<String>,
+                u32,
+                u32,
+                String,
+                String,
+            ),
+        >(
+            r#"
+            SELECT r.identity_key, s.student_name, s.class_info, r.score, r.max_score, r.details, r.graded_at
+            FROM assignment_results r
+            LEFT JOIN student_ids s ON s.student_id = r.identity_key
+            WHERE r.assignment_name = ?
+            ORDER BY r.identity_key
+            "#,
+        )
+        .bind(name)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        rows.into_iter()
+            .map(
+                |(identity_key, student_name, class_info, score, max_score, details, graded_at)| {
+                    Ok(GradeEntry {
+                        identity_key,
+                        student_name,
+                        class_info,
+                        score,
+                        max_score,
+                        checks: serde_json::from_str(&details)?,
+                        graded_at,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// 导出作业的成绩单 CSV
+    pub async fn gradebook_csv(&self, name: &str) -> Result<String> {
+        let assignment = self.get_assignment(name).await?;
+        let entries = self.get_gradebook(name).await?;
+        Ok(grader::gradebook_csv(&assignment.checks, &entries))
+    }
+
+    // 教师账号
+
+    /// 教师账号对应的数据库用户名
+    fn teacher_username(name: &str) -> String {
+        format!("{}_{}", TEACHER_USERNAME_PREFIX, name)
+    }
+
+    /// 按引擎名称排序的供应后端，保证教师账号在各引擎上的操作顺序稳定
+    fn sorted_provisioners(&self) -> Vec<(DatabaseEngine, &Arc<dyn Provisioner>)> {
+        let mut provisioners: Vec<_> = self
+            .provisioners
+            .iter()
+            .map(|(engine, provisioner)| (*engine, provisioner))
+            .collect();
+        provisioners.sort_by_key(|(engine, _)| engine.to_string());
+        provisioners
+    }
+
+    /// 获取教师的数据库用户名，教师不存在时返回错误
+    async fn teacher_db_user(&self, name: &str) -> Result<String> {
+        sqlx::query_scalar("SELECT db_user FROM teachers WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.sqlite_pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("教师 {} 不存在", name))
+    }
+
+    /// 教师可以查看的学生数据库: (身份标识, 数据库名, 引擎)
+    async fn teacher_targets(&self, name: &str) -> Result<Vec<(String, String, String)>> {
+        let targets = sqlx::query_as(
+            r#"
+            SELECT a.identity_key, a.db_name, a.db_engine FROM applicants a
+            JOIN student_ids s ON s.student_id = a.identity_key
+            JOIN teacher_classes t ON t.class_info = s.class_info
+            WHERE t.teacher = ? AND a.status IN ('success', 'suspended', 'expired') AND a.db_name != ''
+            ORDER BY a.identity_key
+            "#,
+        )
+        .bind(name)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+        Ok(targets)
+    }
+
+    /// 获取所有教师账号
+    pub async fn list_teachers(&self) -> Result<Vec<Teacher>> {
+        let rows = sqlx::query_as::<_, (String, Option<String>, String, String)>(
+            "SELECT name, display_name, db_user, created_at FROM teachers ORDER BY name",
+        )
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        let mut teachers = Vec::with_capacity(rows.len());
+        for (name, display_name, username, created_at) in rows {
+            let classes: Vec<String> = sqlx::query_scalar(
+                "SELECT class_info FROM teacher_classes WHERE teacher = ? ORDER BY class_info",
+            )
+            .bind(&name)
+            .fetch_all(&self.sqlite_pool)
+            .await?;
+            let database_count = self.teacher_targets(&name).await?.len() as i32;
+            teachers.push(Teacher {
+                name,
+                display_name,
+                username,
+                classes,
+                database_count,
+                created_at,
+            });
+        }
+        Ok(teachers)
+    }
+
+    /// 创建教师账号
+    ///
+    /// 在每个已配置的引擎上创建同名的数据库用户，并授予任课班级学生数据库的只读权限。
+    /// 返回创建了用户的引擎和授权结果。
+    pub async fn create_teacher(
+        &self,
+        name: &str,
+        display_name: Option<&str>,
+        classes: &[String],
+        password: &str,
+    ) -> Result<(String, Vec<DatabaseEngine>, TeacherAccessResult)> {
+        let username = Self::teacher_username(name);
+        if !Self::is_valid_identifier(name) || !Self::is_valid_username(&username) {
+            return Err(anyhow::anyhow!(
+                "教师账号名称无效: 只能包含字母、数字和下划线，且以字母开头，不超过 {} 个字符",
+                MAX_USERNAME_LEN - TEACHER_USERNAME_PREFIX.len() - 1
+            ));
+        }
+        let classes = Self::normalize_classes(classes)?;
+
+        let exists: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM teachers WHERE name = ?) + (SELECT COUNT(*) FROM applicants WHERE db_user = ? AND status != 'failed')",
+        )
+        .bind(name)
+        .bind(&username)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        if exists > 0 {
+            return Err(anyhow::anyhow!("教师 {} 已存在或用户名已被占用", name));
+        }
+
+        let mut engines = Vec::new();
+        for (engine, provisioner) in self.sorted_provisioners() {
+            let created = async {
+                provisioner
+                    .create_user(&username, password, &self.resource_limits)
+                    .await?;
+                if let Err(e) = provisioner
+                    .revoke_privileges(None, &username, DANGEROUS_PRIVILEGES)
+                    .await
+                {
+                    warn!("撤销危险权限时出现警告 (可忽略): {}", e);
+                }
+                provisioner.flush_privileges().await
+            }
+            .await;
+            if let Err(e) = created {
+                error!("在 {} 上创建教师用户 {} 失败: {}", engine, username, e);
+                for (engine, provisioner) in self.sorted_provisioners() {
+                    if engines.contains(&engine)
+                        && let Err(drop_err) = provisioner.drop_user(&username).await
+                    {
+                        error!("删除教师用户 {} 失败: {}", username, drop_err);
+                    }
+                }
+                return Err(e);
+            }
+            engines.push(engine);
+        }
+
+        let mut tx = self.sqlite_pool.begin().await?;
+        sqlx::query("INSERT INTO teachers (name, display_name, db_user) VALUES (?, ?, ?)")
+            .bind(name)
+            .bind(display_name)
+            .bind(&username)
+            .execute(&mut *tx)
+            .await?;
+        for class_info in &classes {
+            sqlx::query("INSERT INTO teacher_classes (teacher, class_info) VALUES (?, ?)")
+                .bind(name)
+                .bind(class_info)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        let access = self
+            .sync_teacher_access(name, &username, Vec::new())
+            .await?;
+        info!(
+            "教师 {} 创建完成: 用户 {}, {} 个班级, 授权 {} 个数据库",
+            name,
+            username,
+            classes.len(),
+            access.granted
+        );
+        Ok((username, engines, access))
+    }
+
+    /// 去掉班级名称的首尾空白和重复项
+    fn normalize_classes(classes: &[String]) -> Result<Vec<String>> {
+        let mut normalized: Vec<String> = Vec::with_capacity(classes.len());
+        for class_info in classes {
+            let class_info = class_info.trim();
+            if class_info.is_empty() {
+                return Err(anyhow::anyhow!("班级信息无效: 不能为空"));
+            }
+            if !normalized.iter().any(|c| c == class_info) {
+                normalized.push(class_info.to_string());
+            }
+        }
+        Ok(normalized)
+    }
+
+    /// 按教师当前的任课班级授权，并撤销 `previous` 中不再需要的权限
+    async fn sync_teacher_access(
+        &self,
+        name: &str,
+        username: &str,
+        previous: Vec<(String, String, String)>,
+    ) -> Result<TeacherAccessResult> {
+        let targets = self.teacher_targets(name).await?;
+        let mut result = TeacherAccessResult::default();
+        let mut touched = HashSet::new();
+
+        for (identity_key, db_name, engine) in &previous {
+            if targets.iter().any(|(_, db, _)| db == db_name) {
+                continue;
+            }
+            let revoked = async {
+                let engine: DatabaseEngine = engine.parse()?;
+                touched.insert(engine);
+                self.provisioner(engine)?
+                    .revoke_privileges(Some(db_name), username, TEACHER_PRIVILEGES)
+                    .await
+            }
+            .await;
+            match revoked {
+                Ok(()) => result.revoked += 1,
+                Err(e) => result.errors.push(format!("{}: {}", identity_key, e)),
+            }
+        }
+
+        for (identity_key, db_name, engine) in &targets {
+            let granted = async {
+                let engine: DatabaseEngine = engine.parse()?;
+                touched.insert(engine);
+                self.provisioner(engine)?
+                    .grant_privileges(db_name, username, TEACHER_PRIVILEGES)
+                    .await
+            }
+            .await;
+            match granted {
+                Ok(()) => result.granted += 1,
+                Err(e) => result.errors.push(format!("{}: {}", identity_key, e)),
+            }
+        }
+
+        for engine in touched {
+            if let Ok(provisioner) = self.provisioner(engine)
+                && let Err(e) = provisioner.flush_privileges().await
+            {
+                result
+                    .errors
+                    .push(format!("{}: 刷新权限失败: {}", engine, e));
+            }
+        }
+        Ok(result)
+    }
+
+    /// 替换教师的任课班级，授予新班级学生数据库的只读权限并撤销不再任课班级的权限
+    pub async fn set_teacher_classes(
+        &self,
+        name: &str,
+        classes: &[String],
+    ) -> Result<TeacherAccessResult> {
+        let username = self.teacher_db_user(name).await?;
+        let classes = Self::normalize_classes(classes)?;
+        let previous = self.teacher_targets(name).await?;
+
+        let mut tx = self.sqlite_pool.begin().await?;
+        sqlx::query("DELETE FROM teacher_classes WHERE teacher = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        for class_info in &classes {
+            sqlx::query("INSERT INTO teacher_classes (teacher, class_info) VALUES (?, ?)")
+                .bind(name)
+                .bind(class_info)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        let result = self.sync_teacher_access(name, &username, previous).await?;
+        info!(
+            "教师 {} 的任课班级已更新: 授权 {}, 撤销 {}, {} 个错误",
+            name,
+            result.granted,
+            result.revoked,
+            result.errors.len()
+        );
+        Ok(result)
+    }
+
+    /// 重置教师在所有引擎上的密码
+    pub async fn reset_teacher_password(
+        &self,
+        name: &str,
+        password: &str,
+    ) -> Result<(String, Vec<DatabaseEngine>)> {
+        let username = self.teacher_db_user(name).await?;
+        let mut engines = Vec::new();
+        for (engine, provisioner) in self.sorted_provisioners() {
+            // 教师创建之后才配置的引擎上没有该用户，先补建
+            if !provisioner.user_exists(&username).await? {
+                provisioner
+                    .create_user(&username, password, &self.resource_limits)
+                    .await?;
+            }
+            provisioner.set_password(&username, password).await?;
+            engines.push(engine);
+        }
+        info!("教师 {} 的密码已重置", name);
+        Ok((username, engines))
+    }
+
+    /// 删除教师账号，删除数据库用户即撤销其所有权限
+    pub async fn delete_teacher(&self, name: &str) -> Result<()> {
+        let username = self.teacher_db_user(name).await?;
+        for (engine, provisioner) in self.sorted_provisioners() {
+            provisioner.kill_sessions(&username).await.ok();
+            provisioner.drop_user(&username).await.map_err(|e| {
+                anyhow::anyhow!("在 {} 上删除教师用户 {} 失败: {}", engine, username, e)
+            })?;
+            provisioner.flush_privileges().await?;
+        }
+
+        let mut tx = self.sqlite_pool.begin().await?;
+        sqlx::query("DELETE FROM teacher_classes WHERE teacher = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM teachers WHERE name = ?")
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        info!("教师 {} 已删除 (用户 {})", name, username);
+        Ok(())
+    }
+
+    /// 为新创建的学生数据库授予任课教师只读权限
+    async fn grant_teacher_access(
+        &self,
+        identity_key: &str,
+        engine: DatabaseEngine,
+        db_name: &str,
+    ) -> Result<()> {
+        let teachers: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT t.db_user FROM teachers t
+            JOIN teacher_classes c ON c.teacher = t.name
+            JOIN student_ids s ON s.class_info = c.class_info
+            WHERE s.student_id = ?
+            ORDER BY t.db_user
+            "#,
+        )
+        .bind(identity_key)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+        if teachers.is_empty() {
+            return Ok(());
+        }
+
+        let provisioner = self.provisioner(engine)?;
+        for username in &teachers {
+            provisioner
+                .grant_privileges(db_name, username, TEACHER_PRIVILEGES)
+                .await?;
+        }
+        provisioner.flush_privileges().await?;
+        info!(
+            "已授予教师 {} 查看数据库 {} 的权限",
+            teachers.join(", "),
+            db_name
+        );
+        Ok(())
+    }
+
+    // 命名数据库
+
+    /// 命名数据库的数据库名：申请时创建的数据库名加后缀
+    pub fn named_database_name(primary_db: &str, suffix: &str) -> String {
+        format!("{}_{}", primary_db, suffix)
+    }
+
+    /// 校验命名数据库的后缀
+    fn validate_database_suffix(suffix: &str) -> Result<()> {
+        let valid = !suffix.is_empty()
+            && suffix.len() <= MAX_DATABASE_SUFFIX_LEN
+            && suffix.starts_with(|c: char| c.is_ascii_lowercase())
+            && suffix
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid {
+            return Err(anyhow::anyhow!(
+                "数据库后缀无效: 只能包含小写字母、数字和下划线，且以字母开头，不超过 {} 个字符",
+                MAX_DATABASE_SUFFIX_LEN
+            ));
+        }
+        Ok(())
+    }
+
+    /// 获取身份标识可以拥有的数据库数，白名单中未单独设置时使用默认值
+    async fn database_quota(&self, identity_key: &str) -> Result<u32> {
+        let quota: Option<i64> =
+            sqlx::query_scalar("SELECT max_databases FROM student_ids WHERE student_id = ?")
+                .bind(identity_key)
+                .fetch_optional(&self.sqlite_pool)
+                .await?
+                .flatten();
+        Ok(quota
+            .map(|quota| quota.max(1) as u32)
+            .unwrap_or(self.quota.max_databases_per_identity))
+    }
+
+    /// 设置单个用户的数据库数量配额，`None` 表示使用默认值
+    pub async fn set_database_quota(
+        &self,
+        identity_key: &str,
+        max_databases: Option<u32>,
+    ) -> Result<u32> {
+        if max_databases == Some(0) {
+            return Err(anyhow::anyhow!("数据库数量配额无效: 至少为 1"));
+        }
+        let result = sqlx::query(
+            "UPDATE student_ids SET max_databases = ?, updated_at = CURRENT_TIMESTAMP WHERE student_id = ?",
+        )
+        .bind(max_databases.map(|max| max as i64))
+        .bind(identity_key)
+        .execute(&self.sqlite_pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("白名单中不存在用户 {}", identity_key));
+        }
+
+        let quota = self.database_quota(identity_key).await?;
+        info!("用户 {} 的数据库数量配额已设置为 {}", identity_key, quota);
+        Ok(quota)
+    }
+
+    /// 列出用户拥有的所有数据库，申请时创建的数据库排在最前
+    pub async fn list_student_databases(&self, identity_key: &str) -> Result<StudentDatabaseList> {
+        let databases = sqlx::query_as::<_, StudentDatabase>(
+            r#"
+            SELECT db_name, db_suffix AS suffix, db_engine, status, storage_bytes, created_at
+            FROM applicants
+            WHERE identity_key = ? AND status NOT IN ('failed', 'deleted') AND db_name != ''
+            ORDER BY db_suffix IS NOT NULL, db_suffix
+            "#,
+        )
+        .bind(identity_key)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        Ok(StudentDatabaseList {
+            identity_key: identity_key.to_string(),
+            max_databases: self.database_quota(identity_key).await?,
+            databases,
+        })
+    }
+
+    /// 为用户创建命名数据库，由申请时创建的用户使用并按其权限配置档授权
+    ///
+    /// 命名数据库继承用户当前的有效期，结构模板不会应用到命名数据库。
+    /// 与申请数据库一样受申请窗口和容量限制，不允许时返回 [`ApplicationRejection`] 错误。
+    pub async fn create_named_database(
+        &self,
+        identity_key: &str,
+        suffix: &str,
+    ) -> Result<StudentDatabase> {
+        Self::validate_database_suffix(suffix)?;
+        let _guard = self.provision_lock.lock().await;
+        self.ensure_application_allowed(identity_key).await?;
+
+        let primary = sqlx::query_as::<_, (String, String, String, Option<String>, bool)>(
+            "SELECT db_name, db_user, db_engine, privilege_profile, quota_exceeded FROM applicants WHERE identity_key = ? AND db_suffix IS NULL AND status = 'success'",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+        let Some((primary_db, username, engine, profile, quota_exceeded)) = primary else {
+            return Err(anyhow::anyhow!(
+                "用户 {} 不存在可用的数据库，需先申请数据库",
+                identity_key
+            ));
+        };
+
+        let quota = self.database_quota(identity_key).await?;
+        let owned: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM applicants WHERE identity_key = ? AND status NOT IN ('failed', 'deleted') AND db_name != ''",
+        )
+        .bind(identity_key)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        if owned >= quota as i64 {
+            return Err(anyhow::anyhow!(
+                "用户 {} 的数据库数量已达到上限 {}",
+                identity_key,
+                quota
+            ));
+        }
+
+        let db_name = Self::named_database_name(&primary_db, suffix);
+        if !Self::is_valid_database_name(&db_name) {
+            return Err(anyhow::anyhow!(
+                "数据库后缀无效: 数据库名 {} 不合法",
+                db_name
+            ));
+        }
+        // 已删除且没有保留数据的同名记录可以复用，其余同名数据库都视为占用
+        let taken: i64 = sqlx::query_scalar(
+            r#"
+            SELECT (SELECT COUNT(*) FROM applicants WHERE db_name = ? AND status != 'failed'
+                      AND NOT (identity_key = ? AND status = 'deleted' AND archive_name IS NULL))
+                 + (SELECT COUNT(*) FROM teams WHERE db_name = ?)
+            "#,
+        )
+        .bind(&db_name)
+        .bind(identity_key)
+        .bind(&db_name)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        let engine: DatabaseEngine = engine.parse()?;
+        let provisioner = self.provisioner(engine)?;
+        if taken > 0 || provisioner.database_exists(&db_name).await? {
+            return Err(anyhow::anyhow!("数据库 {} 已存在或名称已被占用", db_name));
+        }
+
+        let profile = profile.unwrap_or_else(|| self.default_profile());
+        // 存储配额按用户统计，已超出配额的用户新建的数据库同样只读
+        let privileges = Self::quota_privileges(self.profile_privileges(&profile)?, quota_exceeded);
+
+        provisioner.create_database(&db_name).await?;
+        let role = self
+            .uses_roles(engine)
+            .then(|| Self::role_name_for(&profile, &db_name));
+        let granted = async {
+            match &role {
+                Some(role) => {
+                    self.set_role_privileges(provisioner.as_ref(), &db_name, role, &privileges)
+                        .await?;
+                    provisioner.grant_role(role, &username).await?;
+                }
+                None => {
+                    provisioner
+                        .grant_privileges(&db_name, &username, &privileges)
+                        .await?;
+                }
+            }
+            provisioner.flush_privileges().await?;
+
+            let mut tx = self.sqlite_pool.begin().await?;
+            sqlx::query(
+                "DELETE FROM applicants WHERE identity_key = ? AND db_name = ? AND status IN ('failed', 'deleted')",
+            )
+            .bind(identity_key)
+            .bind(&db_name)
+            .execute(&mut *tx)
+            .await?;
+            // 在写入记录的同一条语句中重新统计数量，并发创建时不会超出配额
+            let inserted = sqlx::query(
+                r#"
+                INSERT INTO applicants (identity_key, db_name, db_suffix, db_user, db_engine, privilege_profile, privilege_role, expires_at, quota_exceeded, status)
+                SELECT identity_key, ?, ?, db_user, db_engine, ?, ?, expires_at, quota_exceeded, 'success'
+                FROM applicants WHERE identity_key = ? AND db_suffix IS NULL AND status = 'success'
+                  AND (SELECT COUNT(*) FROM applicants
+                       WHERE identity_key = ? AND status NOT IN ('failed', 'deleted') AND db_name != '') < ?
+                "#,
+            )
+            .bind(&db_name)
+            .bind(suffix)
+            .bind(&profile)
+            .bind(role.as_deref())
+            .bind(identity_key)
+            .bind(identity_key)
+            .bind(quota as i64)
+            .execute(&mut *tx)
+            .await?;
+            if inserted.rows_affected() == 0 {
+                return Err(anyhow::anyhow!(
+                    "用户 {} 的数据库数量已达到上限 {}",
+                    identity_key,
+                    quota
+                ));
+            }
+            tx.commit().await?;
+            anyhow::Ok(())
+        }
+        .await;
+
+        if let Err(e) = granted {
+            error!("创建命名数据库 {} 失败，开始回滚: {}", db_name, e);
+            self.revoke_direct_privileges(provisioner.as_ref(), &db_name, &username)
+                .await;
+            if let Err(drop_err) = provisioner.drop_database(&db_name).await {
+                error!("删除数据库 {} 失败: {}", db_name, drop_err);
+            }
+            self.drop_profile_roles(provisioner.as_ref(), engine, &db_name)
+                .await;
+            return Err(e);
+        }
+
+        if let Err(e) = self
+            .grant_teacher_access(identity_key, engine, &db_name)
+            .await
+        {
+            warn!("授予任课教师查看权限失败: {} {}", identity_key, e);
+        }
+        self.record_history(
+            identity_key,
+            "database_created",
+            Some(&format!("数据库 {} ({})", db_name, engine)),
+        )
+        .await;
+        info!("用户 {} 的命名数据库 {} 创建完成", identity_key, db_name);
+
+        let database = sqlx::query_as::<_, StudentDatabase>(
+            "SELECT db_name, db_suffix AS suffix, db_engine, status, storage_bytes, created_at FROM applicants WHERE identity_key = ? AND db_name = ?",
+        )
+        .bind(identity_key)
+        .bind(&db_name)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        Ok(database)
+    }
+
+    /// 删除用户的命名数据库，申请时创建的数据库不能通过此方法删除
+    ///
+    /// 保留期内数据库移入隔离库，随用户一同恢复或在保留期结束后清除，在此之前同名数据库不能重新创建。
+    pub async fn delete_named_database(&self, identity_key: &str, suffix: &str) -> Result<()> {
+        let record = sqlx::query_as::<_, (i64, String, String, String)>(
+            "SELECT id, db_name, db_user, db_engine FROM applicants WHERE identity_key = ? AND db_suffix = ? AND status NOT IN ('failed', 'deleted')",
+        )
+        .bind(identity_key)
+        .bind(suffix)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+        let Some((id, db_name, username, engine)) = record else {
+            return Err(anyhow::anyhow!(
+                "用户 {} 不存在后缀为 {} 的数据库",
+                identity_key,
+                suffix
+            ));
+        };
+        let engine: DatabaseEngine = engine.parse()?;
+        let provisioner = self.provisioner(engine)?;
+
+        // 数据库删除后直接授予的权限仍会保留，先撤销，避免同名数据库重建时沿用
+        self.revoke_direct_privileges(provisioner.as_ref(), &db_name, &username)
+            .await;
+        self.remove_named_database(
+            provisioner.as_ref(),
+            engine,
+            id,
+            &db_name,
+            NAMED_DATABASE_DELETION_REASON,
+        )
+        .await?;
+        provisioner.flush_privileges().await?;
+
+        self.record_history(identity_key, "database_dropped", Some(&db_name))
+            .await;
+        info!("用户 {} 的命名数据库 {} 已删除", identity_key, db_name);
+        Ok(())
+    }
+
+    /// 以学生自己的用户名和密码登录申请时创建的数据库，校验身份
+    async fn verify_own_password(&self, identity_key: &str, password: &str) -> Result<bool> {
+        let (db_name, username) = self
+            .stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        provisioner
+            .verify_password(&db_name, &username, password)
+            .await
+    }
+
+    /// 学生列出自己的数据库，密码错误时返回 `Ok(None)`
+    pub async fn list_own_databases(
+        &self,
+        identity_key: &str,
+        password: &str,
+    ) -> Result<Option<StudentDatabaseList>> {
+        if !self.verify_own_password(identity_key, password).await? {
+            warn!("用户 {} 查看数据库列表时密码校验失败", identity_key);
+            return Ok(None);
+        }
+        self.list_student_databases(identity_key).await.map(Some)
+    }
+
+    /// 学生创建自己的命名数据库，密码错误时返回 `Ok(None)`
+    pub async fn create_own_database(
+        &self,
+        identity_key: &str,
+        password: &str,
+        suffix: &str,
+    ) -> Result<Option<StudentDatabase>> {
+        if !self.verify_own_password(identity_key, password).await? {
+            warn!("用户 {} 创建命名数据库时密码校验失败", identity_key);
+            return Ok(None);
+        }
+        self.create_named_database(identity_key, suffix)
+            .await
+            .map(Some)
+    }
+
+    /// 学生删除自己的命名数据库，密码错误时返回 `Ok(None)`
+    pub async fn delete_own_database(
+        &self,
+        identity_key: &str,
+        password: &str,
+        suffix: &str,
+    ) -> Result<Option<()>> {
+        if !self.verify_own_password(identity_key, password).await? {
+            warn!("用户 {} 删除命名数据库时密码校验失败", identity_key);
+            return Ok(None);
+        }
+        self.delete_named_database(identity_key, suffix)
+            .await
+            .map(Some)
+    }
+
+    // 教学班
+
+    /// 解析开课、结课时间和申请窗口，接受 `YYYY-MM-DD` 或 `YYYY-MM-DD HH:MM:SS`，统一转换为后者
+    pub fn parse_window_time(value: Option<&str>) -> Result<Option<String>> {
+        let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+            return Ok(None);
+        };
+        let parsed =
+            chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").or_else(|_| {
+                chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .map(|date| date.and_hms_opt(0, 0, 0).unwrap())
+            });
+        match parsed {
+            Ok(time) => Ok(Some(time.format("%Y-%m-%d %H:%M:%S").to_string())),
+            Err(_) => Err(anyhow::anyhow!(
+                "时间格式无效: {}（应为 YYYY-MM-DD 或 YYYY-MM-DD HH:MM:SS）",
+                value
+            )),
+        }
+    }
+
+    /// 将教学班名称解析为 ID，`None` 表示不筛选
+    pub async fn resolve_cohort(&self, name: Option<&str>) -> Result<Option<i64>> {
+        let Some(name) = name.filter(|n| !n.is_empty()) else {
+            return Ok(None);
+        };
+        let id: Option<i64> = sqlx::query_scalar("SELECT id FROM cohorts WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.sqlite_pool)
+            .await?;
+        id.map(Some)
+            .ok_or_else(|| anyhow::anyhow!("教学班 {} 不存在", name))
+    }
+
+    /// 获取未归档的教学班 ID，用于加入学生
+    async fn open_cohort_id(&self, name: &str) -> Result<i64> {
+        let cohort = sqlx::query_as::<_, (i64, Option<String>)>(
+            "SELECT id, archived_at FROM cohorts WHERE name = ?",
+        )
+        .bind(name)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+        match cohort {
+            None => Err(anyhow::anyhow!("教学班 {} 不存在", name)),
+            Some((_, Some(_))) => Err(anyhow::anyhow!("教学班 {} 已归档", name)),
+            Some((id, None)) => Ok(id),
+        }
+    }
+
+    /// 获取教学班
+    pub async fn get_cohort(&self, name: &str) -> Result<Cohort> {
+        sqlx::query_as::<_, Cohort>(
+            r#"
+            SELECT c.name, c.course, c.term, c.teacher, c.opens_at, c.closes_at, c.default_profile,
+                   c.default_lifetime_days, c.max_databases,
+                   (SELECT COUNT(*) FROM student_ids s WHERE s.cohort_id = c.id) AS member_count,
+                   (SELECT COUNT(*) FROM student_ids s WHERE s.cohort_id = c.id AND s.has_applied = 1) AS applied_count,
+                   c.archived_at, c.created_at, c.updated_at
+            FROM cohorts c WHERE c.name = ?
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("教学班 {} 不存在", name))
+    }
+
+    /// 获取所有教学班，未归档的排在前面
+    pub async fn list_cohorts(&self) -> Result<Vec<Cohort>> {
+        let cohorts = sqlx::query_as::<_, Cohort>(
+            r#"
+            SELECT c.name, c.course, c.term, c.teacher, c.opens_at, c.closes_at, c.default_profile,
+                   c.default_lifetime_days, c.max_databases,
+                   (SELECT COUNT(*) FROM student_ids s WHERE s.cohort_id = c.id) AS member_count,
+                   (SELECT COUNT(*) FROM student_ids s WHERE s.cohort_id = c.id AND s.has_applied = 1) AS applied_count,
+                   c.archived_at, c.created_at, c.updated_at
+            FROM cohorts c
+            ORDER BY c.archived_at IS NOT NULL, c.term DESC, c.name
+            "#,
+        )
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+        Ok(cohorts)
+    }
+
+    /// 创建或更新教学班，已归档的教学班不能修改
+    ///
+    /// 默认权限配置档和有效期只影响之后申请的学生，已创建的数据库不受影响。
+    pub async fn save_cohort(&self, name: &str, request: &SaveCohortRequest) -> Result<Cohort> {
+        if !Self::is_valid_catalog_name(name) {
+            return Err(anyhow::anyhow!(
+                "教学班名称无效: {}（只允许字母、数字、下划线和连字符）",
+                name
+            ));
+        }
+        let course = request.course.trim();
+        let term = request.term.trim();
+        if course.is_empty() || term.is_empty() {
+            return Err(anyhow::anyhow!("教学班无效: 课程和学期不能为空"));
+        }
+        let default_profile = request.default_profile.as_deref().filter(|p| !p.is_empty());
+        self.ensure_profile_exists(default_profile)?;
+        let opens_at = Self::parse_window_time(request.opens_at.as_deref())?;
+        let closes_at = Self::parse_window_time(request.closes_at.as_deref())?;
+        if let (Some(opens_at), Some(closes_at)) = (&opens_at, &closes_at)
+            && opens_at >= closes_at
+        {
+            return Err(anyhow::anyhow!("时间无效: 结课时间必须晚于开课时间"));
+        }
+
+        let archived: Option<Option<String>> =
+            sqlx::query_scalar("SELECT archived_at FROM cohorts WHERE name = ?")
+                .bind(name)
+                .fetch_optional(&self.sqlite_pool)
+                .await?;
+        if archived.flatten().is_some() {
+            return Err(anyhow::anyhow!("教学班 {} 已归档", name));
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO cohorts (name, course, term, teacher, opens_at, closes_at, default_profile, default_lifetime_days, max_databases)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(name) DO UPDATE SET
+                course = excluded.course,
+                term = excluded.term,
+                teacher = excluded.teacher,
+                opens_at = excluded.opens_at,
+                closes_at = excluded.closes_at,
+                default_profile = excluded.default_profile,
+                default_lifetime_days = excluded.default_lifetime_days,
+                max_databases = excluded.max_databases,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(name)
+        .bind(course)
+        .bind(term)
+        .bind(request.teacher.as_deref().filter(|t| !t.is_empty()))
+        .bind(opens_at)
+        .bind(closes_at)
+        .bind(default_profile)
+        .bind(request.default_lifetime_days.map(|days| days as i64))
+        .bind(request.max_databases.filter(|max| *max > 0).map(|max| max as i64))
+        .execute(&self.sqlite_pool)
+        .await?;
+
+        info!("教学班 {} 已保存: {} ({})", name, course, term);
+        self.get_cohort(name).await
+    }
+
+    /// 删除教学班，学生保留在白名单中但不再属于任何教学班
+    pub async fn delete_cohort(&self, name: &str) -> Result<()> {
+        let id = self
+            .resolve_cohort(Some(name))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("教学班 {} 不存在", name))?;
+
+        let mut tx = self.sqlite_pool.begin().await?;
+        sqlx::query("UPDATE student_ids SET cohort_id = NULL, updated_at = CURRENT_TIMESTAMP WHERE cohort_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM cohorts WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        info!("教学班 {} 已删除", name);
+        Ok(())
+    }
+
+    /// 将白名单中的学生加入教学班，返回加入的人数
+    ///
+    /// 已属于其他教学班的学生转入此教学班；任一编号不在白名单中时不做任何修改。
+    pub async fn add_cohort_members(&self, name: &str, student_ids: &[String]) -> Result<u64> {
+        let id = self.open_cohort_id(name).await?;
+
+        let mut tx = self.sqlite_pool.begin().await?;
+        let mut added = 0;
+        for student_id in student_ids {
+            let updated = sqlx::query(
+                "UPDATE student_ids SET cohort_id = ?, updated_at = CURRENT_TIMESTAMP WHERE student_id = ?",
+            )
+            .bind(id)
+            .bind(student_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if updated == 0 {
+                return Err(anyhow::anyhow!("白名单中不存在用户 {}", student_id));
+            }
+            added += updated;
+        }
+        tx.commit().await?;
+
+        info!("{} 名学生已加入教学班 {}", added, name);
+        Ok(added)
+    }
+
+    /// 将学生移出教学班
+    pub async fn remove_cohort_member(&self, name: &str, student_id: &str) -> Result<()> {
+        let id = self
+            .resolve_cohort(Some(name))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("教学班 {} 不存在", name))?;
+        let removed = sqlx::query(
+            "UPDATE student_ids SET cohort_id = NULL, updated_at = CURRENT_TIMESTAMP WHERE student_id = ? AND cohort_id = ?",
+        )
+        .bind(student_id)
+        .bind(id)
+        .execute(&self.sqlite_pool)
+        .await?
+        .rows_affected();
+        if removed == 0 {
+            return Err(anyhow::anyhow!(
+                "教学班 {} 中不存在学生 {}",
+                name,
+                student_id
+            ));
+        }
+
+        info!("学生 {} 已移出教学班 {}", student_id, name);
+        Ok(())
+    }
+
+    /// 学期结束时归档整个教学班
+    ///
+    /// 所有学生的数据库（包括命名数据库）移入归档库并删除用户，与有效期到期后的归档相同。
+    /// 每个数据库单独处理，失败的记录在结果中；全部成功后教学班标记为已归档，不能再加入学生。
+    pub async fn archive_cohort(&self, name: &str) -> Result<CohortArchiveResult> {
+        let id = self
+            .resolve_cohort(Some(name))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("教学班 {} 不存在", name))?;
+        let mut result = CohortArchiveResult {
+            cohort: name.to_string(),
+            ..Default::default()
+        };
+
+        // 命名数据库排在申请时创建的数据库之前，用户在最后一个数据库归档时删除
+        let archivable = sqlx::query_as::<_, (i64, String, String, String, bool)>(
+            r#"
+            SELECT a.id, a.identity_key, a.db_name, a.db_user, a.db_suffix IS NULL FROM applicants a
+            JOIN student_ids s ON s.student_id = a.identity_key
+            WHERE s.cohort_id = ? AND a.status IN ('success', 'suspended', 'expired') AND a.db_name != ''
+            ORDER BY a.identity_key, a.db_suffix IS NULL
+            "#,
+        )
+        .bind(id)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        for (id, identity_key, db_name, username, primary) in archivable {
+            match self
+                .archive_applicant_database(id, &identity_key, &db_name, &username, primary)
+                .await
+            {
+                Ok(()) => {
+                    if primary {
+                        result.archived.push(identity_key);
+                    }
+                }
+                Err(e) => result.errors.push(format!("{}: {}", identity_key, e)),
+            }
+        }
+
+        if result.errors.is_empty() {
+            sqlx::query(
+                "UPDATE cohorts SET archived_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+            )
+            .bind(id)
+            .execute(&self.sqlite_pool)
+            .await?;
+        }
+
+        info!(
+            "教学班 {} 归档完成: 归档 {} 个用户, 失败 {} 个",
+            name,
+            result.archived.len(),
+            result.errors.len()
+        );
+        Ok(result)
+    }
+
+    // 申请窗口与容量
+
+    /// 检查身份标识当前能否申请数据库，`None` 表示允许
+    ///
+    /// 学生所在教学班设置了开课、结课时间时以教学班为准，否则使用全局申请窗口，按服务器本地时间比较。
+    /// 容量按未删除、未归档的数据库计算（含命名数据库），数据库服务器的容量另计团队数据库。
+    pub async fn check_application_allowed(
+        &self,
+        identity_key: &str,
+    ) -> Result<Option<ApplicationRejection>> {
+        let cohort =
+            sqlx::query_as::<_, (i64, String, Option<String>, Option<String>, Option<i64>)>(
+                r#"
+            SELECT c.id, c.name, c.opens_at, c.closes_at, c.max_databases FROM student_ids s
+            JOIN cohorts c ON c.id = s.cohort_id
+            WHERE s.student_id = ?
+            "#,
+            )
+            .bind(identity_key)
+            .fetch_optional(&self.sqlite_pool)
+            .await?;
+
+        let (opens_at, closes_at) = match &cohort {
+            Some((_, _, opens_at, closes_at, _)) if opens_at.is_some() || closes_at.is_some() => {
+                (opens_at.clone(), closes_at.clone())
+            }
+            _ => (
+                Self::parse_window_time(self.application.opens_at.as_deref())?,
+                Self::parse_window_time(self.application.closes_at.as_deref())?,
+            ),
+        };
+        if let Some(rejection) = Self::check_window(opens_at, closes_at) {
+            return Ok(Some(rejection));
+        }
+
+        if let Some((id, name, _, _, Some(max_databases))) = cohort {
+            let live: i64 = sqlx::query_scalar(
+                r#"
+                SELECT COUNT(*) FROM applicants a
+                JOIN student_ids s ON s.student_id = a.identity_key
+                WHERE s.cohort_id = ? AND a.status IN ('success', 'suspended', 'expired')
+                "#,
+            )
+            .bind(id)
+            .fetch_one(&self.sqlite_pool)
+            .await?;
+            if live >= max_databases {
+                return Ok(Some(ApplicationRejection::CapacityReached(format!(
+                    "教学班 {} 的数据库数量已达上限 {}",
+                    name, max_databases
+                ))));
+            }
+        }
+
+        let engine = self.resolve_engine(identity_key).await?;
+        self.check_server_capacity(engine).await
+    }
+
+    /// 检查身份标识当前能否创建数据库，不允许时返回 [`ApplicationRejection`] 错误
+    async fn ensure_application_allowed(&self, identity_key: &str) -> Result<()> {
+        match self.check_application_allowed(identity_key).await? {
+            Some(rejection) => Err(rejection.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// 检查当前能否在指定引擎上创建团队数据库，使用全局申请窗口和数据库服务器的容量
+    async fn ensure_team_allowed(&self, engine: DatabaseEngine) -> Result<()> {
+        let opens_at = Self::parse_window_time(self.application.opens_at.as_deref())?;
+        let closes_at = Self::parse_window_time(self.application.closes_at.as_deref())?;
+        let rejection = match Self::check_window(opens_at, closes_at) {
+            Some(rejection) => Some(rejection),
+            None => self.check_server_capacity(engine).await?,
+        };
+        match rejection {
+            Some(rejection) => Err(rejection.into()),
+            None => Ok(()),
+        }
+    }
+
+    /// 按服务器本地时间检查申请窗口
+    fn check_window(
+        opens_at: Option<String>,
+        closes_at: Option<String>,
+    ) -> Option<ApplicationRejection> {
+        let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        if let Some(opens_at) = opens_at
+            && now < opens_at
+        {
+            return Some(ApplicationRejection::NotOpenYet(opens_at));
+        }
+        if let Some(closes_at) = closes_at
+            && now >= closes_at
+        {
+            return Some(ApplicationRejection::Closed(closes_at));
+        }
+        None
+    }
+
+    /// 检查数据库服务器的容量，团队数据库也计入
+    async fn check_server_capacity(
+        &self,
+        engine: DatabaseEngine,
+    ) -> Result<Option<ApplicationRejection>> {
+        let max_per_server = self.application.max_databases_per_server as i64;
+        if max_per_server > 0 {
+            let live: i64 = sqlx::query_scalar(
+                r#"
+                SELECT (SELECT COUNT(*) FROM applicants WHERE db_engine = ?1 AND status IN ('success', 'suspended', 'expired'))
+                     + (SELECT COUNT(*) FROM teams WHERE db_engine = ?1)
+                "#,
+            )
+            .bind(engine.as_str())
+            .fetch_one(&self.sqlite_pool)
+            .await?;
+            if live >= max_per_server {
+                return Ok(Some(ApplicationRejection::CapacityReached(format!(
+                    "{} 服务器的数据库数量已达上限 {}",
+                    engine, max_per_server
+                ))));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // 团队数据库
+
+    /// 团队对应的数据库名，默认为 `db__team_名称`
+    ///
+    /// 身份标识部分以 [`TEAM_NAME_MARKER`] 开头，不会与任何学号映射出的数据库名重合。
+    pub fn team_database_name(naming: &NamingConfig, team: &str) -> String {
+        Self::map_marked_identifier(
+            naming,
+            &naming.database_prefix,
+            TEAM_NAME_MARKER,
+            team,
+            MAX_DATABASE_NAME_LEN,
+        )
+    }
+
+    /// 团队成员在团队数据库上的用户名，默认为 `user__team_团队_身份标识`，过长时截断并追加哈希
+    pub fn team_username(naming: &NamingConfig, team: &str, identity_key: &str) -> String {
+        Self::map_marked_identifier(
+            naming,
+            &naming.username_prefix,
+            TEAM_NAME_MARKER,
+            &format!("{}_{}", team, identity_key),
+            MAX_USERNAME_LEN,
+        )
+    }
+
+    /// 白名单中映射到给定数据库名或用户名的学号
+    ///
+    /// 用于创建团队和添加成员前的冲突检查，包括本次修复之前按旧规则命名的团队。
+    async fn whitelisted_identity_mapping_to(
+        &self,
+        db_name: Option<&str>,
+        username: Option<&str>,
+    ) -> Result<Option<String>> {
+        let student_ids: Vec<String> = sqlx::query_scalar("SELECT student_id FROM student_ids")
+            .fetch_all(&self.sqlite_pool)
+            .await?;
+        Ok(student_ids.into_iter().find(|id| {
+            db_name == Some(Self::database_name_for(&self.naming, id).as_str())
+                || username == Some(Self::username_for(&self.naming, id).as_str())
+        }))
+    }
+
+    /// 学号映射出的数据库名或用户名与已有的团队数据库或团队成员重合时返回错误
+    async fn ensure_no_team_collision(&self, identity_key: &str) -> Result<()> {
+        let db_name = Self::database_name_for(&self.naming, identity_key);
+        let username = Self::username_for(&self.naming, identity_key);
+        let taken: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM teams WHERE db_name = ?) + (SELECT COUNT(*) FROM team_members WHERE db_user = ?)",
+        )
+        .bind(&db_name)
+        .bind(&username)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        if taken > 0 {
+            return Err(anyhow::anyhow!(
+                "学号 {} 对应的数据库名 {} 或用户名 {} 已被团队占用",
+                identity_key,
+                db_name,
+                username
+            ));
+        }
+        Ok(())
+    }
+
+    /// 获取团队的数据库名、引擎和权限配置档，团队不存在时返回错误
+    async fn team_resources(&self, team: &str) -> Result<(String, DatabaseEngine, String)> {
+        let (db_name, engine, profile) = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT db_name, db_engine, privilege_profile FROM teams WHERE name = ?",
+        )
+        .bind(team)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("团队 {} 不存在", team))?;
+        Ok((db_name, engine.parse()?, profile))
+    }
+
+    /// 获取团队数据库及其成员
+    pub async fn get_team(&self, team: &str) -> Result<TeamInfo> {
+        let (name, db_name, db_engine, privilege_profile, created_at) =
+            sqlx::query_as::<_, (String, String, String, String, String)>(
+                "SELECT name, db_name, db_engine, privilege_profile, created_at FROM teams WHERE name = ?",
+            )
+            .bind(team)
+            .fetch_optional(&self.sqlite_pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("团队 {} 不存在", team))?;
+
+        let members = sqlx::query_as::<_, (String, Option<String>, String, String)>(
+            r#"
+            SELECT m.identity_key, s.student_name, m.db_user, m.joined_at FROM team_members m
+            LEFT JOIN student_ids s ON s.student_id = m.identity_key
+            WHERE m.team = ?
+            ORDER BY m.joined_at, m.identity_key
+            "#,
+        )
+        .bind(&name)
+        .fetch_all(&self.sqlite_pool)
+        .await?
+        .into_iter()
+        .map(
+            |(identity_key, student_name, username, joined_at)| TeamMember {
+                identity_key,
+                student_name,
+                username,
+                joined_at,
+            },
+        )
+        .collect();
+
+        Ok(TeamInfo {
+            name,
+            db_name,
+            db_engine,
+            privilege_profile,
+            members,
+            created_at,
+        })
+    }
+
+    /// 获取所有团队数据库
+    pub async fn list_teams(&self) -> Result<Vec<TeamInfo>> {
+        let names: Vec<String> = sqlx::query_scalar("SELECT name FROM teams ORDER BY name")
+            .fetch_all(&self.sqlite_pool)
+            .await?;
+        let mut teams = Vec::with_capacity(names.len());
+        for name in names {
+            teams.push(self.get_team(&name).await?);
+        }
+        Ok(teams)
+    }
+
+    /// 创建团队数据库（不含成员）
+    ///
+    /// 受全局申请窗口和数据库服务器容量限制，不允许时返回 [`ApplicationRejection`] 错误。
+    pub async fn create_team(
+        &self,
+        name: &str,
+        engine: Option<DatabaseEngine>,
+        profile: Option<&str>,
+    ) -> Result<TeamInfo> {
+        if !name.is_ascii() || !Self::is_valid_identifier(name) || name.len() > MAX_TEAM_NAME_LEN {
+            return Err(anyhow::anyhow!(
+                "团队名称无效: 只能包含字母、数字和下划线，且以字母开头，不超过 {} 个字符",
+                MAX_TEAM_NAME_LEN
+            ));
+        }
+        let default_profile = self.default_profile();
+        let profile = profile.unwrap_or(&default_profile);
+        self.profile_privileges(profile)?;
+        let engine = engine.unwrap_or(self.default_engine);
+        let provisioner = self.provisioner(engine)?;
+
+        let db_name = Self::team_database_name(&self.naming, name);
+        if !Self::is_valid_database_name(&db_name) {
+            return Err(anyhow::anyhow!("团队名称无效: 数据库名 {} 不合法", db_name));
+        }
+        let _guard = self.provision_lock.lock().await;
+        self.ensure_team_allowed(engine).await?;
+        let taken: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM teams WHERE name = ? OR db_name = ?) + (SELECT COUNT(*) FROM applicants WHERE db_name = ? AND status != 'failed')",
+        )
+        .bind(name)
+        .bind(&db_name)
+        .bind(&db_name)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        if taken > 0 {
+            return Err(anyhow::anyhow!("团队 {} 已存在或数据库名已被占用", name));
+        }
+        if let Some(identity_key) = self
+            .whitelisted_identity_mapping_to(Some(&db_name), None)
+            .await?
+        {
+            return Err(anyhow::anyhow!(
+                "团队 {} 的数据库名 {} 与学号 {} 冲突",
+                name,
+                db_name,
+                identity_key
+            ));
+        }
+        // 不接管服务器上已有的同名数据库，否则保存记录失败时会把它删除
+        if provisioner.database_exists(&db_name).await? {
+            return Err(anyhow::anyhow!("数据库 {} 已存在", db_name));
+        }
+
+        provisioner.create_database(&db_name).await?;
+        if let Err(e) = sqlx::query(
+            "INSERT INTO teams (name, db_name, db_engine, privilege_profile) VALUES (?, ?, ?, ?)",
+        )
+        .bind(name)
+        .bind(&db_name)
+        .bind(engine.to_string())
+        .bind(profile)
+        .execute(&self.sqlite_pool)
+        .await
+        {
+            error!("保存团队记录失败，删除数据库 {}: {}", db_name, e);
+            if let Err(drop_err) = provisioner.drop_database(&db_name).await {
+                error!("删除数据库 {} 失败: {}", db_name, drop_err);
+            }
+            return Err(e.into());
+        }
+
+        info!(
+            "团队 {} 创建完成: 数据库 {} ({}), 权限配置档 {}",
+            name, db_name, engine, profile
+        );
+        self.get_team(name).await
+    }
+
+    /// 添加团队成员，为其在团队数据库上创建独立的用户并按团队的配置档授权
+    pub async fn add_team_member(
+        &self,
+        team: &str,
+        identity_key: &str,
+        password: &str,
+    ) -> Result<DatabaseCredentials> {
+        let (db_name, engine, profile) = self.team_resources(team).await?;
+        let privileges = self.profile_privileges(&profile)?;
+        let provisioner = self.provisioner(engine)?;
+
+        let listed: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM student_ids WHERE student_id = ?")
+                .bind(identity_key)
+                .fetch_one(&self.sqlite_pool)
+                .await?;
+        if listed == 0 {
+            return Err(anyhow::anyhow!("白名单中不存在用户 {}", identity_key));
+        }
+        let joined: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM team_members WHERE team = ? AND identity_key = ?",
+        )
+        .bind(team)
+        .bind(identity_key)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        if joined > 0 {
+            return Err(anyhow::anyhow!(
+                "用户 {} 已是团队 {} 的成员",
+                identity_key,
+                team
+            ));
+        }
+        // 停用或到期的学生在团队数据库上同样不能登录，恢复前不能加入新的团队
+        let locked: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM applicants WHERE identity_key = ? AND status IN ('suspended', 'expired')",
+        )
+        .bind(identity_key)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        if locked > 0 {
+            return Err(anyhow::anyhow!(
+                "用户 {} 已停用或已到期，不能加入团队",
+                identity_key
+            ));
+        }
+
+        let username = Self::team_username(&self.naming, team, identity_key);
+        if !Self::is_valid_username(&username) {
+            return Err(anyhow::anyhow!("团队成员用户名无效: {}", username));
+        }
+        let taken: i64 = sqlx::query_scalar(
+            "SELECT (SELECT COUNT(*) FROM applicants WHERE db_user = ? AND status != 'failed') + (SELECT COUNT(*) FROM teachers WHERE db_user = ?) + (SELECT COUNT(*) FROM team_members WHERE db_user = ?)",
+        )
+        .bind(&username)
+        .bind(&username)
+        .bind(&username)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+        if taken > 0 {
+            return Err(anyhow::anyhow!("团队成员用户名 {} 已被占用", username));
+        }
+        if let Some(owner) = self
+            .whitelisted_identity_mapping_to(None, Some(&username))
+            .await?
+        {
+            return Err(anyhow::anyhow!(
+                "团队成员用户名 {} 与学号 {} 冲突",
+                username,
+                owner
+            ));
+        }
+
+        provisioner
+            .create_user(&username, password, &self.resource_limits)
+            .await?;
+        let granted = async {
+            // 与个人数据库一样，启用角色时所有成员共用团队数据库上配置档对应的角色
+            let role = self
+                .grant_team_privileges(
+                    provisioner.as_ref(),
+                    engine,
+                    &db_name,
+                    &profile,
+                    &username,
+                    &privileges,
+                )
+                .await?;
+            if let Err(e) = provisioner
+                .revoke_privileges(None, &username, DANGEROUS_PRIVILEGES)
+                .await
+            {
+                warn!("撤销危险权限时出现警告 (可忽略): {}", e);
+            }
+            provisioner.flush_privileges().await?;
+            sqlx::query(
+                "INSERT INTO team_members (team, identity_key, db_user, privilege_role) VALUES (?, ?, ?, ?)",
+            )
+            .bind(team)
+            .bind(identity_key)
+            .bind(&username)
+            .bind(role.as_deref())
+            .execute(&self.sqlite_pool)
+            .await?;
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        if let Err(e) = granted {
+            error!(
+                "添加团队成员 {} 失败，删除用户 {}: {}",
+                identity_key, username, e
+            );
+            if let Err(drop_err) = provisioner.drop_user(&username).await {
+                error!("删除用户 {} 失败: {}", username, drop_err);
+            }
+            return Err(e);
+        }
+
+        self.record_history(
+            identity_key,
+            "team_joined",
+            Some(&format!("团队 {} ({})", team, db_name)),
+        )
+        .await;
+        info!(
+            "用户 {} 已加入团队 {}: 用户名 {}, 权限 {}",
+            identity_key,
+            team,
+            username,
+            privileges.join(", ")
+        );
+        Ok(provisioner.credentials(&db_name, &username, password))
+    }
+
+    /// 撤销成员的权限并删除其在团队数据库上的用户
+    async fn drop_team_member_user(
+        &self,
+        provisioner: &dyn Provisioner,
+        db_name: &str,
+        username: &str,
+    ) -> Result<()> {
+        self.revoke_direct_privileges(provisioner, db_name, username)
+            .await;
+        if let Err(e) = provisioner.kill_sessions(username).await {
+            warn!("断开用户 {} 的会话时出现警告: {}", username, e);
+        }
+        provisioner.drop_user(username).await?;
+        provisioner.flush_privileges().await
+    }
+
+    /// 按配置档为团队成员授权，启用角色时授予团队数据库上配置档对应的角色并返回角色名
+    async fn grant_team_privileges(
+        &self,
+        provisioner: &dyn Provisioner,
+        engine: DatabaseEngine,
+        db_name: &str,
+        profile: &str,
+        username: &str,
+        privileges: &[&str],
+    ) -> Result<Option<String>> {
+        if self.uses_roles(engine) {
+            let role = Self::role_name_for(profile, db_name);
+            self.set_role_privileges(provisioner, db_name, &role, privileges)
+                .await?;
+            provisioner.grant_role(&role, username).await?;
+            Ok(Some(role))
+        } else {
+            provisioner
+                .grant_privileges(db_name, username, privileges)
+                .await?;
+            Ok(None)
+        }
+    }
+
+    /// 按配置档的新权限重新授权直接授权的团队成员，返回更新的成员数及错误列表
+    ///
+    /// 通过角色授权的成员由 [`Self::sync_profile_roles`] 随角色一同更新。
+    async fn regrant_team_members(&self, profile: &str) -> Result<(i32, Vec<String>)> {
+        let privileges = self.profile_privileges(profile)?;
+        let members = sqlx::query_as::<_, (String, String, String, String, String)>(
+            r#"
+            SELECT m.team, m.identity_key, m.db_user, t.db_name, t.db_engine FROM team_members m
+            JOIN teams t ON t.name = m.team
+            WHERE t.privilege_profile = ? AND m.privilege_role IS NULL
+            "#,
+        )
+        .bind(profile)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        let mut updated_count = 0;
+        let mut errors = Vec::new();
+        for (team, identity_key, username, db_name, engine) in members {
+            let regranted = async {
+                let engine: DatabaseEngine = engine.parse()?;
+                let provisioner = self.provisioner(engine)?;
+                self.revoke_direct_privileges(provisioner.as_ref(), &db_name, &username)
+                    .await;
+                let role = self
+                    .grant_team_privileges(
+                        provisioner.as_ref(),
+                        engine,
+                        &db_name,
+                        profile,
+                        &username,
+                        &privileges,
+                    )
+                    .await?;
+                provisioner.flush_privileges().await?;
+                sqlx::query(
+                    "UPDATE team_members SET privilege_role = ? WHERE team = ? AND identity_key = ?",
+                )
+                .bind(role.as_deref())
+                .bind(&team)
+                .bind(&identity_key)
+                .execute(&self.sqlite_pool)
+                .await?;
+                anyhow::Ok(())
+            }
+            .await;
+            match regranted {
+                Ok(()) => updated_count += 1,
+                Err(e) => errors.push(format!("{}: {}", username, e)),
+            }
+        }
+        Ok((updated_count, errors))
+    }
+
+    /// 学生加入的所有团队中对应的引擎和用户名
+    async fn team_member_users(&self, identity_key: &str) -> Result<Vec<(DatabaseEngine, String)>> {
+        sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT t.db_engine, m.db_user FROM team_members m
+            JOIN teams t ON t.name = m.team
+            WHERE m.identity_key = ?
+            "#,
+        )
+        .bind(identity_key)
+        .fetch_all(&self.sqlite_pool)
+        .await?
+        .into_iter()
+        .map(|(engine, username)| Ok((engine.parse()?, username)))
+        .collect()
+    }
+
+    /// 锁定学生在所有团队数据库上的用户并断开会话，返回断开的会话数
+    ///
+    /// 学生被停用或到期时调用，与其个人账号一同锁定。
+    async fn lock_team_member_users(&self, identity_key: &str) -> Result<u32> {
+        let mut killed = 0;
+        for (engine, username) in self.team_member_users(identity_key).await? {
+            let provisioner = self.provisioner(engine)?;
+            provisioner.lock_user(&username).await?;
+            killed += provisioner.kill_sessions(&username).await?;
+        }
+        Ok(killed)
+    }
+
+    /// 解锁学生在所有团队数据库上的用户
+    async fn unlock_team_member_users(&self, identity_key: &str) -> Result<()> {
+        for (engine, username) in self.team_member_users(identity_key).await? {
+            self.provisioner(engine)?.unlock_user(&username).await?;
+        }
+        Ok(())
+    }
+
+    /// 将学生移出其加入的所有团队，在学生被删除或归档时调用
+    async fn leave_all_teams(&self, identity_key: &str, detail: &str) -> Result<()> {
+        let teams: Vec<String> =
+            sqlx::query_scalar("SELECT team FROM team_members WHERE identity_key = ?")
+                .bind(identity_key)
+                .fetch_all(&self.sqlite_pool)
+                .await?;
+        for team in teams {
+            self.remove_team_member(&team, identity_key).await?;
+            info!("用户 {} {}，已移出团队 {}", identity_key, detail, team);
+        }
+        Ok(())
+    }
+
+    /// 移除团队成员，撤销其在团队数据库上的权限并删除对应的用户
+    pub async fn remove_team_member(&self, team: &str, identity_key: &str) -> Result<()> {
+        let (db_name, engine, _) = self.team_resources(team).await?;
+        let username: String = sqlx::query_scalar(
+            "SELECT db_user FROM team_members WHERE team = ? AND identity_key = ?",
+        )
+        .bind(team)
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("团队 {} 中不存在成员 {}", team, identity_key))?;
+
+        self.drop_team_member_user(self.provisioner(engine)?.as_ref(), &db_name, &username)
+            .await?;
+        sqlx::query("DELETE FROM team_members WHERE team = ? AND identity_key = ?")
+            .bind(team)
+            .bind(identity_key)
+            .execute(&self.sqlite_pool)
+            .await?;
+
+        self.record_history(identity_key, "team_left", Some(&format!("团队 {}", team)))
+            .await;
+        info!("用户 {} 已从团队 {} 中移除", identity_key, team);
+        Ok(())
+    }
+
+    /// 删除团队数据库及所有成员的用户
+    pub async fn delete_team(&self, team: &str) -> Result<()> {
+        let (db_name, engine, _) = self.team_resources(team).await?;
+        let provisioner = self.provisioner(engine)?;
+        let members = sqlx::query_as::<_, (String, String)>(
+            "SELECT identity_key, db_user FROM team_members WHERE team = ?",
+        )
+        .bind(team)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        for (_, username) in &members {
+            self.drop_team_member_user(provisioner.as_ref(), &db_name, username)
+                .await?;
+        }
+        provisioner.drop_database(&db_name).await?;
+        self.drop_profile_roles(provisioner.as_ref(), engine, &db_name)
+            .await;
+
+        let mut tx = self.sqlite_pool.begin().await?;
+        sqlx::query("DELETE FROM team_members WHERE team = ?")
+            .bind(team)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM teams WHERE name = ?")
+            .bind(team)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        for (identity_key, _) in &members {
+            self.record_history(
+                identity_key,
+                "team_left",
+                Some(&format!("团队 {} 已删除", team)),
+            )
+            .await;
+        }
+        info!("团队 {} 已删除 (数据库 {})", team, db_name);
+        Ok(())
+    }
+
+    /// 列出所有个人数据库和团队数据库
+    pub async fn list_databases(&self) -> Result<Vec<DatabaseSummary>> {
+        let personal = sqlx::query_as::<_, (String, String, String, String, String)>(
+            "SELECT identity_key, db_name, db_engine, status, created_at FROM applicants WHERE status IN ('success', 'expired', 'suspended') AND deleted_at IS NULL ORDER BY identity_key",
+        )
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        let mut databases: Vec<DatabaseSummary> = personal
+            .into_iter()
+            .map(
+                |(identity_key, db_name, db_engine, status, created_at)| DatabaseSummary {
+                    kind: "personal".to_string(),
+                    members: vec![identity_key.clone()],
+                    owner: identity_key,
+                    db_name,
+                    db_engine,
+                    status,
+                    created_at,
+                },
+            )
+            .collect();
+        for team in self.list_teams().await? {
+            databases.push(DatabaseSummary {
+                kind: "team".to_string(),
+                owner: team.name,
+                db_name: team.db_name,
+                db_engine: team.db_engine,
+                members: team.members.into_iter().map(|m| m.identity_key).collect(),
+                status: "success".to_string(),
+                created_at: team.created_at,
+            });
+        }
+        Ok(databases)
+    }
+
+    // 结构相似度检查
+
+    /// 读取学生数据库的结构特征
+    async fn schema_fingerprint(
+        &self,
+        identity_key: &str,
+        db_name: &str,
+        include_rows: bool,
+    ) -> Result<BTreeSet<String>> {
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        if !provisioner.database_exists(db_name).await? {
+            return Err(anyhow::anyhow!("数据库 {} 不存在", db_name));
+        }
+        let snapshot = provisioner.schema_snapshot(db_name, include_rows).await?;
+        Ok(similarity::fingerprint(&snapshot))
+    }
+
+    fn similarity_report_from_row(
+        (id, class_info, include_rows, threshold, student_count, pairs, errors, created_at): (
+            i64,
+            Option<String>,
+            bool,
+            f64,
+            i32,
+            String,
+            String,
+            String,
+        ),
+    ) -> Result<SimilarityReport> {
+        Ok(SimilarityReport {
+            id,
+            class_info,
+            include_rows,
+            threshold,
+            student_count,
+            pairs: serde_json::from_str(&pairs)?,
+            errors: serde_json::from_str(&errors)?,
+            created_at,
+        })
+    }
+
+    /// 比较学生数据库的结构，生成并保存相似度报告
+    ///
+    /// 指定班级时只比较该班级的学生，否则在每个班级内分别比较。
+    /// 已停用或已过期的学生也参与比较，已删除的不参与。
+    pub async fn run_similarity_report(
+        &self,
+        class_info: Option<&str>,
+        include_rows: bool,
+        threshold: f64,
+    ) -> Result<SimilarityReport> {
+        if threshold.is_nan() || threshold <= 0.0 || threshold > 1.0 {
+            return Err(anyhow::anyhow!("相似度阈值无效: 应大于 0 且不超过 1"));
+        }
+
+        let targets = sqlx::query_as::<_, (String, String, Option<String>)>(
+            r#"
+            SELECT a.identity_key, a.db_name, s.class_info FROM applicants a
+            LEFT JOIN student_ids s ON s.student_id = a.identity_key
+            WHERE a.status IN ('success', 'suspended', 'expired') AND a.db_name != '' AND a.db_suffix IS NULL
+              AND (?1 IS NULL OR s.class_info = ?1)
+            ORDER BY a.identity_key
+            "#,
+        )
+        .bind(class_info)
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        let mut cohorts: BTreeMap<Option<String>, Vec<(String, BTreeSet<String>)>> =
+            BTreeMap::new();
+        let mut student_count = 0;
+        let mut errors = Vec::new();
+        for (identity_key, db_name, class) in targets {
+            match self
+                .schema_fingerprint(&identity_key, &db_name, include_rows)
+                .await
+            {
+                Ok(features) => {
+                    cohorts
+                        .entry(class)
+                        .or_default()
+                        .push((identity_key, features));
+                    student_count += 1;
+                }
+                Err(e) => errors.push(format!("{}: {}", identity_key, e)),
+            }
+        }
+
+        let mut pairs = Vec::new();
+        for (class, students) in cohorts {
+            for mut pair in similarity::rank_pairs(&students, threshold) {
+                pair.class_info = class.clone();
+                pairs.push(pair);
+            }
+        }
+        similarity::sort_pairs(&mut pairs);
+
+        let row = sqlx::query_as(
+            r#"
+            INSERT INTO similarity_reports (class_info, include_rows, threshold, student_count, pairs, errors)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING id, class_info, include_rows, threshold, student_count, pairs, errors, created_at
+            "#,
+        )
+        .bind(class_info)
+        .bind(include_rows)
+        .bind(threshold)
+        .bind(student_count)
+        .bind(serde_json::to_string(&pairs)?)
+        .bind(serde_json::to_string(&errors)?)
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+
+        info!(
+            "结构相似度检查完成: {} 名学生, {} 对相似, {} 个错误",
+            student_count,
+            pairs.len(),
+            errors.len()
+        );
+        Self::similarity_report_from_row(row)
+    }
+
+    /// 获取相似度报告
+    pub async fn get_similarity_report(&self, id: i64) -> Result<SimilarityReport> {
+        let row = sqlx::query_as(
+            "SELECT id, class_info, include_rows, threshold, student_count, pairs, errors, created_at FROM similarity_reports WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("相似度报告 {} 不存在", id))?;
+        Self::similarity_report_from_row(row)
+    }
+
+    /// 获取某个班级最近一次的相似度报告，`class_info` 为空时返回检查所有班级的报告
+    pub async fn latest_similarity_report(
+        &self,
+        class_info: Option<&str>,
+    ) -> Result<SimilarityReport> {
+        let row = sqlx::query_as(
+            "SELECT id, class_info, include_rows, threshold, student_count, pairs, errors, created_at FROM similarity_reports WHERE class_info IS ? ORDER BY id DESC LIMIT 1",
+        )
+        .bind(class_info)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("相似度报告不存在，请先执行检查"))?;
+        Self::similarity_report_from_row(row)
+    }
+
+    // 数据库基线与重置
+
+    /// 是否允许学生重置自己的数据库
+    pub fn allows_student_reset(&self) -> bool {
+        self.reset.allow_students
+    }
+
+    /// 申请时使用的结构模板
+    async fn applied_template(&self, identity_key: &str) -> Result<Option<String>> {
+        let template: Option<String> = sqlx::query_scalar(
+            "SELECT schema_template FROM applicants WHERE identity_key = ? AND db_suffix IS NULL AND status != 'failed' AND db_name != '' ORDER BY id DESC LIMIT 1",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?
+        .flatten();
+        Ok(template)
+    }
+
+    /// 获取身份标识的数据库基线
+    pub async fn get_baseline(&self, identity_key: &str) -> Result<BaselineInfo> {
+        self.stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let template = self.applied_template(identity_key).await?;
+        let checkpoint = sqlx::query_as::<_, (i64, String)>(
+            "SELECT LENGTH(CAST(script AS BLOB)), created_at FROM database_baselines WHERE identity_key = ?",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+
+        let source = match (&checkpoint, &template) {
+            (Some(_), _) => "checkpoint",
+            (None, Some(_)) => "template",
+            (None, None) => "empty",
+        };
+        let (size_bytes, created_at) = checkpoint.unzip();
+        Ok(BaselineInfo {
+            identity_key: identity_key.to_string(),
+            source: source.to_string(),
+            template,
+            size_bytes,
+            created_at,
+        })
+    }
+
+    /// 将数据库的当前状态保存为检查点，之后重置时恢复到此状态
+    ///
+    /// 检查点是数据库的 SQL 导出，大小受 `IMPORT_MAX_SQL_MB` 限制。
+    pub async fn save_baseline(&self, identity_key: &str) -> Result<BaselineInfo> {
+        let (db_name, _) = self
+            .stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        if !provisioner.database_exists(&db_name).await? {
+            return Err(anyhow::anyhow!("数据库 {} 不存在", db_name));
+        }
+
+        let max_sql_mb = self.import.max_sql_mb;
+        let (sender, mut chunks) = tokio::sync::mpsc::channel(DUMP_CHANNEL_CAPACITY);
+        let dump = async {
+            let result = provisioner.dump_database(&db_name, &sender).await;
+            drop(sender);
+            result
+        };
+        // 接收端随读取任务结束而关闭，超过大小限制时导出会随之停止
+        let collect = async move {
+            let mut script = String::new();
+            while let Some(chunk) = chunks.recv().await {
+                script.push_str(&chunk?);
+                if script.len() > max_sql_mb as usize * 1024 * 1024 {
+                    return Err(anyhow::anyhow!(
+                        "数据库超过 {} MB，无法保存为基线",
+                        max_sql_mb
+                    ));
+                }
+            }
+            Ok(script)
+        };
+        let (dumped, script) = tokio::join!(dump, collect);
+        let script = script?;
+        dumped?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO database_baselines (identity_key, script) VALUES (?, ?)
+            ON CONFLICT(identity_key) DO UPDATE SET
+                script = excluded.script,
+                created_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(identity_key)
+        .bind(&script)
+        .execute(&self.sqlite_pool)
+        .await?;
+
+        self.record_history(
+            identity_key,
+            "baseline_saved",
+            Some(&format!("{} 字节", script.len())),
+        )
+        .await;
+        info!("已保存用户 {} 的数据库检查点", identity_key);
+        self.get_baseline(identity_key).await
+    }
+
+    /// 删除保存的检查点，之后重置时恢复到申请时的状态
+    pub async fn clear_baseline(&self, identity_key: &str) -> Result<BaselineInfo> {
+        let deleted = sqlx::query("DELETE FROM database_baselines WHERE identity_key = ?")
+            .bind(identity_key)
+            .execute(&self.sqlite_pool)
+            .await?
+            .rows_affected();
+        if deleted == 0 {
+            return Err(anyhow::anyhow!("用户 {} 的检查点不存在", identity_key));
+        }
+
+        self.record_history(identity_key, "baseline_cleared", None)
+            .await;
+        self.get_baseline(identity_key).await
+    }
+
+    /// 将数据库重置到基线，用户名和密码保持不变
+    ///
+    /// 先断开用户的会话并删除数据库中的所有对象，再执行基线脚本：
+    /// 保存的检查点优先，其次是申请时使用的结构模板（按模板的当前内容），都没有时保留空库。
+    /// 检查点来自学生自己的数据库，只能以学生自己的账号执行，管理员重置时需要先删除检查点。
+    pub async fn reset_database(&self, identity_key: &str) -> Result<ResetResult> {
+        self.reset_to_baseline(identity_key, None).await
+    }
+
+    /// 重置数据库，提供密码时检查点以学生自己的账号执行，结构模板始终以管理员连接执行
+    async fn reset_to_baseline(
+        &self,
+        identity_key: &str,
+        password: Option<&str>,
+    ) -> Result<ResetResult> {
+        let (db_name, username) = self
+            .stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        if !provisioner.database_exists(&db_name).await? {
+            return Err(anyhow::anyhow!("数据库 {} 不存在", db_name));
+        }
+
+        // 清空数据库前先读取并校验基线，模板已被删除、检查点无法恢复等情况下不做任何修改
+        let checkpoint: Option<String> =
+            sqlx::query_scalar("SELECT script FROM database_baselines WHERE identity_key = ?")
+                .bind(identity_key)
+                .fetch_optional(&self.sqlite_pool)
+                .await?;
+        let (source, statements, as_user) = match checkpoint {
+            Some(script) => {
+                let Some(password) = password else {
+                    return Err(anyhow::anyhow!(
+                        "用户 {} 保存了检查点，检查点只能由学生本人使用数据库密码恢复，管理员重置前需先删除检查点",
+                        identity_key
+                    ));
+                };
+                let statements = import::split_statements(&script);
+                dump::validate_dump_statements(&statements)?;
+                ("checkpoint", statements, Some(password))
+            }
+            None => match self.applied_template(identity_key).await? {
+                Some(template) => {
+                    let template = self.get_schema_template(&template).await?;
+                    ("template", import::split_statements(&template.script), None)
+                }
+                None => ("empty", Vec::new(), None),
+            },
+        };
+
+        let killed = provisioner.kill_sessions(&username).await?;
+        if killed > 0 {
+            info!("重置数据库前断开用户 {} 的 {} 个会话", username, killed);
+        }
+        let dropped_objects = provisioner.clear_database(&db_name).await?;
+        let restored =
+            match as_user {
+                Some(password) => provisioner
+                    .execute_as_user(&db_name, &username, password, &statements, 1)
+                    .await
+                    .and_then(|results| {
+                        match results.into_iter().zip(&statements).find_map(
+                            |(result, statement)| result.err().map(|e| (statement.line, e)),
+                        ) {
+                            Some((line, e)) => {
+                                Err(anyhow::anyhow!("第 {} 行的语句执行失败: {}", line, e))
+                            }
+                            None => Ok(()),
+                        }
+                    }),
+                None => provisioner.execute_script(&db_name, &statements).await,
+            };
+        if let Err(e) = restored {
+            self.record_history(
+                identity_key,
+                "reset_failed",
+                Some(&format!("恢复基线 {} 失败: {}", source, e)),
+            )
+            .await;
+            return Err(anyhow::anyhow!("数据库已清空，但恢复基线失败: {}", e));
+        }
+
+        self.record_history(
+            identity_key,
+            "reset",
+            Some(&format!(
+                "恢复到基线 {}，删除 {} 个对象，执行 {} 条语句",
+                source,
+                dropped_objects,
+                statements.len()
+            )),
+        )
+        .await;
+        info!("用户 {} 的数据库已重置到基线 {}", identity_key, source);
+        Ok(ResetResult {
+            identity_key: identity_key.to_string(),
+            source: source.to_string(),
+            dropped_objects,
+            restored_statements: statements.len(),
+        })
+    }
+
+    /// 学生重置自己的数据库，以学生自己的用户名和密码登录校验身份
+    ///
+    /// 密码错误时返回 `Ok(None)`。
+    pub async fn reset_own_database(
+        &self,
+        identity_key: &str,
+        password: &str,
+    ) -> Result<Option<ResetResult>> {
+        let (db_name, username) = self
+            .stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        if !provisioner
+            .verify_password(&db_name, &username, password)
+            .await?
+        {
+            warn!("用户 {} 重置数据库时密码校验失败", identity_key);
+            return Ok(None);
+        }
+        self.reset_to_baseline(identity_key, Some(password))
+            .await
+            .map(Some)
+    }
+
+    // 密码重置
+
+    fn hash_reset_code(code: &str) -> String {
+        format!(
+            "{:x}",
+            Sha256::digest(code.trim().to_ascii_uppercase().as_bytes())
+        )
+    }
+
+    /// 为已创建数据库的用户生成一次性重置码，覆盖之前未使用的重置码
+    ///
+    /// 返回重置码和过期时间，数据库中只保存重置码的哈希。
+    pub async fn issue_reset_code(&self, identity_key: &str) -> Result<(String, String)> {
+        self.stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+
+        let code = crate::utils::generate_reset_code(RESET_CODE_LEN);
+        let expires_at: String = sqlx::query_scalar(
+            r#"
+            INSERT INTO credential_reset_codes (identity_key, code_hash, expires_at)
+            VALUES (?, ?, datetime('now', ?))
+            ON CONFLICT(identity_key) DO UPDATE SET
+                code_hash = excluded.code_hash,
+                attempts = 0,
+                expires_at = excluded.expires_at,
+                created_at = CURRENT_TIMESTAMP
+            RETURNING expires_at
+            "#,
+        )
+        .bind(identity_key)
+        .bind(Self::hash_reset_code(&code))
+        .bind(format!("+{} minutes", RESET_CODE_TTL_MINUTES))
+        .fetch_one(&self.sqlite_pool)
+        .await?;
+
+        self.record_history(
+            identity_key,
+            "reset_code_issued",
+            Some(&format!("有效期至 {}", expires_at)),
+        )
+        .await;
+        info!(
+            "已为用户 {} 生成重置码，有效期至 {}",
+            identity_key, expires_at
+        );
+        Ok((code, expires_at))
+    }
+
+    /// 使用一次性重置码重置用户密码，数据库和权限保持不变
+    ///
+    /// 重置码无效、过期或错误次数过多时返回 `None`。
+    pub async fn reset_password(
+        &self,
+        identity_key: &str,
+        code: &str,
+        new_password: &str,
+    ) -> Result<Option<DatabaseCredentials>> {
+        let stored = sqlx::query_as::<_, (String, i64, bool)>(
+            "SELECT code_hash, attempts, expires_at <= datetime('now') FROM credential_reset_codes WHERE identity_key = ?",
+        )
+        .bind(identity_key)
+        .fetch_optional(&self.sqlite_pool)
+        .await?;
+
+        let Some((code_hash, attempts, expired)) = stored else {
+            warn!("用户 {} 没有有效的重置码", identity_key);
+            return Ok(None);
+        };
+
+        if expired {
+            warn!("用户 {} 的重置码已过期", identity_key);
+            self.delete_reset_code(identity_key).await?;
+            return Ok(None);
+        }
+
+        if code_hash != Self::hash_reset_code(code) {
+            warn!(
+                "用户 {} 的重置码错误 (第 {} 次)",
+                identity_key,
+                attempts + 1
+            );
+            if attempts + 1 >= MAX_RESET_CODE_ATTEMPTS {
+                warn!("用户 {} 的重置码错误次数过多，已作废", identity_key);
+                self.delete_reset_code(identity_key).await?;
+            } else {
+                sqlx::query(
+                    "UPDATE credential_reset_codes SET attempts = attempts + 1 WHERE identity_key = ?",
+                )
+                .bind(identity_key)
+                .execute(&self.sqlite_pool)
+                .await?;
+            }
+            return Ok(None);
+        }
+
+        // 重置码只能使用一次，先作废再修改密码
+        self.delete_reset_code(identity_key).await?;
+
+        let (db_name, username) = self
+            .stored_names(identity_key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户 {} 不存在或尚未创建数据库", identity_key))?;
+        let provisioner = self.provisioner(self.resolve_engine(identity_key).await?)?;
+        provisioner.set_password(&username, new_password).await?;
+        provisioner.flush_privileges().await?;
+
+        self.record_history(identity_key, "password_reset", Some("密码已通过重置码重置"))
+            .await;
+        info!("用户 {} 的密码已重置", identity_key);
+        Ok(Some(provisioner.credentials(
+            &db_name,
+            &username,
+            new_password,
+        )))
+    }
+
+    async fn delete_reset_code(&self, identity_key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM credential_reset_codes WHERE identity_key = ?")
+            .bind(identity_key)
+            .execute(&self.sqlite_pool)
+            .await?;
+        Ok(())
+    }
+
+    // 存储配额
+
+    /// 统计所有用户数据库的大小并执行存储配额
+    ///
+    /// 配额按用户计算，申请时创建的数据库与命名数据库的大小合计。超出配额的用户在其所有数据库上
+    /// 被撤销 INSERT/UPDATE 权限，回落到配额以内后恢复配档中的写入权限。
+    /// 通过角色授权的用户在其专属角色上撤销和恢复。
+    pub async fn check_storage_quotas(&self) -> Result<StorageCheckResult> {
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                String,
+                String,
+                String,
+                Option<String>,
+                Option<String>,
+                bool,
+            ),
+        >(
+            "SELECT identity_key, db_name, db_user, db_engine, privilege_profile, privilege_role, quota_exceeded FROM applicants WHERE status = 'success' ORDER BY identity_key, id",
+        )
+        .fetch_all(&self.sqlite_pool)
+        .await?;
+
+        let quota_bytes = self.storage.quota_mb as u64 * 1024 * 1024;
+        let mut result = StorageCheckResult {
+            checked_count: 0,
+            downgraded: Vec::new(),
+            restored: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        let mut identities: Vec<(String, Vec<_>)> = Vec::new();
+        for (identity_key, db_name, username, engine, profile, role, was_exceeded) in rows {
+            let database = (db_name, username, engine, profile, role, was_exceeded);
+            match identities.last_mut() {
+                Some((last, databases)) if *last == identity_key => databases.push(database),
+                _ => identities.push((identity_key, vec![database])),
+            }
+        }
+
+        for (identity_key, databases) in identities {
+            // 1. 统计用户所有数据库的大小，任一数据库统计失败时本次不处理该用户
+            let mut measured = Vec::with_capacity(databases.len());
+            for (db_name, _, engine, ..) in &databases {
+                let size = async {
+                    let provisioner = self.provisioner(engine.parse()?)?;
+                    let size = provisioner.database_size(db_name).await?;
+                    anyhow::Ok((provisioner, size))
+                }
+                .await;
+                match size {
+                    Ok(size) => measured.push(size),
+                    Err(e) => {
+                        result.errors.push(format!("{}: {}", identity_key, e));
+                        break;
+                    }
+                }
+            }
+            if measured.len() < databases.len() {
+                continue;
+            }
+            result.checked_count += databases.len() as i32;
+
+            let total: u64 = measured.iter().map(|(_, size)| size).sum();
+            let exceeded = quota_bytes > 0 && total > quota_bytes;
+
+            // 2. 在用户的每个数据库上撤销或恢复写入权限
+            let mut changed = false;
+            for ((db_name, username, _, profile, role, was_exceeded), (provisioner, size)) in
+                databases.into_iter().zip(measured)
+            {
+                let grantee = role.as_deref().unwrap_or(&username);
+
+                let transition = if exceeded && !was_exceeded {
+                    provisioner
+                        .revoke_privileges(Some(&db_name), grantee, QUOTA_RESTRICTED_PRIVILEGES)
+                        .await
+                        .map(|_| true)
+                } else if !exceeded && was_exceeded {
+                    let profile = profile.unwrap_or_else(|| self.default_profile());
+                    match self.profile_privileges(&profile) {
+                        Ok(privileges) => {
+                            let writes: Vec<&str> = privileges
+                                .into_iter()
+                                .filter(|p| QUOTA_RESTRICTED_PRIVILEGES.contains(p))
+                                .collect();
+                            if writes.is_empty() {
+                                Ok(true)
+                            } else {
+                                provisioner
+                                    .grant_privileges(&db_name, grantee, &writes)
+                                    .await
+                                    .map(|_| true)
+                            }
+                        }
+                        Err(e) => Err(e),
+                    }
+                } else {
+                    Ok(false)
+                };
+
+                let quota_exceeded = match transition {
+                    Ok(true) => {
+                        changed = true;
+                        if let Err(e) = provisioner.flush_privileges().await {
+                            result.errors.push(format!("{}: {}", identity_key, e));
+                        }
+                        exceeded
+                    }
+                    Ok(false) => was_exceeded,
+                    Err(e) => {
+                        result.errors.push(format!("{}: {}", identity_key, e));
+                        was_exceeded
+                    }
+                };
+
+                sqlx::query(
+                    "UPDATE applicants SET storage_bytes = ?, storage_checked_at = CURRENT_TIMESTAMP, quota_exceeded = ? WHERE identity_key = ? AND db_name = ? AND status = 'success'",
+                )
+                .bind(size as i64)
+                .bind(quota_exceeded)
+                .bind(&identity_key)
+                .bind(&db_name)
+                .execute(&self.sqlite_pool)
+                .await?;
+            }
+
+            if changed {
+                let event = if exceeded {
+                    warn!(
+                        "用户 {} 的数据库共占用 {} 字节，超出配额 {} MB，限制为只读",
+                        identity_key, total, self.storage.quota_mb
+                    );
+                    result.downgraded.push(identity_key.clone());
+                    "quota_exceeded"
+                } else {
+                    info!(
+                        "用户 {} 的数据库共占用 {} 字节，已回落到配额以内，恢复写入权限",
+                        identity_key, total
+                    );
+                    result.restored.push(identity_key.clone());
+                    "quota_restored"
+                };
+                self.record_history(&identity_key, event, Some(&format!("{} 字节", total)))
+                    .await;
+            }
+        }
+
+        info!(
+            "存储配额检查完成: 统计 {} 个数据库，限制 {} 个用户，恢复 {} 个用户，失败 {} 个",
+            result.checked_count,
+            result.downgraded.len(),
+            result.restored.len(),
+            result.errors.len()
+        );
+        Ok(result)
+    }
+
+    /// 存储统计间隔，0 表示不启动定期统计
+    pub fn storage_check_interval(&self) -> Option<std::time::Duration> {
+        match self.storage.check_interval_secs {
+            0 => None,
+            secs => Some(std::time::Duration::from_secs(secs as u64)),
+        }
     }
 
     /// 获取公开申请记录 (脱敏处理)
@@ -1141,8 +6684,340 @@ impl DatabaseManager {
 
 #[cfg(test)]
 mod tests {
+    use super::fake::{FakeProvisioner, FakeStep};
     use super::*;
 
+    async fn create_fake_manager() -> (DatabaseManager, Arc<FakeProvisioner>) {
+        create_manager_with(FakeProvisioner::new()).await
+    }
+
+    async fn create_manager_with(fake: FakeProvisioner) -> (DatabaseManager, Arc<FakeProvisioner>) {
+        let config = AppConfig {
+            server: crate::config::ServerConfig {
+                host: "127.0.0.1".to_string(),
+                port: 3000,
+            },
+            database: crate::config::DatabaseConfig {
+                sqlite_path: ":memory:".to_string(),
+                default_engine: DatabaseEngine::MySql,
+            },
+            mysql: Some(crate::config::MySQLConfig {
+                host: "localhost".to_string(),
+                port: 3306,
+                username: "test".to_string(),
+                password: "test".to_string(),
+                database: "test".to_string(),
+                allowed_host: None,
+            }),
+            postgres: None,
+            naming: NamingConfig::default(),
+            privileges: PrivilegeConfig::default(),
+            resource_limits: ResourceLimits::default(),
+            storage: StorageConfig::default(),
+            lifecycle: LifecycleConfig::default(),
+            quota: DatabaseQuotaConfig::default(),
+            application: ApplicationConfig::default(),
+            import: ImportConfig::default(),
+            reset: ResetConfig::default(),
+            admin: crate::config::AdminConfig {
+                password: "test_admin".to_string(),
+            },
+        };
+        let fake = Arc::new(fake);
+        let manager = DatabaseManager::with_provisioner(&config, fake.clone())
+            .await
+            .unwrap();
+        (manager, fake)
+    }
+
+    #[tokio::test]
+    async fn test_transaction_rolls_back_when_record_fails() {
+        let (manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010201", None, None, None, None)
+            .await
+            .unwrap();
+
+        // 有效的旧记录占用了 (identity_key, db_name)，其数据库仍在时直接拒绝，不删除已有的数据库
+        sqlx::query(
+            "INSERT INTO applicants (identity_key, db_name, db_user, status) VALUES (?, 'db_2023010201', '', 'suspended')",
+        )
+        .bind("2023010201")
+        .execute(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        fake.create_database("db_2023010201").await.unwrap();
+
+        let result = manager
+            .provision_database_with_transaction("2023010201", "Passw0rd!")
+            .await;
+        assert!(result.is_err());
+        assert!(fake.has_database("db_2023010201"));
+        assert!(!fake.has_user("user_2023010201"));
+
+        // 数据库已不存在时本次申请会创建资源，写入记录触发唯一索引后只删除本次创建的资源
+        fake.remove_database("db_2023010201");
+        let result = manager
+            .provision_database_with_transaction("2023010201", "Passw0rd!")
+            .await;
+        assert!(result.is_err());
+        assert!(!fake.has_database("db_2023010201"));
+        assert!(!fake.has_user("user_2023010201"));
+    }
+
+    #[tokio::test]
+    async fn test_provision_stops_at_failed_step() {
+        let (manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010202", None, None, None, None)
+            .await
+            .unwrap();
+
+        fake.fail_on(FakeStep::CreateUser);
+        let result = manager.provision_database("2023010202", "Passw0rd!").await;
+        assert!(result.is_err());
+        // 本次创建的数据库已回滚
+        assert!(fake.databases().is_empty());
+        assert!(!fake.has_user("user_2023010202"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_apply_keeps_resources_it_did_not_create() {
+        let (manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010212", None, None, None, None)
+            .await
+            .unwrap();
+
+        // 名称已被其他记录占用时直接拒绝，不删除占用者的资源
+        fake.create_database("db_2023010212").await.unwrap();
+        fake.create_user("user_2023010212", "Passw0rd!", &ResourceLimits::default())
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO applicants (identity_key, db_name, db_user, status) VALUES ('someone_else', 'db_2023010212', 'user_2023010212', 'success')",
+        )
+        .execute(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        let result = manager
+            .provision_database_with_transaction("2023010212", "Passw0rd!")
+            .await;
+        assert!(result.is_err());
+        assert!(fake.has_database("db_2023010212"));
+        assert!(fake.has_user("user_2023010212"));
+
+        // 没有记录的残留用户同样不会被接管或删除
+        manager
+            .add_student_id("2023010222", None, None, None, None)
+            .await
+            .unwrap();
+        fake.create_user("user_2023010222", "Passw0rd!", &ResourceLimits::default())
+            .await
+            .unwrap();
+        assert!(
+            manager
+                .provision_database_with_transaction("2023010222", "Other0rd!")
+                .await
+                .is_err()
+        );
+        assert!(!fake.has_database("db_2023010222"));
+        assert_eq!(
+            fake.password_of("user_2023010222").as_deref(),
+            Some("Passw0rd!")
+        );
+
+        // 修复只删除有效的那条记录，保留已删除记录（隔离库）和命名数据库的记录
+        sqlx::query(
+            r#"
+            INSERT INTO applicants (identity_key, db_name, db_user, status, archive_name) VALUES
+                ('2023010213', 'db_2023010213', 'user_2023010213', 'deleted', 'del_db_2023010213'),
+                ('2023010213', 'db_2023010213', 'user_2023010213', 'success', NULL)
+            "#,
+        )
+        .execute(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO applicants (identity_key, db_name, db_suffix, db_user, status) VALUES ('2023010213', 'db_2023010213_extra', 'extra', 'user_2023010213', 'success')",
+        )
+        .execute(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        manager
+            .repair_data_inconsistency("2023010213")
+            .await
+            .unwrap();
+        let remaining: Vec<(String, Option<String>)> = sqlx::query_as(
+            "SELECT status, db_suffix FROM applicants WHERE identity_key = '2023010213' ORDER BY id",
+        )
+        .fetch_all(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            remaining,
+            vec![
+                ("deleted".to_string(), None),
+                ("success".to_string(), Some("extra".to_string()))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_profile_granted_through_role() {
+        let (manager, fake) = create_manager_with(FakeProvisioner::with_roles()).await;
+        manager
+            .add_student_id("2023010203", None, None, None, None)
+            .await
+            .unwrap();
+        manager
+            .provision_database_with_transaction("2023010203", "Passw0rd!")
+            .await
+            .unwrap();
+
+        // 用户通过角色获得配置档权限，角色只作用于用户自己的数据库
+        let role = DatabaseManager::role_name_for("dml", "db_2023010203");
+        assert!(role.len() <= MAX_USERNAME_LEN);
+        assert_eq!(fake.roles_of("user_2023010203"), vec![role.clone()]);
+        let mut expected: Vec<String> = provisioner::STUDENT_PRIVILEGES
+            .iter()
+            .map(|p| p.to_string())
+            .collect();
+        expected.sort();
+        assert_eq!(fake.grants("db_2023010203", "user_2023010203"), expected);
+
+        // 切换配置档时改为授予新角色，旧角色被删除
+        manager
+            .change_privilege_profile("2023010203", "readonly")
+            .await
+            .unwrap();
+        let readonly_role = DatabaseManager::role_name_for("readonly", "db_2023010203");
+        assert_eq!(
+            fake.roles_of("user_2023010203"),
+            vec![readonly_role.clone()]
+        );
+        assert_eq!(fake.roles(), vec![readonly_role]);
+        assert_eq!(
+            fake.grants("db_2023010203", "user_2023010203"),
+            vec!["SELECT".to_string()]
+        );
+
+        // 删除用户时一并删除角色
+        manager
+            .admin_delete_user("2023010203", "测试")
+            .await
+            .unwrap();
+        assert!(fake.roles().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_profile_change_updates_all_roles() {
+        let (manager, fake) = create_manager_with(FakeProvisioner::with_roles()).await;
+        manager.sync_profile_roles().await.unwrap();
+        for id in ["2023010204", "2023010205"] {
+            manager
+                .add_student_id(id, None, None, None, None)
+                .await
+                .unwrap();
+            manager
+                .provision_database_with_transaction(id, "Passw0rd!")
+                .await
+                .unwrap();
+        }
+
+        // 配置档权限未变化时不需要更新
+        assert_eq!(manager.sync_profile_roles().await.unwrap().0, 0);
+
+        manager.privileges.write().unwrap().profiles.insert(
+            "dml".to_string(),
+            vec!["SELECT".to_string(), "INSERT".to_string()],
+        );
+        let (updated, errors) = manager.sync_profile_roles().await.unwrap();
+        assert_eq!(updated, 2);
+        assert!(errors.is_empty());
+        for id in ["2023010204", "2023010205"] {
+            assert_eq!(
+                fake.grants(&format!("db_{}", id), &format!("user_{}", id)),
+                vec!["INSERT".to_string(), "SELECT".to_string()]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_saved_profile_applies_immediately() {
+        for (provisioner, with_roles) in [
+            (FakeProvisioner::with_roles(), true),
+            (FakeProvisioner::default(), false),
+        ] {
+            let (manager, fake) = create_manager_with(provisioner).await;
+            manager.sync_profile_roles().await.unwrap();
+            manager
+                .add_student_id("2023010207", None, None, None, None)
+                .await
+                .unwrap();
+            manager
+                .provision_database_with_transaction("2023010207", "Passw0rd!")
+                .await
+                .unwrap();
+            // 停用期间修改配置档，恢复后使用新的权限
+            manager.suspend_user("2023010207", "调查").await.unwrap();
+
+            // 不允许的权限不会被保存
+            assert!(
+                manager
+                    .save_privilege_profile("dml", &["SUPER".to_string()])
+                    .await
+                    .is_err()
+            );
+
+            let (updated, errors) = manager
+                .save_privilege_profile("dml", &["select".to_string()])
+                .await
+                .unwrap();
+            assert_eq!(updated, 1);
+            assert!(errors.is_empty());
+            assert_eq!(
+                fake.grants("db_2023010207", "user_2023010207"),
+                vec!["SELECT".to_string()]
+            );
+            assert_eq!(!fake.roles().is_empty(), with_roles);
+            manager.unsuspend_user("2023010207").await.unwrap();
+            assert_eq!(
+                fake.grants("db_2023010207", "user_2023010207"),
+                vec!["SELECT".to_string()]
+            );
+
+            // 保存的配置档在重启后仍然有效
+            let mut restarted = manager.privileges.read().unwrap().clone();
+            restarted
+                .profiles
+                .insert("dml".to_string(), vec!["ALL PRIVILEGES".to_string()]);
+            *manager.privileges.write().unwrap() = restarted;
+            manager.load_saved_profiles().await.unwrap();
+            assert_eq!(manager.profile_privileges("dml").unwrap(), vec!["SELECT"]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roles_fall_back_to_direct_grants() {
+        let (manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010206", None, None, None, None)
+            .await
+            .unwrap();
+        manager
+            .provision_database_with_transaction("2023010206", "Passw0rd!")
+            .await
+            .unwrap();
+
+        assert!(fake.roles().is_empty());
+        assert!(fake.roles_of("user_2023010206").is_empty());
+        assert!(
+            fake.grants("db_2023010206", "user_2023010206")
+                .contains(&"SELECT".to_string())
+        );
+    }
+
     #[test]
     fn test_is_valid_identifier() {
         // 有效的标识符
@@ -1164,6 +7039,15 @@ mod tests {
         assert!(!DatabaseManager::is_valid_identifier(&long_name)); // 超过64字符
     }
 
+    #[test]
+    fn test_is_valid_student_id() {
+        assert!(DatabaseManager::is_valid_student_id("2023010101"));
+
+        assert!(!DatabaseManager::is_valid_student_id("202301010")); // 少于10位
+        assert!(!DatabaseManager::is_valid_student_id("20230101011")); // 多于10位
+        assert!(!DatabaseManager::is_valid_student_id("2023o10101")); // 包含字母
+    }
+
     #[test]
     fn test_is_valid_host() {
         // 有效的主机
@@ -1235,4 +7119,753 @@ mod tests {
         let long_hostname = "a".repeat(254);
         assert!(!DatabaseManager::is_valid_hostname(&long_hostname));
     }
+
+    #[test]
+    fn test_name_mapping_keeps_plain_keys() {
+        let naming = NamingConfig::default();
+        assert_eq!(
+            DatabaseManager::database_name_for(&naming, "2023010101"),
+            "db_2023010101"
+        );
+        assert_eq!(
+            DatabaseManager::username_for(&naming, "2023010101"),
+            "user_2023010101"
+        );
+        assert_eq!(
+            DatabaseManager::username_for(&naming, "USER123"),
+            "user_USER123"
+        );
+    }
+
+    #[test]
+    fn test_name_mapping_shortens_long_and_unusual_keys() {
+        let naming = NamingConfig::default();
+        let long_key = format!("{}A", "2023".repeat(12));
+        let other_key = format!("{}B", "2023".repeat(12));
+
+        let username = DatabaseManager::username_for(&naming, &long_key);
+        assert!(username.len() <= MAX_USERNAME_LEN, "{}", username);
+        assert!(
+            DatabaseManager::is_valid_username(&username),
+            "{}",
+            username
+        );
+        // 确定性且不同身份标识不会冲突
+        assert_eq!(username, DatabaseManager::username_for(&naming, &long_key));
+        assert_ne!(username, DatabaseManager::username_for(&naming, &other_key));
+
+        let db_name = DatabaseManager::database_name_for(&naming, &long_key);
+        assert_eq!(db_name, format!("db_{}", long_key));
+
+        for key in ["张三-01", "a-b-c", "学生"] {
+            let db_name = DatabaseManager::database_name_for(&naming, key);
+            let username = DatabaseManager::username_for(&naming, key);
+            assert!(
+                DatabaseManager::is_valid_database_name(&db_name),
+                "{}",
+                db_name
+            );
+            assert!(
+                DatabaseManager::is_valid_username(&username),
+                "{}",
+                username
+            );
+            assert!(db_name.is_ascii() && username.is_ascii());
+        }
+        assert_ne!(
+            DatabaseManager::username_for(&naming, "a-b"),
+            DatabaseManager::username_for(&naming, "a_b")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_long_identity_uses_stored_names() {
+        let (manager, fake) = create_fake_manager().await;
+        let identity_key = "2023".repeat(12);
+        manager
+            .add_student_id(&identity_key, None, None, None, None)
+            .await
+            .unwrap();
+
+        let credentials = manager
+            .provision_database_with_transaction(&identity_key, "Passw0rd!")
+            .await
+            .unwrap();
+        assert!(credentials.username.len() <= MAX_USERNAME_LEN);
+        assert!(fake.has_user(&credentials.username));
+        assert!(
+            manager
+                .verify_data_consistency(&identity_key)
+                .await
+                .unwrap()
+        );
+
+        manager
+            .admin_delete_user(&identity_key, "测试")
+            .await
+            .unwrap();
+        assert!(fake.users().is_empty());
+        let databases = fake.databases();
+        assert_eq!(databases.len(), 1);
+        assert!(databases[0].len() <= MAX_DATABASE_NAME_LEN);
+        assert!(databases[0].starts_with("del_"));
+    }
+
+    #[tokio::test]
+    async fn test_team_names_do_not_collide_with_students() {
+        let (manager, fake) = create_fake_manager().await;
+
+        // 新团队的名称不会与任何学号映射出的名称重合
+        let team = manager.create_team("proj", None, None).await.unwrap();
+        assert_eq!(team.db_name, "db__team_proj");
+        manager
+            .add_student_id("team_proj", None, None, None, None)
+            .await
+            .unwrap();
+        let credentials = manager
+            .provision_database_with_transaction("team_proj", "Passw0rd!")
+            .await
+            .unwrap();
+        assert_eq!(credentials.db_name, "db_team_proj");
+        assert!(fake.has_database("db__team_proj"));
+
+        // 旧版本创建的团队占用了 db_team_old，映射到同一名称的学号不能加入白名单
+        fake.create_database("db_team_old").await.unwrap();
+        sqlx::query(
+            "INSERT INTO teams (name, db_name, db_engine, privilege_profile) VALUES ('old', 'db_team_old', 'mysql', 'dml')",
+        )
+        .execute(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        assert!(
+            manager
+                .add_student_id("team_old", None, None, None, None)
+                .await
+                .is_err()
+        );
+
+        // 升级前已在白名单中的学号申请失败，团队数据库保持原样
+        sqlx::query("INSERT INTO student_ids (student_id) VALUES ('team_old')")
+            .execute(&manager.sqlite_pool)
+            .await
+            .unwrap();
+        assert!(
+            manager
+                .provision_database_with_transaction("team_old", "Passw0rd!")
+                .await
+                .is_err()
+        );
+        assert!(fake.has_database("db_team_old"));
+        assert!(!fake.has_user("user_team_old"));
+    }
+
+    #[tokio::test]
+    async fn test_team_members_follow_profile_and_account() {
+        let (manager, fake) = create_manager_with(FakeProvisioner::with_roles()).await;
+        manager.sync_profile_roles().await.unwrap();
+        manager.create_team("proj", None, None).await.unwrap();
+        for id in ["2023010230", "2023010231"] {
+            manager
+                .add_student_id(id, None, None, None, None)
+                .await
+                .unwrap();
+            manager
+                .add_team_member("proj", id, "Passw0rd!")
+                .await
+                .unwrap();
+        }
+        manager
+            .provision_database_with_transaction("2023010230", "Passw0rd!")
+            .await
+            .unwrap();
+        let member = DatabaseManager::team_username(&manager.naming, "proj", "2023010230");
+
+        // 启用角色时团队成员通过角色授权，修改配置档后团队数据库的权限随之更新
+        assert_eq!(fake.roles_of(&member).len(), 1);
+        manager
+            .save_privilege_profile("dml", &["SELECT".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            fake.grants("db__team_proj", &member),
+            vec!["SELECT".to_string()]
+        );
+
+        // 停用学生时一同锁定其团队数据库用户，恢复时解锁
+        manager.suspend_user("2023010230", "调查").await.unwrap();
+        assert!(fake.is_locked(&member));
+        manager.unsuspend_user("2023010230").await.unwrap();
+        assert!(!fake.is_locked(&member));
+
+        // 到期锁定和延期解锁同样作用于团队数据库用户
+        sqlx::query(
+            "UPDATE applicants SET expires_at = datetime('now', '-1 days') WHERE identity_key = '2023010230'",
+        )
+        .execute(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        manager.process_expirations().await.unwrap();
+        assert!(fake.is_locked(&member));
+        assert!(
+            manager
+                .add_team_member("proj", "2023010230", "Passw0rd!")
+                .await
+                .is_err()
+        );
+        manager.extend_expiry("2023010230", 30).await.unwrap();
+        assert!(!fake.is_locked(&member));
+
+        // 删除学生时将其移出团队
+        manager
+            .admin_delete_user("2023010230", "毕业")
+            .await
+            .unwrap();
+        assert!(!fake.has_user(&member));
+        let team = manager.get_team("proj").await.unwrap();
+        assert_eq!(team.members.len(), 1);
+        assert_eq!(team.members[0].identity_key, "2023010231");
+
+        // 不接管服务器上已有的同名数据库
+        fake.create_database("db__team_stray").await.unwrap();
+        assert!(manager.create_team("stray", None, None).await.is_err());
+        assert!(fake.has_database("db__team_stray"));
+    }
+
+    #[tokio::test]
+    async fn test_named_databases_share_storage_quota_and_quarantine() {
+        let (mut manager, fake) = create_fake_manager().await;
+        manager.storage.quota_mb = 1;
+        manager
+            .add_student_id("2023010232", None, None, None, None)
+            .await
+            .unwrap();
+        manager
+            .provision_database_with_transaction("2023010232", "Passw0rd!")
+            .await
+            .unwrap();
+        manager
+            .set_database_quota("2023010232", Some(3))
+            .await
+            .unwrap();
+        manager
+            .create_named_database("2023010232", "alpha")
+            .await
+            .unwrap();
+
+        // 存储配额按用户合计，每个数据库都未超出但合计超出时所有数据库都只读
+        fake.set_database_size("db_2023010232", 600 * 1024);
+        fake.set_database_size("db_2023010232_alpha", 600 * 1024);
+        let result = manager.check_storage_quotas().await.unwrap();
+        assert_eq!(result.checked_count, 2);
+        assert_eq!(result.downgraded, vec!["2023010232".to_string()]);
+        for db_name in ["db_2023010232", "db_2023010232_alpha"] {
+            assert!(
+                !fake
+                    .grants(db_name, "user_2023010232")
+                    .contains(&"INSERT".to_string())
+            );
+        }
+
+        // 超出配额时新建的命名数据库同样只读
+        manager
+            .create_named_database("2023010232", "beta")
+            .await
+            .unwrap();
+        let grants = fake.grants("db_2023010232_beta", "user_2023010232");
+        assert!(grants.contains(&"SELECT".to_string()));
+        assert!(!grants.contains(&"INSERT".to_string()));
+
+        // 单独删除的命名数据库移入隔离库，保留期内不能重新创建同名数据库
+        manager
+            .delete_named_database("2023010232", "alpha")
+            .await
+            .unwrap();
+        assert!(!fake.has_database("db_2023010232_alpha"));
+        assert!(
+            fake.databases()
+                .iter()
+                .any(|name| name.starts_with("del_db_2023010232_alpha_"))
+        );
+        assert!(
+            manager
+                .create_named_database("2023010232", "alpha")
+                .await
+                .is_err()
+        );
+
+        // 删除后合计回落到配额以内，恢复写入权限
+        let result = manager.check_storage_quotas().await.unwrap();
+        assert_eq!(result.restored, vec!["2023010232".to_string()]);
+        assert!(
+            fake.grants("db_2023010232_beta", "user_2023010232")
+                .contains(&"INSERT".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_named_databases_respect_quota() {
+        let (manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010217", None, None, None, None)
+            .await
+            .unwrap();
+        manager
+            .provision_database_with_transaction("2023010217", "Passw0rd!")
+            .await
+            .unwrap();
+        manager
+            .set_database_quota("2023010217", Some(2))
+            .await
+            .unwrap();
+
+        // 只剩一个名额时同时创建两个命名数据库，只有一个成功，失败的一方不留下数据库
+        let (first, second) = tokio::join!(
+            manager.create_named_database("2023010217", "alpha"),
+            manager.create_named_database("2023010217", "beta")
+        );
+        assert_eq!(first.is_ok() as u8 + second.is_ok() as u8, 1);
+        let owned = manager
+            .list_student_databases("2023010217")
+            .await
+            .unwrap()
+            .databases;
+        assert_eq!(owned.len(), 2);
+        assert_eq!(fake.databases().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_applications_respect_server_capacity() {
+        let (mut manager, fake) = create_fake_manager().await;
+        manager.application.max_databases_per_server = 1;
+        for id in ["2023010218", "2023010219"] {
+            manager
+                .add_student_id(id, None, None, None, None)
+                .await
+                .unwrap();
+        }
+
+        // 容量检查与创建串行执行，同时申请时只有一个成功
+        let (first, second) = tokio::join!(
+            manager.provision_database_with_transaction("2023010218", "Passw0rd!"),
+            manager.provision_database_with_transaction("2023010219", "Passw0rd!")
+        );
+        let rejected = first.err().or(second.err()).unwrap();
+        assert!(matches!(
+            rejected.downcast_ref::<ApplicationRejection>(),
+            Some(ApplicationRejection::CapacityReached(_))
+        ));
+        assert_eq!(fake.databases().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_replayed_as_student() {
+        let (manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010220", None, None, None, None)
+            .await
+            .unwrap();
+        let credentials = manager
+            .provision_database_with_transaction("2023010220", "Passw0rd!")
+            .await
+            .unwrap();
+        manager
+            .import_sql(
+                "2023010220",
+                &credentials.password,
+                b"CREATE TABLE t (id INT);\n",
+            )
+            .await
+            .unwrap()
+            .unwrap();
+
+        // 检查点中混入导出文件以外的语句时拒绝恢复，数据库保持不变
+        sqlx::query("INSERT INTO database_baselines (identity_key, script) VALUES (?, ?)")
+            .bind("2023010220")
+            .bind("CREATE TABLE `t` (id INT);\nGRANT ALL ON *.* TO 'user_2023010220';\n")
+            .execute(&manager.sqlite_pool)
+            .await
+            .unwrap();
+        assert!(
+            manager
+                .reset_own_database("2023010220", &credentials.password)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            fake.executed_statements("db_2023010220"),
+            vec!["CREATE TABLE t (id INT)"]
+        );
+
+        // 账号锁定时无法以学生身份恢复，清空前即失败
+        sqlx::query("UPDATE database_baselines SET script = ? WHERE identity_key = ?")
+            .bind("CREATE TABLE `t` (id INT);\nINSERT INTO `t` (`id`) VALUES\n(1);\n")
+            .bind("2023010220")
+            .execute(&manager.sqlite_pool)
+            .await
+            .unwrap();
+        fake.lock_user("user_2023010220").await.unwrap();
+        assert!(
+            manager
+                .reset_own_database("2023010220", &credentials.password)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            fake.executed_statements("db_2023010220"),
+            vec!["CREATE TABLE t (id INT)"]
+        );
+        fake.unlock_user("user_2023010220").await.unwrap();
+
+        let result = manager
+            .reset_own_database("2023010220", &credentials.password)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.restored_statements, 2);
+        assert_eq!(
+            fake.executed_statements("db_2023010220"),
+            vec![
+                "CREATE TABLE `t` (id INT)",
+                "INSERT INTO `t` (`id`) VALUES\n(1)"
+            ]
+        );
+        assert!(manager.reset_database("2023010220").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dump_rejected_when_all_slots_busy() {
+        let (manager, _fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010216", None, None, None, None)
+            .await
+            .unwrap();
+        manager
+            .provision_database_with_transaction("2023010216", "Passw0rd!")
+            .await
+            .unwrap();
+
+        let busy = Arc::clone(&manager.dump_slots)
+            .try_acquire_many_owned(MAX_CONCURRENT_DUMPS as u32)
+            .unwrap();
+        assert!(manager.dump_database("2023010216").await.is_err());
+
+        // 导出结束后名额归还
+        drop(busy);
+        let mut dump = manager.dump_database("2023010216").await.unwrap();
+        while let Some(chunk) = dump.chunks.recv().await {
+            chunk.unwrap();
+        }
+        assert_eq!(manager.dump_slots.available_permits(), MAX_CONCURRENT_DUMPS);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_deletion_keeps_each_quarantine() {
+        let (manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010214", None, None, None, None)
+            .await
+            .unwrap();
+
+        // 保留期内再次申请并删除，两次的隔离库互不覆盖
+        for _ in 0..2 {
+            sqlx::query("UPDATE student_ids SET has_applied = 0 WHERE student_id = '2023010214'")
+                .execute(&manager.sqlite_pool)
+                .await
+                .unwrap();
+            manager
+                .provision_database_with_transaction("2023010214", "Passw0rd!")
+                .await
+                .unwrap();
+            manager
+                .admin_delete_user("2023010214", "测试")
+                .await
+                .unwrap();
+        }
+        let archives: Vec<String> = sqlx::query_scalar(
+            "SELECT archive_name FROM applicants WHERE identity_key = '2023010214' AND status = 'deleted' ORDER BY id",
+        )
+        .fetch_all(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        assert_eq!(archives.len(), 2);
+        assert_ne!(archives[0], archives[1]);
+        assert_eq!(fake.databases(), archives);
+    }
+
+    #[tokio::test]
+    async fn test_failed_restore_returns_database_to_quarantine() {
+        let (mut manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010223", None, None, None, None)
+            .await
+            .unwrap();
+        manager
+            .provision_database_with_transaction("2023010223", "Passw0rd!")
+            .await
+            .unwrap();
+        manager
+            .admin_delete_user("2023010223", "测试")
+            .await
+            .unwrap();
+        let quarantine = fake.databases().pop().unwrap();
+
+        // 授权失败时数据库移回隔离库，删除本次创建的用户，记录保持可恢复
+        fake.fail_on(FakeStep::GrantPrivileges);
+        assert!(
+            manager
+                .restore_user("2023010223", "Restor3d!")
+                .await
+                .is_err()
+        );
+        assert_eq!(fake.databases(), vec![quarantine.clone()]);
+        assert!(!fake.has_user("user_2023010223"));
+        let (status, archive_name): (String, Option<String>) = sqlx::query_as(
+            "SELECT status, archive_name FROM applicants WHERE identity_key = '2023010223'",
+        )
+        .fetch_one(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        assert_eq!(status, "deleted");
+        assert_eq!(archive_name, Some(quarantine));
+        fake.clear_failures();
+
+        // 数据库服务器已满时不恢复
+        manager.application.max_databases_per_server = 1;
+        manager
+            .add_student_id("2023010224", None, None, None, None)
+            .await
+            .unwrap();
+        manager
+            .provision_database_with_transaction("2023010224", "Passw0rd!")
+            .await
+            .unwrap();
+        let rejected = manager
+            .restore_user("2023010223", "Restor3d!")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            rejected.downcast_ref::<ApplicationRejection>(),
+            Some(ApplicationRejection::CapacityReached(_))
+        ));
+
+        manager.application.max_databases_per_server = 0;
+        manager
+            .restore_user("2023010223", "Restor3d!")
+            .await
+            .unwrap();
+        assert!(fake.has_database("db_2023010223"));
+        assert_eq!(
+            fake.password_of("user_2023010223").as_deref(),
+            Some("Restor3d!")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_deletion_leaves_database_in_place() {
+        let (manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010215", None, None, None, None)
+            .await
+            .unwrap();
+        manager
+            .provision_database_with_transaction("2023010215", "Passw0rd!")
+            .await
+            .unwrap();
+
+        // 隔离库移动失败时不修改记录，数据库和用户保持原样
+        fake.fail_on(FakeStep::MoveDatabase);
+        assert!(
+            manager
+                .admin_delete_user("2023010215", "测试")
+                .await
+                .is_err()
+        );
+        assert!(fake.has_database("db_2023010215"));
+        assert!(fake.has_user("user_2023010215"));
+        assert!(manager.verify_data_consistency("2023010215").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_expiry_locks_then_archives() {
+        let (manager, fake) = create_fake_manager().await;
+        manager
+            .add_student_id("2023010209", None, Some("数据库原理1班"), None, None)
+            .await
+            .unwrap();
+        manager
+            .set_class_expiry("数据库原理1班", 120)
+            .await
+            .unwrap();
+        manager
+            .provision_database_with_transaction("2023010209", "Passw0rd!")
+            .await
+            .unwrap();
+
+        let expires_at: Option<String> =
+            sqlx::query_scalar("SELECT expires_at FROM applicants WHERE identity_key = ?")
+                .bind("2023010209")
+                .fetch_one(&manager.sqlite_pool)
+                .await
+                .unwrap();
+        assert!(expires_at.is_some());
+        assert!(
+            manager
+                .process_expirations()
+                .await
+                .unwrap()
+                .locked
+                .is_empty()
+        );
+
+        let set_expiry = |offset: &'static str| {
+            sqlx::query(
+                "UPDATE applicants SET expires_at = datetime('now', ?) WHERE identity_key = ?",
+            )
+            .bind(offset)
+            .bind("2023010209")
+            .execute(&manager.sqlite_pool)
+        };
+
+        // 到期后锁定账号，数据保留
+        set_expiry("-1 days").await.unwrap();
+        let result = manager.process_expirations().await.unwrap();
+        assert_eq!(result.locked, vec!["2023010209"]);
+        assert!(result.archived.is_empty());
+        assert!(fake.is_locked("user_2023010209"));
+        assert!(fake.has_database("db_2023010209"));
+
+        // 延期后解锁并恢复正常状态
+        let extended = manager.extend_expiry("2023010209", 30).await.unwrap();
+        assert_eq!(extended.status, "success");
+        assert!(!fake.is_locked("user_2023010209"));
+
+        // 宽限期结束后归档并删除用户和原数据库
+        set_expiry("-31 days").await.unwrap();
+        let result = manager.process_expirations().await.unwrap();
+        assert_eq!(result.locked, vec!["2023010209"]);
+        assert_eq!(result.archived, vec!["2023010209"]);
+        let archive: String = sqlx::query_scalar(
+            "SELECT archive_name FROM applicants WHERE identity_key = ? AND status = 'archived'",
+        )
+        .bind("2023010209")
+        .fetch_one(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        assert!(archive.starts_with("arc_db_2023010209_"));
+        assert_eq!(fake.databases(), vec![archive.clone()]);
+        assert!(!fake.has_user("user_2023010209"));
+
+        // 已归档的数据库不能再延期，但可以从归档库恢复
+        assert!(manager.extend_expiry("2023010209", 30).await.is_err());
+        let credentials = manager
+            .restore_user("2023010209", "N3wPassw0rd!")
+            .await
+            .unwrap();
+        assert_eq!(credentials.db_name, "db_2023010209");
+        assert_eq!(fake.databases(), vec!["db_2023010209".to_string()]);
+        assert_eq!(
+            fake.password_of("user_2023010209").as_deref(),
+            Some("N3wPassw0rd!")
+        );
+
+        // 再次到期后重新归档
+        set_expiry("-31 days").await.unwrap();
+        let result = manager.process_expirations().await.unwrap();
+        assert_eq!(result.archived, vec!["2023010209"]);
+        assert_eq!(fake.databases(), vec![archive.clone()]);
+
+        // 删除后保留期结束，归档库被清除
+        manager
+            .admin_delete_user("2023010209", "课程结束")
+            .await
+            .unwrap();
+        assert!(fake.has_database(&archive));
+        sqlx::query(
+            "UPDATE applicants SET deleted_at = datetime('now', '-8 days') WHERE identity_key = ?",
+        )
+        .bind("2023010209")
+        .execute(&manager.sqlite_pool)
+        .await
+        .unwrap();
+        let result = manager.process_expirations().await.unwrap();
+        assert_eq!(result.purged, vec!["2023010209"]);
+        assert!(fake.databases().is_empty());
+    }
+
+    #[test]
+    fn test_naming_template_with_term() {
+        let naming = NamingConfig {
+            template: "{prefix}_{term}_{identity}".to_string(),
+            database_prefix: "xa".to_string(),
+            username_prefix: "xa".to_string(),
+            term: Some("2025s".to_string()),
+        };
+        assert!(DatabaseManager::validate_naming(&naming).is_ok());
+        assert_eq!(
+            DatabaseManager::database_name_for(&naming, "2023010101"),
+            "xa_2025s_2023010101"
+        );
+        assert_eq!(
+            DatabaseManager::username_for(&naming, "2023010101"),
+            "xa_2025s_2023010101"
+        );
+
+        // 超长时仍然保留模板中的前缀和学期
+        let username = DatabaseManager::username_for(&naming, &"1".repeat(50));
+        assert!(username.starts_with("xa_2025s_"), "{}", username);
+        assert!(username.len() <= MAX_USERNAME_LEN);
+    }
+
+    #[test]
+    fn test_validate_naming_rejects_bad_templates() {
+        let with_template = |template: &str| NamingConfig {
+            template: template.to_string(),
+            ..NamingConfig::default()
+        };
+
+        assert!(DatabaseManager::validate_naming(&NamingConfig::default()).is_ok());
+        // 缺少或重复 {identity}
+        assert!(DatabaseManager::validate_naming(&with_template("{prefix}_x")).is_err());
+        assert!(DatabaseManager::validate_naming(&with_template("{identity}_{identity}")).is_err());
+        // 以数字开头的名称不合法
+        assert!(DatabaseManager::validate_naming(&with_template("{identity}")).is_err());
+        // 未知占位符或非法字符
+        assert!(
+            DatabaseManager::validate_naming(&with_template("{prefix}_{school}_{identity}"))
+                .is_err()
+        );
+        assert!(DatabaseManager::validate_naming(&with_template("{prefix}-{identity}")).is_err());
+        // 使用 {term} 但未设置学期
+        assert!(
+            DatabaseManager::validate_naming(&with_template("{prefix}_{term}_{identity}")).is_err()
+        );
+        // 前缀过长，用户名无法容纳哈希后缀
+        let long_prefix = NamingConfig {
+            username_prefix: "u".repeat(30),
+            ..NamingConfig::default()
+        };
+        assert!(DatabaseManager::validate_naming(&long_prefix).is_err());
+    }
+
+    #[test]
+    fn test_parse_database_engine() {
+        assert_eq!(
+            "mysql".parse::<DatabaseEngine>().unwrap(),
+            DatabaseEngine::MySql
+        );
+        assert_eq!(
+            "MariaDB".parse::<DatabaseEngine>().unwrap(),
+            DatabaseEngine::MySql
+        );
+        assert_eq!(
+            "postgresql".parse::<DatabaseEngine>().unwrap(),
+            DatabaseEngine::Postgres
+        );
+        assert_eq!(
+            " pg ".parse::<DatabaseEngine>().unwrap(),
+            DatabaseEngine::Postgres
+        );
+        assert!("oracle".parse::<DatabaseEngine>().is_err());
+        assert!("".parse::<DatabaseEngine>().is_err());
+    }
 }
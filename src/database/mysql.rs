@@ -0,0 +1,1183 @@
+use super::DatabaseManager;
+use super::dump::{self, DumpSender, DumpValue, InsertBatcher};
+use super::grader::{GRADER_MAX_ROWS, GRADER_QUERY_TIMEOUT_MS, QueryRows};
+use super::import::SqlStatement;
+use super::provisioner::Provisioner;
+use super::similarity::{SIMILARITY_ROWS_PER_TABLE, SchemaColumn, SchemaIndex, SchemaSnapshot};
+use crate::config::MySQLConfig;
+use crate::models::{DatabaseCredentials, DatabaseEngine, ResourceLimits};
+use crate::utils::encode_uri_component;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use log::{error, info, warn};
+use sqlx::mysql::{MySqlConnectOptions, MySqlConnection};
+use sqlx::{Connection, Executor, MySql, Pool, Row};
+
+/// 基于 MySQL 的供应后端
+pub struct MySqlProvisioner {
+    pool: Pool<MySql>,
+    config: MySQLConfig,
+}
+
+impl MySqlProvisioner {
+    /// 连接 MySQL 并创建供应后端
+    pub async fn connect(config: &MySQLConfig, max_retries: u32) -> Result<Self> {
+        let pool = Self::connect_with_retry(config, max_retries).await?;
+        Ok(Self {
+            pool,
+            config: config.clone(),
+        })
+    }
+
+    /// MySQL 连接重试
+    async fn connect_with_retry(config: &MySQLConfig, max_retries: u32) -> Result<Pool<MySql>> {
+        let mysql_url = format!(
+            "mysql://{}:{}@{}:{}/{}?ssl-mode=disabled&allowPublicKeyRetrieval=true",
+            config.username, config.password, config.host, config.port, config.database
+        );
+
+        let mut last_error = None;
+
+        for attempt in 1..=max_retries {
+            info!("尝试连接 MySQL 数据库 (第 {} 次)", attempt);
+
+            match sqlx::mysql::MySqlPoolOptions::new()
+                .max_connections(10) // 增加最大连接数
+                .min_connections(1) // 设置最小连接数
+                .acquire_timeout(std::time::Duration::from_secs(15)) // 获取连接超时
+                .idle_timeout(std::time::Duration::from_secs(600)) // 空闲连接超时
+                .max_lifetime(std::time::Duration::from_secs(3600)) // 连接最大生命周期
+                .test_before_acquire(true) // 获取前测试连接
+                .connect(&mysql_url)
+                .await
+            {
+                Ok(pool) => {
+                    info!("MySQL 连接成功");
+                    return Ok(pool);
+                }
+                Err(e) => {
+                    error!("MySQL 连接失败 (第 {} 次): {}", attempt, e);
+                    last_error = Some(e);
+
+                    if attempt < max_retries {
+                        let delay = std::time::Duration::from_secs(2_u64.pow(attempt - 1)); // 指数退避
+                        warn!("等待 {:?} 后重试...", delay);
+                        tokio::time::sleep(delay).await;
+                    }
+                }
+            }
+        }
+
+        Err(last_error.unwrap().into())
+    }
+
+    /// 获取允许连接的主机，默认为 localhost 以确保安全
+    fn allowed_host(&self) -> &str {
+        self.config.allowed_host.as_deref().unwrap_or("localhost")
+    }
+
+    /// 生成 CREATE USER / ALTER USER 的资源限制选项
+    fn resource_options(limits: &ResourceLimits) -> String {
+        format!(
+            "MAX_USER_CONNECTIONS {} MAX_QUERIES_PER_HOUR {} MAX_UPDATES_PER_HOUR {}",
+            limits.max_user_connections, limits.max_queries_per_hour, limits.max_updates_per_hour
+        )
+    }
+
+    /// 校验允许的主机，严禁在生产环境使用通配符 %
+    fn checked_allowed_host(&self) -> Result<&str> {
+        let allowed_host = self.allowed_host();
+
+        let is_dev_mode = std::env::var("DEV_MODE").unwrap_or_default() == "true";
+        if allowed_host == "%" && !is_dev_mode {
+            error!("安全违规: 生产环境禁止使用通配符主机 '%'");
+            return Err(anyhow::anyhow!("安全错误: 不允许使用通配符主机 '%'"));
+        }
+
+        if !DatabaseManager::is_valid_host(allowed_host) {
+            error!("无效的主机格式: {}", allowed_host);
+            return Err(anyhow::anyhow!("Invalid host format"));
+        }
+
+        Ok(allowed_host)
+    }
+}
+
+/// 导出时字段值的读取方式
+enum ColumnKind {
+    Number,
+    Text,
+    Binary,
+    Bit,
+}
+
+impl ColumnKind {
+    fn from_data_type(data_type: &str) -> Self {
+        match data_type.to_ascii_lowercase().as_str() {
+            "tinyint" | "smallint" | "mediumint" | "int" | "integer" | "bigint" | "decimal"
+            | "numeric" | "float" | "double" | "real" | "year" => ColumnKind::Number,
+            "binary" | "varbinary" | "tinyblob" | "blob" | "mediumblob" | "longblob"
+            | "geometry" | "point" | "linestring" | "polygon" | "multipoint"
+            | "multilinestring" | "multipolygon" | "geometrycollection" | "geomcollection" => {
+                ColumnKind::Binary
+            }
+            "bit" => ColumnKind::Bit,
+            _ => ColumnKind::Text,
+        }
+    }
+
+    /// 查询表达式，所有类型都转换为字符串读取，避免逐类型解码
+    fn select_expr(&self, column: &str) -> String {
+        let column = dump::quote_identifier(column);
+        match self {
+            ColumnKind::Number | ColumnKind::Text => format!("CAST({} AS CHAR)", column),
+            ColumnKind::Binary => format!("HEX({})", column),
+            ColumnKind::Bit => format!("BIN({})", column),
+        }
+    }
+
+    fn value(&self, value: Option<String>) -> DumpValue {
+        match (self, value) {
+            (_, None) => DumpValue::Null,
+            (ColumnKind::Number, Some(v)) => DumpValue::Number(v),
+            (ColumnKind::Text, Some(v)) => DumpValue::Text(v),
+            (ColumnKind::Binary, Some(v)) => DumpValue::Hex(v),
+            (ColumnKind::Bit, Some(v)) => DumpValue::Bits(v),
+        }
+    }
+}
+
+impl MySqlProvisioner {
+    /// 以管理员身份连接到指定数据库的连接参数，用于需要默认数据库的会话
+    fn admin_options(&self, db_name: &str) -> MySqlConnectOptions {
+        MySqlConnectOptions::new()
+            .host(&self.config.host)
+            .port(self.config.port)
+            .username(&self.config.username)
+            .password(&self.config.password)
+            .database(db_name)
+    }
+
+    /// 在只读事务中执行查询，值按文本返回
+    async fn read_only_rows(options: &MySqlConnectOptions, sql: &str) -> Result<QueryRows> {
+        let mut conn = MySqlConnection::connect_with(options).await?;
+
+        // MAX_EXECUTION_TIME 只限制 SELECT，MariaDB 等不支持时忽略
+        let timeout_sql = format!(
+            "SET SESSION MAX_EXECUTION_TIME = {}",
+            GRADER_QUERY_TIMEOUT_MS
+        );
+        if let Err(e) = conn.execute(timeout_sql.as_str()).await {
+            warn!("设置评分查询超时失败: {}", e);
+        }
+        // 只读事务防止查询调用的函数或视图修改数据
+        conn.execute("START TRANSACTION READ ONLY").await?;
+
+        let result = async {
+            let mut rows = Vec::new();
+            // 不带参数的查询使用文本协议，所有值都以文本返回
+            let mut stream = conn.fetch(sql);
+            while let Some(row) = stream.try_next().await? {
+                if rows.len() >= GRADER_MAX_ROWS {
+                    return Err(anyhow::anyhow!("查询结果超过 {} 行", GRADER_MAX_ROWS));
+                }
+                let values = (0..row.len())
+                    .map(|i| {
+                        row.try_get_unchecked::<Option<Vec<u8>>, _>(i)
+                            .map(|value| value.map(|v| String::from_utf8_lossy(&v).into_owned()))
+                    })
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                rows.push(values);
+            }
+            Ok(rows)
+        }
+        .await;
+
+        let _ = conn.execute("ROLLBACK").await;
+        let _ = conn.close().await;
+        result
+    }
+
+    /// 导出一张表的数据，按批次发送 INSERT 语句
+    async fn dump_table_rows(
+        conn: &mut MySqlConnection,
+        db_name: &str,
+        table: &str,
+        sender: &DumpSender,
+    ) -> Result<u64> {
+        let columns = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT COLUMN_NAME, DATA_TYPE, EXTRA FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? ORDER BY ORDINAL_POSITION",
+        )
+        .bind(db_name)
+        .bind(table)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        // 生成列的值由表达式计算，不能写入
+        let columns: Vec<(String, ColumnKind)> = columns
+            .into_iter()
+            .filter(|(_, _, extra)| {
+                !extra.contains("VIRTUAL GENERATED") && !extra.contains("STORED GENERATED")
+            })
+            .map(|(name, data_type, _)| (name, ColumnKind::from_data_type(&data_type)))
+            .collect();
+        if columns.is_empty() {
+            return Ok(0);
+        }
+
+        let names: Vec<String> = columns.iter().map(|(name, _)| name.clone()).collect();
+        let select_sql = format!(
+            "SELECT {} FROM {}.{}",
+            columns
+                .iter()
+                .map(|(name, kind)| kind.select_expr(name))
+                .collect::<Vec<_>>()
+                .join(", "),
+            dump::quote_identifier(db_name),
+            dump::quote_identifier(table)
+        );
+
+        let mut batcher = InsertBatcher::default();
+        let mut row_count = 0u64;
+        let mut rows = sqlx::query(&select_sql).fetch(&mut *conn);
+        while let Some(row) = rows.try_next().await? {
+            let mut values = Vec::with_capacity(columns.len());
+            for (index, (_, kind)) in columns.iter().enumerate() {
+                values.push(kind.value(row.try_get::<Option<String>, _>(index)?));
+            }
+            row_count += 1;
+            if let Some(statement) = batcher.push(table, &names, values) {
+                dump::send_chunk(sender, statement).await?;
+            }
+        }
+        if let Some(statement) = batcher.flush(table, &names) {
+            dump::send_chunk(sender, statement).await?;
+        }
+
+        Ok(row_count)
+    }
+
+    /// 在给定连接上导出数据库，按顺序发送表结构、数据和视图
+    async fn dump_to(conn: &mut MySqlConnection, db_name: &str, sender: &DumpSender) -> Result<()> {
+        // 在一致性快照中读取，导出期间的写入不会让各表数据互相矛盾
+        sqlx::query("START TRANSACTION WITH CONSISTENT SNAPSHOT, READ ONLY")
+            .execute(&mut *conn)
+            .await?;
+
+        let generated_at = chrono::Utc::now()
+            .format("%Y-%m-%d %H:%M:%S UTC")
+            .to_string();
+        dump::send_chunk(sender, dump::dump_header(db_name, &generated_at)).await?;
+
+        let objects = sqlx::query_as::<_, (String, String)>(
+            "SELECT TABLE_NAME, TABLE_TYPE FROM information_schema.TABLES WHERE TABLE_SCHEMA = ? ORDER BY TABLE_TYPE = 'VIEW', TABLE_NAME",
+        )
+        .bind(db_name)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut table_count = 0;
+        let mut row_count = 0;
+        for (name, table_type) in &objects {
+            let qualified = format!(
+                "{}.{}",
+                dump::quote_identifier(db_name),
+                dump::quote_identifier(name)
+            );
+            if table_type == "VIEW" {
+                let row = sqlx::query(&format!("SHOW CREATE VIEW {}", qualified))
+                    .fetch_one(&mut *conn)
+                    .await?;
+                let create_view: String = row.try_get(1)?;
+                dump::send_chunk(sender, dump::view_definition(db_name, name, &create_view))
+                    .await?;
+                continue;
+            }
+
+            let row = sqlx::query(&format!("SHOW CREATE TABLE {}", qualified))
+                .fetch_one(&mut *conn)
+                .await?;
+            let create_table: String = row.try_get(1)?;
+            dump::send_chunk(sender, dump::table_definition(name, &create_table)).await?;
+
+            row_count += Self::dump_table_rows(conn, db_name, name, sender).await?;
+            dump::send_chunk(sender, "\n".to_string()).await?;
+            table_count += 1;
+        }
+
+        sqlx::query("COMMIT").execute(&mut *conn).await?;
+        dump::send_chunk(sender, dump::dump_footer()).await?;
+        info!(
+            "数据库 {} 导出完成: {} 张表, {} 行数据",
+            db_name, table_count, row_count
+        );
+        Ok(())
+    }
+
+    /// 读取库中的触发器、视图、存储过程、函数和事件的定义
+    async fn schema_objects(
+        conn: &mut MySqlConnection,
+        db_name: &str,
+    ) -> Result<Vec<SchemaObject>> {
+        let names = sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT 'TRIGGER', TRIGGER_NAME FROM information_schema.TRIGGERS WHERE TRIGGER_SCHEMA = ?
+            UNION ALL
+            SELECT 'FUNCTION', ROUTINE_NAME FROM information_schema.ROUTINES WHERE ROUTINE_SCHEMA = ? AND ROUTINE_TYPE = 'FUNCTION'
+            UNION ALL
+            SELECT 'PROCEDURE', ROUTINE_NAME FROM information_schema.ROUTINES WHERE ROUTINE_SCHEMA = ? AND ROUTINE_TYPE = 'PROCEDURE'
+            UNION ALL
+            SELECT 'VIEW', TABLE_NAME FROM information_schema.TABLES WHERE TABLE_SCHEMA = ? AND TABLE_TYPE = 'VIEW'
+            UNION ALL
+            SELECT 'EVENT', EVENT_NAME FROM information_schema.EVENTS WHERE EVENT_SCHEMA = ?
+            "#,
+        )
+        .bind(db_name)
+        .bind(db_name)
+        .bind(db_name)
+        .bind(db_name)
+        .bind(db_name)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut objects = Vec::with_capacity(names.len());
+        for (kind, name) in names {
+            let (kind, definition_column) = match kind.as_str() {
+                "TRIGGER" => ("TRIGGER", "SQL Original Statement"),
+                "FUNCTION" => ("FUNCTION", "Create Function"),
+                "PROCEDURE" => ("PROCEDURE", "Create Procedure"),
+                "VIEW" => ("VIEW", "Create View"),
+                _ => ("EVENT", "Create Event"),
+            };
+            let show_sql = format!(
+                "SHOW CREATE {} {}.{}",
+                kind,
+                dump::quote_identifier(db_name),
+                dump::quote_identifier(&name)
+            );
+            let row = sqlx::query(&show_sql).fetch_one(&mut *conn).await?;
+            let definition: Option<String> = row.try_get(definition_column)?;
+            let definition =
+                definition.ok_or_else(|| anyhow::anyhow!("无法读取 {} {} 的定义", kind, name))?;
+            let sql_mode = if kind == "VIEW" {
+                None
+            } else {
+                Some(row.try_get::<String, _>("sql_mode")?)
+            };
+            objects.push(SchemaObject {
+                kind,
+                name,
+                sql_mode,
+                definition,
+            });
+        }
+        Ok(objects)
+    }
+
+    /// 在连接的当前库中重建对象
+    ///
+    /// 视图可能依赖其他视图或函数，失败的对象在下一轮重试，直到全部成功或不再有进展。
+    async fn recreate_objects(
+        conn: &mut MySqlConnection,
+        objects: &[&SchemaObject],
+        from: &str,
+        to: &str,
+    ) -> Result<()> {
+        let mut pending: Vec<&SchemaObject> = objects.to_vec();
+        while !pending.is_empty() {
+            let mut failed = Vec::new();
+            let mut last_error = None;
+            for object in &pending {
+                let mut result = Ok(());
+                for statement in object.create_statements(from, to) {
+                    // CREATE TRIGGER 等语句不能预处理，使用文本协议执行
+                    if let Err(e) = conn.execute(statement.as_str()).await {
+                        result = Err(e);
+                        break;
+                    }
+                }
+                if let Err(e) = result {
+                    last_error = Some(format!("{} {}: {}", object.kind, object.name, e));
+                    failed.push(*object);
+                }
+            }
+            if failed.len() == pending.len() {
+                return Err(anyhow::anyhow!(
+                    "无法在 {} 中重建 {}",
+                    to,
+                    last_error.unwrap_or_default()
+                ));
+            }
+            pending = failed;
+        }
+        Ok(())
+    }
+
+    /// 将一个库的全部基础表移动到另一个库
+    async fn rename_tables(conn: &mut MySqlConnection, from: &str, to: &str) -> Result<usize> {
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT TABLE_NAME FROM information_schema.TABLES WHERE TABLE_SCHEMA = ? AND TABLE_TYPE = 'BASE TABLE'",
+        )
+        .bind(from)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for table in &tables {
+            let rename_sql = format!(
+                "RENAME TABLE {}.{} TO {}.{}",
+                dump::quote_identifier(from),
+                dump::quote_identifier(table),
+                dump::quote_identifier(to),
+                dump::quote_identifier(table)
+            );
+            conn.execute(rename_sql.as_str()).await?;
+        }
+        Ok(tables.len())
+    }
+
+    /// 删除库中的全部触发器，带触发器的表不能跨库 RENAME
+    async fn drop_triggers(conn: &mut MySqlConnection, db_name: &str) -> Result<()> {
+        let triggers: Vec<String> = sqlx::query_scalar(
+            "SELECT TRIGGER_NAME FROM information_schema.TRIGGERS WHERE TRIGGER_SCHEMA = ?",
+        )
+        .bind(db_name)
+        .fetch_all(&mut *conn)
+        .await?;
+        for trigger in triggers {
+            let drop_sql = format!(
+                "DROP TRIGGER {}.{}",
+                dump::quote_identifier(db_name),
+                dump::quote_identifier(&trigger)
+            );
+            conn.execute(drop_sql.as_str()).await?;
+        }
+        Ok(())
+    }
+
+    /// 移动失败时把表移回原库并重建触发器，视图等其他对象一直留在原库中
+    async fn undo_move(
+        conn: &mut MySqlConnection,
+        from: &str,
+        to: &str,
+        triggers: &[&SchemaObject],
+    ) -> Result<()> {
+        Self::drop_triggers(conn, to).await?;
+        Self::rename_tables(conn, to, from).await?;
+        conn.execute(format!("USE {}", dump::quote_identifier(from)).as_str())
+            .await?;
+        Self::recreate_objects(conn, triggers, from, from).await?;
+        conn.execute(format!("DROP DATABASE IF EXISTS {}", dump::quote_identifier(to)).as_str())
+            .await?;
+        Ok(())
+    }
+}
+
+/// 依附于数据库的对象，RENAME TABLE 不能把它们带到其他库，移动数据库时需要在目标库中重建
+#[derive(Debug, Clone)]
+struct SchemaObject {
+    /// TRIGGER、VIEW、FUNCTION、PROCEDURE 或 EVENT
+    kind: &'static str,
+    name: String,
+    /// 创建对象时的 sql_mode，视图没有
+    sql_mode: Option<String>,
+    definition: String,
+}
+
+impl SchemaObject {
+    /// 在目标库中重建对象的语句，定义中对原库名的限定改为目标库
+    fn create_statements(&self, from: &str, to: &str) -> Vec<String> {
+        let mut statements = Vec::new();
+        if let Some(sql_mode) = &self.sql_mode {
+            statements.push(format!(
+                "SET SESSION sql_mode = {}",
+                dump::quote_string(sql_mode)
+            ));
+        }
+        statements.push(retarget_definition(&self.definition, from, to));
+        statements
+    }
+}
+
+/// 将定义中 `from`. 和 from. 形式的库名限定替换为目标库
+fn retarget_definition(definition: &str, from: &str, to: &str) -> String {
+    let qualified = regex::Regex::new(&format!(
+        r"(`{}`|\b{}\b)\.",
+        regex::escape(&from.replace('`', "``")),
+        regex::escape(from)
+    ))
+    .unwrap();
+    qualified
+        .replace_all(definition, format!("{}.", dump::quote_identifier(to)))
+        .into_owned()
+}
+
+#[async_trait]
+impl Provisioner for MySqlProvisioner {
+    fn engine(&self) -> DatabaseEngine {
+        DatabaseEngine::MySql
+    }
+
+    async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn create_database(&self, db_name: &str) -> Result<()> {
+        // MySQL 不支持对数据库名进行参数化，调用方已做白名单验证
+        let create_db_sql = format!("CREATE DATABASE IF NOT EXISTS `{}`", db_name);
+        if let Err(e) = sqlx::query(&create_db_sql).execute(&self.pool).await {
+            error!("创建数据库失败: {}, SQL: {}", e, create_db_sql);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn create_user(
+        &self,
+        username: &str,
+        password: &str,
+        limits: &ResourceLimits,
+    ) -> Result<()> {
+        let allowed_host = self.checked_allowed_host()?;
+
+        let create_user_sql = format!(
+            "CREATE USER IF NOT EXISTS '{}'@'{}' IDENTIFIED BY '{}' WITH {}",
+            username,
+            allowed_host,
+            password.replace("'", "''"), // 转义单引号
+            Self::resource_options(limits)
+        );
+
+        if let Err(e) = sqlx::query(&create_user_sql).execute(&self.pool).await {
+            error!(
+                "创建用户失败: {}, SQL: CREATE USER IF NOT EXISTS '{}'@'{}' IDENTIFIED BY '[REDACTED]' WITH {}",
+                e,
+                username,
+                allowed_host,
+                Self::resource_options(limits)
+            );
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn set_password(&self, username: &str, password: &str) -> Result<()> {
+        let alter_user_sql = format!(
+            "ALTER USER '{}'@'{}' IDENTIFIED BY '{}'",
+            username,
+            self.allowed_host(),
+            password.replace("'", "''") // 转义单引号
+        );
+        if let Err(e) = sqlx::query(&alter_user_sql).execute(&self.pool).await {
+            error!(
+                "修改密码失败: {}, SQL: ALTER USER '{}'@'{}' IDENTIFIED BY '[REDACTED]'",
+                e,
+                username,
+                self.allowed_host()
+            );
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn lock_user(&self, username: &str) -> Result<()> {
+        let lock_sql = format!(
+            "ALTER USER '{}'@'{}' ACCOUNT LOCK",
+            username,
+            self.allowed_host()
+        );
+        sqlx::query(&lock_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn unlock_user(&self, username: &str) -> Result<()> {
+        let unlock_sql = format!(
+            "ALTER USER '{}'@'{}' ACCOUNT UNLOCK",
+            username,
+            self.allowed_host()
+        );
+        sqlx::query(&unlock_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn kill_sessions(&self, username: &str) -> Result<u32> {
+        let ids: Vec<u64> = sqlx::query_scalar(
+            "SELECT ID FROM information_schema.PROCESSLIST WHERE USER = ? AND ID <> CONNECTION_ID()",
+        )
+        .bind(username)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut killed = 0;
+        for id in ids {
+            // 会话可能在查询后自行断开，忽略单个 KILL 的失败
+            match sqlx::query(&format!("KILL {}", id))
+                .execute(&self.pool)
+                .await
+            {
+                Ok(_) => killed += 1,
+                Err(e) => warn!("断开会话 {} 失败: {}", id, e),
+            }
+        }
+        Ok(killed)
+    }
+
+    async fn alter_resource_limits(&self, username: &str, limits: &ResourceLimits) -> Result<()> {
+        let alter_user_sql = format!(
+            "ALTER USER '{}'@'{}' WITH {}",
+            username,
+            self.allowed_host(),
+            Self::resource_options(limits)
+        );
+        if let Err(e) = sqlx::query(&alter_user_sql).execute(&self.pool).await {
+            error!("修改资源限制失败: {}, SQL: {}", e, alter_user_sql);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn grant_privileges(
+        &self,
+        db_name: &str,
+        username: &str,
+        privileges: &[&str],
+    ) -> Result<()> {
+        let grant_sql = format!(
+            "GRANT {} ON `{}`.* TO '{}'@'{}'",
+            privileges.join(", "),
+            db_name,
+            username,
+            self.allowed_host()
+        );
+        if let Err(e) = sqlx::query(&grant_sql).execute(&self.pool).await {
+            error!("授权失败: {}, SQL: {}", e, grant_sql);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn revoke_privileges(
+        &self,
+        db_name: Option<&str>,
+        username: &str,
+        privileges: &[&str],
+    ) -> Result<()> {
+        let scope = match db_name {
+            Some(db_name) => format!("`{}`.*", db_name),
+            None => "*.*".to_string(),
+        };
+        let revoke_sql = format!(
+            "REVOKE {} ON {} FROM '{}'@'{}'",
+            privileges.join(", "),
+            scope,
+            username,
+            self.allowed_host()
+        );
+        sqlx::query(&revoke_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn flush_privileges(&self) -> Result<()> {
+        sqlx::query("FLUSH PRIVILEGES").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn drop_user(&self, username: &str) -> Result<()> {
+        let drop_user_sql = format!(
+            "DROP USER IF EXISTS '{}'@'{}'",
+            username,
+            self.allowed_host()
+        );
+        sqlx::query(&drop_user_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn drop_database(&self, db_name: &str) -> Result<()> {
+        let drop_db_sql = format!("DROP DATABASE IF EXISTS `{}`", db_name);
+        sqlx::query(&drop_db_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn move_database(&self, from: &str, to: &str) -> Result<()> {
+        // MySQL 不支持重命名数据库：逐个将表移动到目标库，再在目标库中重建触发器、视图、
+        // 存储过程、函数和事件。使用单独的连接，修改的 sql_mode 和当前库不会带回连接池
+        let mut conn = MySqlConnection::connect_with(&self.admin_options(from)).await?;
+
+        let result = async {
+            // 目标库已存在时拒绝移动，避免和其他库的表混在一起
+            let create_sql = format!("CREATE DATABASE {}", dump::quote_identifier(to));
+            conn.execute(create_sql.as_str())
+                .await
+                .map_err(|e| anyhow::anyhow!("无法创建目标数据库 {}: {}", to, e))?;
+
+            let objects = Self::schema_objects(&mut conn, from).await?;
+            let triggers: Vec<&SchemaObject> =
+                objects.iter().filter(|o| o.kind == "TRIGGER").collect();
+
+            // 带触发器的表不能跨库 RENAME，先删除触发器，表移动后在目标库中重建
+            Self::drop_triggers(&mut conn, from).await?;
+            let moved = async {
+                let tables = Self::rename_tables(&mut conn, from, to).await?;
+                conn.execute(format!("USE {}", dump::quote_identifier(to)).as_str())
+                    .await?;
+                let objects: Vec<&SchemaObject> = objects.iter().collect();
+                Self::recreate_objects(&mut conn, &objects, from, to).await?;
+                Ok::<usize, anyhow::Error>(tables)
+            }
+            .await;
+
+            match moved {
+                Ok(tables) => {
+                    info!(
+                        "已将数据库 {} 的 {} 张表和 {} 个其他对象移动到 {}",
+                        from,
+                        tables,
+                        objects.len(),
+                        to
+                    );
+                    Ok(())
+                }
+                Err(e) => {
+                    if let Err(undo_error) = Self::undo_move(&mut conn, from, to, &triggers).await {
+                        error!(
+                            "恢复数据库 {} 失败，部分表可能仍在 {} 中: {}",
+                            from, to, undo_error
+                        );
+                    }
+                    Err(anyhow::anyhow!(
+                        "移动数据库 {} 到 {} 失败，原数据库保持不变: {}",
+                        from,
+                        to,
+                        e
+                    ))
+                }
+            }
+        }
+        .await;
+
+        let _ = conn.close().await;
+        result?;
+        self.drop_database(from).await
+    }
+
+    async fn clear_database(&self, db_name: &str) -> Result<u32> {
+        let mut conn = self.pool.acquire().await?;
+
+        // 外键检查只对当前会话生效，关闭后可以按任意顺序删除表
+        sqlx::query("SET FOREIGN_KEY_CHECKS = 0")
+            .execute(&mut *conn)
+            .await?;
+
+        let mut statements: Vec<String> = sqlx::query_as::<_, (String, String)>(
+            "SELECT TABLE_NAME, TABLE_TYPE FROM information_schema.TABLES WHERE TABLE_SCHEMA = ? ORDER BY TABLE_TYPE = 'VIEW' DESC, TABLE_NAME",
+        )
+        .bind(db_name)
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(|(name, table_type)| {
+            format!(
+                "DROP {} IF EXISTS {}.{}",
+                if table_type == "VIEW" { "VIEW" } else { "TABLE" },
+                dump::quote_identifier(db_name),
+                dump::quote_identifier(&name)
+            )
+        })
+        .collect();
+
+        let routines = sqlx::query_as::<_, (String, String)>(
+            "SELECT ROUTINE_NAME, ROUTINE_TYPE FROM information_schema.ROUTINES WHERE ROUTINE_SCHEMA = ?",
+        )
+        .bind(db_name)
+        .fetch_all(&mut *conn)
+        .await?;
+        statements.extend(routines.into_iter().map(|(name, routine_type)| {
+            format!(
+                "DROP {} IF EXISTS {}.{}",
+                routine_type,
+                dump::quote_identifier(db_name),
+                dump::quote_identifier(&name)
+            )
+        }));
+
+        let events: Vec<String> = sqlx::query_scalar(
+            "SELECT EVENT_NAME FROM information_schema.EVENTS WHERE EVENT_SCHEMA = ?",
+        )
+        .bind(db_name)
+        .fetch_all(&mut *conn)
+        .await?;
+        statements.extend(events.into_iter().map(|name| {
+            format!(
+                "DROP EVENT IF EXISTS {}.{}",
+                dump::quote_identifier(db_name),
+                dump::quote_identifier(&name)
+            )
+        }));
+
+        let mut result = Ok(());
+        for sql in &statements {
+            if let Err(e) = sqlx::query(sql).execute(&mut *conn).await {
+                error!("清空数据库失败: {}, SQL: {}", e, sql);
+                result = Err(e);
+                break;
+            }
+        }
+
+        // 连接会回到连接池，恢复外键检查
+        let _ = sqlx::query("SET FOREIGN_KEY_CHECKS = 1")
+            .execute(&mut *conn)
+            .await;
+        result?;
+
+        info!("已清空数据库 {}: 删除 {} 个对象", db_name, statements.len());
+        Ok(statements.len() as u32)
+    }
+
+    async fn verify_password(&self, db_name: &str, username: &str, password: &str) -> Result<bool> {
+        let options = MySqlConnectOptions::new()
+            .host(&self.config.host)
+            .port(self.config.port)
+            .username(username)
+            .password(password)
+            .database(db_name);
+
+        match MySqlConnection::connect_with(&options).await {
+            Ok(conn) => {
+                let _ = conn.close().await;
+                Ok(true)
+            }
+            // SQLSTATE 28000: Access denied
+            Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("28000") => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn dump_database(&self, db_name: &str, sender: &DumpSender) -> Result<()> {
+        // 导出可能持续较长时间，使用单独的连接，不占用管理连接池
+        let mut conn = MySqlConnection::connect_with(&self.admin_options(db_name)).await?;
+        let result = Self::dump_to(&mut conn, db_name, sender).await;
+        let _ = conn.close().await;
+        result
+    }
+
+    async fn execute_script(&self, db_name: &str, statements: &[SqlStatement]) -> Result<()> {
+        let mut conn = MySqlConnection::connect_with(&self.admin_options(db_name)).await?;
+
+        for statement in statements {
+            if let Err(e) = conn.execute(statement.sql.as_str()).await {
+                let _ = conn.close().await;
+                return Err(anyhow::anyhow!(
+                    "第 {} 行的语句执行失败: {}",
+                    statement.line,
+                    e
+                ));
+            }
+        }
+
+        let _ = conn.close().await;
+        Ok(())
+    }
+
+    async fn query_rows(
+        &self,
+        db_name: &str,
+        username: &str,
+        password: &str,
+        sql: &str,
+    ) -> Result<QueryRows> {
+        let options = MySqlConnectOptions::new()
+            .host(&self.config.host)
+            .port(self.config.port)
+            .username(username)
+            .password(password)
+            .database(db_name);
+        Self::read_only_rows(&options, sql).await
+    }
+
+    async fn table_columns(&self, db_name: &str, table: &str) -> Result<Option<Vec<String>>> {
+        let columns: Vec<String> = sqlx::query_scalar(
+            "SELECT COLUMN_NAME FROM information_schema.COLUMNS WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? ORDER BY ORDINAL_POSITION",
+        )
+        .bind(db_name)
+        .bind(table)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok((!columns.is_empty()).then_some(columns))
+    }
+
+    async fn schema_snapshot(&self, db_name: &str, include_rows: bool) -> Result<SchemaSnapshot> {
+        let columns = sqlx::query_as::<_, (String, String, String, String)>(
+            r#"
+            SELECT c.TABLE_NAME, c.COLUMN_NAME, c.COLUMN_TYPE, c.IS_NULLABLE
+            FROM information_schema.COLUMNS c
+            JOIN information_schema.TABLES t
+              ON t.TABLE_SCHEMA = c.TABLE_SCHEMA AND t.TABLE_NAME = c.TABLE_NAME
+            WHERE c.TABLE_SCHEMA = ? AND t.TABLE_TYPE = 'BASE TABLE'
+            ORDER BY c.TABLE_NAME, c.ORDINAL_POSITION
+            "#,
+        )
+        .bind(db_name)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|(table, name, data_type, nullable)| SchemaColumn {
+            table,
+            name,
+            data_type,
+            nullable: nullable == "YES",
+        })
+        .collect::<Vec<_>>();
+
+        // 函数索引的 COLUMN_NAME 为 NULL，以表达式占位
+        let statistics = sqlx::query_as::<_, (String, String, i64, Option<String>)>(
+            r#"
+            SELECT TABLE_NAME, INDEX_NAME, CAST(NON_UNIQUE AS SIGNED), COLUMN_NAME
+            FROM information_schema.STATISTICS
+            WHERE TABLE_SCHEMA = ?
+            ORDER BY TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX
+            "#,
+        )
+        .bind(db_name)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut indexes: Vec<SchemaIndex> = Vec::new();
+        for (table, name, non_unique, column) in statistics {
+            let column = column.unwrap_or_else(|| "(expression)".to_string());
+            match indexes.last_mut() {
+                Some(index) if index.table == table && index.name == name => {
+                    index.columns.push(column)
+                }
+                _ => indexes.push(SchemaIndex {
+                    primary: name == "PRIMARY",
+                    unique: non_unique == 0,
+                    table,
+                    name,
+                    columns: vec![column],
+                }),
+            }
+        }
+
+        let mut rows = Vec::new();
+        if include_rows {
+            let mut tables: Vec<&str> = columns.iter().map(|c| c.table.as_str()).collect();
+            tables.dedup();
+            for table in tables {
+                let sql = format!(
+                    "SELECT * FROM {} LIMIT {}",
+                    dump::quote_identifier(table),
+                    SIMILARITY_ROWS_PER_TABLE
+                );
+                rows.push((
+                    table.to_string(),
+                    Self::read_only_rows(&self.admin_options(db_name), &sql).await?,
+                ));
+            }
+        }
+
+        Ok(SchemaSnapshot {
+            columns,
+            indexes,
+            rows,
+        })
+    }
+
+    async fn execute_as_user(
+        &self,
+        db_name: &str,
+        username: &str,
+        password: &str,
+        statements: &[SqlStatement],
+        max_errors: usize,
+    ) -> Result<Vec<std::result::Result<(), String>>> {
+        let options = MySqlConnectOptions::new()
+            .host(&self.config.host)
+            .port(self.config.port)
+            .username(username)
+            .password(password)
+            .database(db_name);
+        let mut conn = MySqlConnection::connect_with(&options).await?;
+
+        let mut results = Vec::with_capacity(statements.len());
+        let mut failed = 0;
+        for statement in statements {
+            // 不带参数的查询走文本协议，支持 CREATE PROCEDURE 等不能预处理的语句
+            match conn.execute(statement.sql.as_str()).await {
+                Ok(_) => results.push(Ok(())),
+                Err(e) => {
+                    failed += 1;
+                    results.push(Err(match e {
+                        sqlx::Error::Database(db_err) => db_err.message().to_string(),
+                        other => other.to_string(),
+                    }));
+                    if failed >= max_errors {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let _ = conn.close().await;
+        Ok(results)
+    }
+
+    async fn database_exists(&self, db_name: &str) -> Result<bool> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM INFORMATION_SCHEMA.SCHEMATA WHERE SCHEMA_NAME = ?",
+        )
+        .bind(db_name)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count > 0)
+    }
+
+    async fn user_exists(&self, username: &str) -> Result<bool> {
+        let count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM mysql.user WHERE User = ?")
+            .bind(username)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count > 0)
+    }
+
+    async fn database_size(&self, db_name: &str) -> Result<u64> {
+        let size = sqlx::query_scalar::<_, u64>(
+            "SELECT CAST(COALESCE(SUM(DATA_LENGTH + INDEX_LENGTH), 0) AS UNSIGNED) FROM information_schema.TABLES WHERE TABLE_SCHEMA = ?",
+        )
+        .bind(db_name)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(size)
+    }
+
+    async fn supports_roles(&self) -> Result<bool> {
+        // MySQL 8.0 起支持角色；MariaDB 的角色语法不同（不支持 SET DEFAULT ROLE ALL），按不支持处理
+        let version: String = sqlx::query_scalar("SELECT VERSION()")
+            .fetch_one(&self.pool)
+            .await?;
+
+        if version.to_ascii_lowercase().contains("mariadb") {
+            return Ok(false);
+        }
+
+        let major = version
+            .split('.')
+            .next()
+            .and_then(|major| major.parse::<u32>().ok())
+            .unwrap_or(0);
+        Ok(major >= 8)
+    }
+
+    async fn create_role(&self, role: &str) -> Result<()> {
+        // 角色与用户使用相同的主机部分，便于复用授权和撤权语句
+        let create_role_sql = format!(
+            "CREATE ROLE IF NOT EXISTS '{}'@'{}'",
+            role,
+            self.checked_allowed_host()?
+        );
+        if let Err(e) = sqlx::query(&create_role_sql).execute(&self.pool).await {
+            error!("创建角色失败: {}, SQL: {}", e, create_role_sql);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn grant_role(&self, role: &str, username: &str) -> Result<()> {
+        let host = self.allowed_host();
+        let grant_role_sql = format!("GRANT '{}'@'{}' TO '{}'@'{}'", role, host, username, host);
+        if let Err(e) = sqlx::query(&grant_role_sql).execute(&self.pool).await {
+            error!("授予角色失败: {}, SQL: {}", e, grant_role_sql);
+            return Err(e.into());
+        }
+
+        // 角色默认不激活，需设为默认角色，用户登录后才会拥有角色的权限
+        let default_role_sql = format!("SET DEFAULT ROLE ALL TO '{}'@'{}'", username, host);
+        sqlx::query(&default_role_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn drop_role(&self, role: &str) -> Result<()> {
+        let drop_role_sql = format!("DROP ROLE IF EXISTS '{}'@'{}'", role, self.allowed_host());
+        sqlx::query(&drop_role_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    fn credentials(&self, db_name: &str, username: &str, password: &str) -> DatabaseCredentials {
+        // 生成完整的连接字符串，用户名和密码经过百分号编码
+        let connection_string = format!(
+            "mysql://{}:{}@{}:{}/{}?allowPublicKeyRetrieval=true&useSSL=false",
+            encode_uri_component(username),
+            encode_uri_component(password),
+            self.config.host,
+            self.config.port,
+            db_name
+        );
+
+        let jdbc_url = format!(
+            "jdbc:mysql://{}:{}/{}?allowPublicKeyRetrieval=true&useSSL=false&user={}&password={}",
+            self.config.host,
+            self.config.port,
+            db_name,
+            encode_uri_component(username),
+            encode_uri_component(password)
+        );
+
+        DatabaseCredentials {
+            db_engine: DatabaseEngine::MySql,
+            db_host: self.config.host.clone(),
+            db_port: self.config.port,
+            db_name: db_name.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            connection_string,
+            jdbc_url,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retarget_definition_rewrites_schema_qualifiers() {
+        let view = "CREATE ALGORITHM=UNDEFINED DEFINER=`user_2023010101`@`localhost` SQL SECURITY DEFINER VIEW `v` AS select `db_2023010101`.`t`.`id` AS `id` from `db_2023010101`.`t`";
+        assert_eq!(
+            retarget_definition(view, "db_2023010101", "del_db_2023010101"),
+            "CREATE ALGORITHM=UNDEFINED DEFINER=`user_2023010101`@`localhost` SQL SECURITY DEFINER VIEW `v` AS select `del_db_2023010101`.`t`.`id` AS `id` from `del_db_2023010101`.`t`"
+        );
+
+        // 未加引号的限定也会替换，名称只是前缀相同的库保持不变
+        let procedure =
+            "CREATE PROCEDURE `p`() BEGIN SELECT * FROM db_2023010101.t JOIN db_20230101011.u; END";
+        assert_eq!(
+            retarget_definition(procedure, "db_2023010101", "arc_db_2023010101"),
+            "CREATE PROCEDURE `p`() BEGIN SELECT * FROM `arc_db_2023010101`.t JOIN db_20230101011.u; END"
+        );
+    }
+
+    #[test]
+    fn test_create_statements_restore_sql_mode() {
+        let trigger = SchemaObject {
+            kind: "TRIGGER",
+            name: "trg".to_string(),
+            sql_mode: Some("STRICT_TRANS_TABLES".to_string()),
+            definition: "CREATE TRIGGER `trg` BEFORE INSERT ON `t` FOR EACH ROW SET NEW.a = 1"
+                .to_string(),
+        };
+        assert_eq!(
+            trigger.create_statements("db_a", "del_db_a"),
+            vec![
+                "SET SESSION sql_mode = 'STRICT_TRANS_TABLES'".to_string(),
+                "CREATE TRIGGER `trg` BEFORE INSERT ON `t` FOR EACH ROW SET NEW.a = 1".to_string(),
+            ]
+        );
+
+        let view = SchemaObject {
+            kind: "VIEW",
+            name: "v".to_string(),
+            sql_mode: None,
+            definition: "CREATE VIEW `v` AS select 1".to_string(),
+        };
+        assert_eq!(view.create_statements("db_a", "del_db_a").len(), 1);
+    }
+}
@@ -0,0 +1,734 @@
+use super::grader::{GRADER_MAX_ROWS, GRADER_QUERY_TIMEOUT_MS, QueryRows};
+use super::import::SqlStatement;
+use super::provisioner::Provisioner;
+use super::similarity::{SIMILARITY_ROWS_PER_TABLE, SchemaColumn, SchemaIndex, SchemaSnapshot};
+use crate::config::PostgreSQLConfig;
+use crate::models::{DatabaseCredentials, DatabaseEngine, ResourceLimits};
+use crate::utils::encode_uri_component;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::TryStreamExt;
+use log::{error, info, warn};
+use sqlx::postgres::{PgConnectOptions, PgConnection, PgPoolOptions};
+use sqlx::{Connection, Executor, Pool, Postgres, Row, ValueRef};
+
+/// PostgreSQL 中有对应含义的表级权限，其余 MySQL 权限名（如 INDEX、LOCK TABLES）会被忽略
+const TABLE_PRIVILEGES: &[&str] = &[
+    "SELECT",
+    "INSERT",
+    "UPDATE",
+    "DELETE",
+    "REFERENCES",
+    "TRIGGER",
+];
+
+/// 基于 PostgreSQL 的供应后端
+///
+/// 每个身份标识对应一个数据库和一个 LOGIN 角色：
+/// - 创建数据库后立即撤销 PUBLIC 在该库上的所有权限
+/// - 授予角色 CONNECT 权限和 public 模式的 USAGE 权限
+/// - 权限列表中的表级权限映射为 public 模式下所有表（含之后新建的表）的权限，
+///   包含 CREATE 时额外授予在 public 模式中建表的权限
+pub struct PostgresProvisioner {
+    pool: Pool<Postgres>,
+    config: PostgreSQLConfig,
+}
+
+impl PostgresProvisioner {
+    /// 连接 PostgreSQL 并创建供应后端
+    pub async fn connect(config: &PostgreSQLConfig, max_retries: u32) -> Result<Self> {
+        let mut last_error = None;
+
+        for attempt in 1..=max_retries {
+            info!("尝试连接 PostgreSQL 数据库 (第 {} 次)", attempt);
+
+            match PgPoolOptions::new()
+                .max_connections(10)
+                .min_connections(1)
+                .acquire_timeout(std::time::Duration::from_secs(15))
+                .idle_timeout(std::time::Duration::from_secs(600))
+                .max_lifetime(std::time::Duration::from_secs(3600))
+                .test_before_acquire(true)
+                .connect_with(Self::connect_options(config, &config.database))
+                .await
+            {
+                Ok(pool) => {
+                    info!("PostgreSQL 连接成功");
+                    return Ok(Self {
+                        pool,
+                        config: config.clone(),
+                    });
+                }
+                Err(e) => {
+                    error!("PostgreSQL 连接失败 (第 {} 次): {}", attempt, e);
+                    last_error = Some(e);
+
+                    if attempt < max_retries {
+                        let delay = std::time::Duration::from_secs(2_u64.pow(attempt - 1)); // 指数退避
+                        warn!("等待 {:?} 后重试...", delay);
+                        tokio::time::sleep(delay).await;
+                    }
+                }
+            }
+        }
+
+        Err(last_error.unwrap().into())
+    }
+
+    fn connect_options(config: &PostgreSQLConfig, database: &str) -> PgConnectOptions {
+        PgConnectOptions::new()
+            .host(&config.host)
+            .port(config.port)
+            .username(&config.username)
+            .password(&config.password)
+            .database(database)
+    }
+
+    /// 模式级权限必须在目标数据库内授予，因此需要单独连接到该数据库
+    async fn connect_to(&self, db_name: &str) -> Result<PgConnection> {
+        let conn =
+            PgConnection::connect_with(&Self::connect_options(&self.config, db_name)).await?;
+        Ok(conn)
+    }
+
+    /// 在只读事务中执行查询，值按文本返回
+    async fn read_only_rows(options: &PgConnectOptions, sql: &str) -> Result<QueryRows> {
+        let mut conn = PgConnection::connect_with(options).await?;
+
+        let timeout_sql = format!("SET statement_timeout = {}", GRADER_QUERY_TIMEOUT_MS);
+        conn.execute(timeout_sql.as_str()).await?;
+        // 只读事务防止查询调用的函数修改数据
+        conn.execute("BEGIN READ ONLY").await?;
+
+        let result = async {
+            let mut rows = Vec::new();
+            // 不带参数的查询使用简单查询协议，所有值都以文本返回
+            let mut stream = conn.fetch(sql);
+            while let Some(row) = stream.try_next().await? {
+                if rows.len() >= GRADER_MAX_ROWS {
+                    return Err(anyhow::anyhow!("查询结果超过 {} 行", GRADER_MAX_ROWS));
+                }
+                let mut values = Vec::with_capacity(row.len());
+                for i in 0..row.len() {
+                    let value = row.try_get_raw(i)?;
+                    if value.is_null() {
+                        values.push(None);
+                    } else {
+                        let text = value.as_str().map_err(|e| anyhow::anyhow!("{}", e))?;
+                        values.push(Some(text.to_string()));
+                    }
+                }
+                rows.push(values);
+            }
+            Ok(rows)
+        }
+        .await;
+
+        let _ = conn.execute("ROLLBACK").await;
+        let _ = conn.close().await;
+        result
+    }
+
+    /// 引用标识符（调用方已做白名单验证，这里仍然转义双引号）
+    fn quote_ident(identifier: &str) -> String {
+        format!("\"{}\"", identifier.replace('"', "\"\""))
+    }
+
+    fn table_privileges(privileges: &[&str]) -> Vec<&'static str> {
+        TABLE_PRIVILEGES
+            .iter()
+            .copied()
+            .filter(|p| privileges.iter().any(|q| q.eq_ignore_ascii_case(p)))
+            .collect()
+    }
+
+    /// PostgreSQL 用 -1 表示不限制连接数
+    fn connection_limit(limits: &ResourceLimits) -> i64 {
+        match limits.max_user_connections {
+            0 => -1,
+            n => n as i64,
+        }
+    }
+
+    fn includes_create(privileges: &[&str]) -> bool {
+        privileges.iter().any(|p| p.eq_ignore_ascii_case("CREATE"))
+    }
+}
+
+#[async_trait]
+impl Provisioner for PostgresProvisioner {
+    fn engine(&self) -> DatabaseEngine {
+        DatabaseEngine::Postgres
+    }
+
+    async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn create_database(&self, db_name: &str) -> Result<()> {
+        // PostgreSQL 不支持 CREATE DATABASE IF NOT EXISTS
+        if !self.database_exists(db_name).await? {
+            let create_db_sql = format!("CREATE DATABASE {}", Self::quote_ident(db_name));
+            if let Err(e) = sqlx::query(&create_db_sql).execute(&self.pool).await {
+                error!("创建数据库失败: {}, SQL: {}", e, create_db_sql);
+                return Err(e.into());
+            }
+        }
+
+        // 默认情况下 PUBLIC 拥有 CONNECT 和 TEMPORARY 权限，必须撤销
+        let revoke_public_sql = format!(
+            "REVOKE ALL ON DATABASE {} FROM PUBLIC",
+            Self::quote_ident(db_name)
+        );
+        sqlx::query(&revoke_public_sql).execute(&self.pool).await?;
+
+        // PostgreSQL 15 之前 PUBLIC 可以在 public 模式中建表
+        let mut conn = self.connect_to(db_name).await?;
+        sqlx::query("REVOKE CREATE ON SCHEMA public FROM PUBLIC")
+            .execute(&mut conn)
+            .await?;
+        conn.close().await?;
+
+        Ok(())
+    }
+
+    async fn create_user(
+        &self,
+        username: &str,
+        password: &str,
+        limits: &ResourceLimits,
+    ) -> Result<()> {
+        if self.user_exists(username).await? {
+            return Ok(());
+        }
+
+        let create_role_sql = format!(
+            "CREATE ROLE {} LOGIN PASSWORD '{}' CONNECTION LIMIT {}",
+            Self::quote_ident(username),
+            password.replace("'", "''"), // 转义单引号
+            Self::connection_limit(limits)
+        );
+
+        if let Err(e) = sqlx::query(&create_role_sql).execute(&self.pool).await {
+            error!(
+                "创建角色失败: {}, SQL: CREATE ROLE {} LOGIN PASSWORD '[REDACTED]' CONNECTION LIMIT {}",
+                e,
+                Self::quote_ident(username),
+                Self::connection_limit(limits)
+            );
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn set_password(&self, username: &str, password: &str) -> Result<()> {
+        let alter_role_sql = format!(
+            "ALTER ROLE {} PASSWORD '{}'",
+            Self::quote_ident(username),
+            password.replace("'", "''") // 转义单引号
+        );
+        if let Err(e) = sqlx::query(&alter_role_sql).execute(&self.pool).await {
+            error!(
+                "修改密码失败: {}, SQL: ALTER ROLE {} PASSWORD '[REDACTED]'",
+                e,
+                Self::quote_ident(username)
+            );
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn lock_user(&self, username: &str) -> Result<()> {
+        let lock_sql = format!("ALTER ROLE {} NOLOGIN", Self::quote_ident(username));
+        sqlx::query(&lock_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn unlock_user(&self, username: &str) -> Result<()> {
+        let unlock_sql = format!("ALTER ROLE {} LOGIN", Self::quote_ident(username));
+        sqlx::query(&unlock_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn kill_sessions(&self, username: &str) -> Result<u32> {
+        let terminated: Vec<bool> = sqlx::query_scalar(
+            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE usename = $1 AND pid <> pg_backend_pid()",
+        )
+        .bind(username)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(terminated.into_iter().filter(|t| *t).count() as u32)
+    }
+
+    async fn alter_resource_limits(&self, username: &str, limits: &ResourceLimits) -> Result<()> {
+        if limits.max_queries_per_hour > 0 || limits.max_updates_per_hour > 0 {
+            warn!("PostgreSQL 不支持按小时限制查询和更新次数，仅设置连接数限制");
+        }
+
+        let alter_role_sql = format!(
+            "ALTER ROLE {} CONNECTION LIMIT {}",
+            Self::quote_ident(username),
+            Self::connection_limit(limits)
+        );
+        sqlx::query(&alter_role_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn grant_privileges(
+        &self,
+        db_name: &str,
+        username: &str,
+        privileges: &[&str],
+    ) -> Result<()> {
+        let role = Self::quote_ident(username);
+
+        let grant_connect_sql = format!(
+            "GRANT CONNECT ON DATABASE {} TO {}",
+            Self::quote_ident(db_name),
+            role
+        );
+        if let Err(e) = sqlx::query(&grant_connect_sql).execute(&self.pool).await {
+            error!("授权失败: {}, SQL: {}", e, grant_connect_sql);
+            return Err(e.into());
+        }
+
+        let mut conn = self.connect_to(db_name).await?;
+        let mut statements = vec![format!("GRANT USAGE ON SCHEMA public TO {}", role)];
+
+        if Self::includes_create(privileges) {
+            statements.push(format!("GRANT CREATE ON SCHEMA public TO {}", role));
+        }
+
+        let table_privileges = Self::table_privileges(privileges);
+        if !table_privileges.is_empty() {
+            let list = table_privileges.join(", ");
+            statements.push(format!(
+                "GRANT {} ON ALL TABLES IN SCHEMA public TO {}",
+                list, role
+            ));
+            statements.push(format!(
+                "ALTER DEFAULT PRIVILEGES IN SCHEMA public GRANT {} ON TABLES TO {}",
+                list, role
+            ));
+            statements.push(format!(
+                "GRANT USAGE, SELECT ON ALL SEQUENCES IN SCHEMA public TO {}",
+                role
+            ));
+        }
+
+        for sql in &statements {
+            if let Err(e) = sqlx::query(sql).execute(&mut conn).await {
+                error!("授权失败: {}, SQL: {}", e, sql);
+                return Err(e.into());
+            }
+        }
+        conn.close().await?;
+
+        Ok(())
+    }
+
+    async fn revoke_privileges(
+        &self,
+        db_name: Option<&str>,
+        username: &str,
+        privileges: &[&str],
+    ) -> Result<()> {
+        let role = Self::quote_ident(username);
+
+        let Some(db_name) = db_name else {
+            // PostgreSQL 没有库级以外的数据权限，对应的是角色属性
+            let alter_role_sql = format!(
+                "ALTER ROLE {} NOSUPERUSER NOCREATEDB NOCREATEROLE NOREPLICATION",
+                role
+            );
+            sqlx::query(&alter_role_sql).execute(&self.pool).await?;
+            return Ok(());
+        };
+
+        let mut conn = self.connect_to(db_name).await?;
+        let mut statements = Vec::new();
+
+        if Self::includes_create(privileges) {
+            statements.push(format!("REVOKE CREATE ON SCHEMA public FROM {}", role));
+        }
+
+        let table_privileges = Self::table_privileges(privileges);
+        if !table_privileges.is_empty() {
+            let list = table_privileges.join(", ");
+            statements.push(format!(
+                "REVOKE {} ON ALL TABLES IN SCHEMA public FROM {}",
+                list, role
+            ));
+            statements.push(format!(
+                "ALTER DEFAULT PRIVILEGES IN SCHEMA public REVOKE {} ON TABLES FROM {}",
+                list, role
+            ));
+        }
+
+        for sql in &statements {
+            sqlx::query(sql).execute(&mut conn).await?;
+        }
+        conn.close().await?;
+
+        Ok(())
+    }
+
+    async fn drop_user(&self, username: &str) -> Result<()> {
+        if !self.user_exists(username).await? {
+            return Ok(());
+        }
+
+        // 角色在其他数据库中仍有权限或对象时无法删除，需要先逐库清理
+        let databases: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT d.datname FROM pg_database d
+            WHERE d.datallowconn
+              AND EXISTS (
+                  SELECT 1 FROM aclexplode(d.datacl) a
+                  JOIN pg_roles r ON r.oid = a.grantee
+                  WHERE r.rolname = $1
+              )
+            "#,
+        )
+        .bind(username)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let drop_owned_sql = format!("DROP OWNED BY {}", Self::quote_ident(username));
+        for database in databases {
+            let mut conn = self.connect_to(&database).await?;
+            sqlx::query(&drop_owned_sql).execute(&mut conn).await?;
+            conn.close().await?;
+        }
+        sqlx::query(&drop_owned_sql).execute(&self.pool).await?;
+
+        let drop_role_sql = format!("DROP ROLE IF EXISTS {}", Self::quote_ident(username));
+        sqlx::query(&drop_role_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn drop_database(&self, db_name: &str) -> Result<()> {
+        // 断开仍连接在该库上的会话，否则无法删除
+        sqlx::query(
+            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE datname = $1 AND pid <> pg_backend_pid()",
+        )
+        .bind(db_name)
+        .execute(&self.pool)
+        .await?;
+
+        let drop_db_sql = format!("DROP DATABASE IF EXISTS {}", Self::quote_ident(db_name));
+        sqlx::query(&drop_db_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn move_database(&self, from: &str, to: &str) -> Result<()> {
+        // 断开仍连接在该库上的会话，否则无法重命名
+        sqlx::query(
+            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity WHERE datname = $1 AND pid <> pg_backend_pid()",
+        )
+        .bind(from)
+        .execute(&self.pool)
+        .await?;
+
+        let rename_sql = format!(
+            "ALTER DATABASE {} RENAME TO {}",
+            Self::quote_ident(from),
+            Self::quote_ident(to)
+        );
+        sqlx::query(&rename_sql).execute(&self.pool).await?;
+
+        // 删除用户时会执行 DROP OWNED BY，先把用户创建的对象转给管理员，避免随用户一起被删除
+        let mut conn = self.connect_to(to).await?;
+        let owners: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT r.rolname FROM pg_class c
+            JOIN pg_roles r ON r.oid = c.relowner
+            WHERE c.relnamespace = 'public'::regnamespace AND r.rolname <> current_user
+            UNION
+            SELECT r.rolname FROM pg_proc p
+            JOIN pg_roles r ON r.oid = p.proowner
+            WHERE p.pronamespace = 'public'::regnamespace AND r.rolname <> current_user
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        for owner in owners {
+            let reassign_sql = format!(
+                "REASSIGN OWNED BY {} TO CURRENT_USER",
+                Self::quote_ident(&owner)
+            );
+            sqlx::query(&reassign_sql).execute(&mut conn).await?;
+        }
+        conn.close().await?;
+
+        // 移动后的数据库只允许管理员访问
+        let revoke_sql = format!(
+            "REVOKE ALL ON DATABASE {} FROM PUBLIC",
+            Self::quote_ident(to)
+        );
+        sqlx::query(&revoke_sql).execute(&self.pool).await?;
+        Ok(())
+    }
+
+    async fn clear_database(&self, db_name: &str) -> Result<u32> {
+        let mut conn = self.connect_to(db_name).await?;
+
+        // 依次删除视图、表、独立的序列、函数和自定义类型，CASCADE 处理对象间的依赖
+        let objects: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT format('DROP %s IF EXISTS %I CASCADE',
+                       CASE c.relkind WHEN 'v' THEN 'VIEW' WHEN 'm' THEN 'MATERIALIZED VIEW'
+                                      WHEN 'S' THEN 'SEQUENCE' ELSE 'TABLE' END,
+                       c.relname)
+            FROM pg_class c
+            WHERE c.relnamespace = 'public'::regnamespace
+              AND c.relkind IN ('v', 'm', 'r', 'p', 'f', 'S')
+              AND NOT EXISTS (
+                  SELECT 1 FROM pg_depend d
+                  WHERE d.objid = c.oid AND d.deptype IN ('a', 'i', 'e')
+              )
+              AND NOT c.relispartition
+            ORDER BY CASE c.relkind WHEN 'v' THEN 0 WHEN 'm' THEN 1 WHEN 'S' THEN 3 ELSE 2 END, c.relname
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        let routines: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT format('DROP ROUTINE IF EXISTS %s CASCADE', p.oid::regprocedure)
+            FROM pg_proc p
+            WHERE p.pronamespace = 'public'::regnamespace
+              AND NOT EXISTS (SELECT 1 FROM pg_depend d WHERE d.objid = p.oid AND d.deptype = 'e')
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+        let types: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT format('DROP %s IF EXISTS %I CASCADE',
+                       CASE t.typtype WHEN 'd' THEN 'DOMAIN' ELSE 'TYPE' END, t.typname)
+            FROM pg_type t
+            WHERE t.typnamespace = 'public'::regnamespace
+              AND t.typtype IN ('e', 'd', 'r')
+              AND NOT EXISTS (SELECT 1 FROM pg_depend d WHERE d.objid = t.oid AND d.deptype = 'e')
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?;
+
+        let statements: Vec<String> = objects.into_iter().chain(routines).chain(types).collect();
+        for sql in &statements {
+            if let Err(e) = conn.execute(sql.as_str()).await {
+                error!("清空数据库失败: {}, SQL: {}", e, sql);
+                let _ = conn.close().await;
+                return Err(e.into());
+            }
+        }
+        conn.close().await?;
+
+        info!("已清空数据库 {}: 删除 {} 个对象", db_name, statements.len());
+        Ok(statements.len() as u32)
+    }
+
+    async fn verify_password(&self, db_name: &str, username: &str, password: &str) -> Result<bool> {
+        let options = Self::connect_options(&self.config, db_name)
+            .username(username)
+            .password(password);
+
+        match PgConnection::connect_with(&options).await {
+            Ok(conn) => {
+                let _ = conn.close().await;
+                Ok(true)
+            }
+            // 28P01: invalid_password，28000: invalid_authorization_specification
+            Err(sqlx::Error::Database(e))
+                if matches!(e.code().as_deref(), Some("28P01") | Some("28000")) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn execute_script(&self, db_name: &str, statements: &[SqlStatement]) -> Result<()> {
+        let mut conn = self.connect_to(db_name).await?;
+
+        for statement in statements {
+            if let Err(e) = conn.execute(statement.sql.as_str()).await {
+                let _ = conn.close().await;
+                return Err(anyhow::anyhow!(
+                    "第 {} 行的语句执行失败: {}",
+                    statement.line,
+                    e
+                ));
+            }
+        }
+
+        let _ = conn.close().await;
+        Ok(())
+    }
+
+    async fn query_rows(
+        &self,
+        db_name: &str,
+        username: &str,
+        password: &str,
+        sql: &str,
+    ) -> Result<QueryRows> {
+        let options = Self::connect_options(&self.config, db_name)
+            .username(username)
+            .password(password);
+        Self::read_only_rows(&options, sql).await
+    }
+
+    async fn table_columns(&self, db_name: &str, table: &str) -> Result<Option<Vec<String>>> {
+        let mut conn = self.connect_to(db_name).await?;
+        let columns: Vec<String> = sqlx::query_scalar(
+            "SELECT column_name::text FROM information_schema.columns WHERE table_schema = 'public' AND table_name = $1 ORDER BY ordinal_position",
+        )
+        .bind(table)
+        .fetch_all(&mut conn)
+        .await?;
+        conn.close().await?;
+        Ok((!columns.is_empty()).then_some(columns))
+    }
+
+    async fn schema_snapshot(&self, db_name: &str, include_rows: bool) -> Result<SchemaSnapshot> {
+        let mut conn = self.connect_to(db_name).await?;
+
+        let columns = sqlx::query_as::<_, (String, String, String, bool)>(
+            r#"
+            SELECT c.relname::text, a.attname::text, format_type(a.atttypid, a.atttypmod), NOT a.attnotnull
+            FROM pg_attribute a
+            JOIN pg_class c ON c.oid = a.attrelid
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE n.nspname = 'public' AND c.relkind IN ('r', 'p')
+              AND a.attnum > 0 AND NOT a.attisdropped
+            ORDER BY c.relname, a.attnum
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?
+        .into_iter()
+        .map(|(table, name, data_type, nullable)| SchemaColumn {
+            table,
+            name,
+            data_type,
+            nullable,
+        })
+        .collect::<Vec<_>>();
+
+        // 表达式索引的列号为 0，以表达式占位
+        let indexes = sqlx::query_as::<_, (String, String, bool, bool, Vec<String>)>(
+            r#"
+            SELECT t.relname::text, i.relname::text, ix.indisprimary, ix.indisunique,
+                   ARRAY(
+                       SELECT COALESCE(a.attname::text, '(expression)')
+                       FROM unnest(ix.indkey::int2[]) WITH ORDINALITY AS k(attnum, ord)
+                       LEFT JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = k.attnum
+                       ORDER BY k.ord
+                   )
+            FROM pg_index ix
+            JOIN pg_class t ON t.oid = ix.indrelid
+            JOIN pg_class i ON i.oid = ix.indexrelid
+            JOIN pg_namespace n ON n.oid = t.relnamespace
+            WHERE n.nspname = 'public'
+            ORDER BY t.relname, i.relname
+            "#,
+        )
+        .fetch_all(&mut conn)
+        .await?
+        .into_iter()
+        .map(|(table, name, primary, unique, columns)| SchemaIndex {
+            table,
+            name,
+            primary,
+            unique,
+            columns,
+        })
+        .collect();
+        conn.close().await?;
+
+        let mut rows = Vec::new();
+        if include_rows {
+            let mut tables: Vec<&str> = columns.iter().map(|c| c.table.as_str()).collect();
+            tables.dedup();
+            for table in tables {
+                let sql = format!(
+                    "SELECT * FROM {} LIMIT {}",
+                    Self::quote_ident(table),
+                    SIMILARITY_ROWS_PER_TABLE
+                );
+                let options = Self::connect_options(&self.config, db_name);
+                rows.push((
+                    table.to_string(),
+                    Self::read_only_rows(&options, &sql).await?,
+                ));
+            }
+        }
+
+        Ok(SchemaSnapshot {
+            columns,
+            indexes,
+            rows,
+        })
+    }
+
+    async fn database_exists(&self, db_name: &str) -> Result<bool> {
+        let count =
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM pg_database WHERE datname = $1")
+                .bind(db_name)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(count > 0)
+    }
+
+    async fn user_exists(&self, username: &str) -> Result<bool> {
+        let count =
+            sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM pg_roles WHERE rolname = $1")
+                .bind(username)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(count > 0)
+    }
+
+    async fn database_size(&self, db_name: &str) -> Result<u64> {
+        let size = sqlx::query_scalar::<_, i64>("SELECT pg_database_size($1)")
+            .bind(db_name)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(size.max(0) as u64)
+    }
+
+    fn credentials(&self, db_name: &str, username: &str, password: &str) -> DatabaseCredentials {
+        let connection_string = format!(
+            "postgres://{}:{}@{}:{}/{}",
+            encode_uri_component(username),
+            encode_uri_component(password),
+            self.config.host,
+            self.config.port,
+            db_name
+        );
+
+        let jdbc_url = format!(
+            "jdbc:postgresql://{}:{}/{}?user={}&password={}",
+            self.config.host,
+            self.config.port,
+            db_name,
+            encode_uri_component(username),
+            encode_uri_component(password)
+        );
+
+        DatabaseCredentials {
+            db_engine: DatabaseEngine::Postgres,
+            db_host: self.config.host.clone(),
+            db_port: self.config.port,
+            db_name: db_name.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            connection_string,
+            jdbc_url,
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use super::dump::DumpSender;
+use super::grader::QueryRows;
+use super::import::SqlStatement;
+use super::similarity::SchemaSnapshot;
+use crate::models::{DatabaseCredentials, DatabaseEngine, ResourceLimits};
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// 授予普通用户的数据库权限（仅限数据操作）
+pub const STUDENT_PRIVILEGES: &[&str] = &[
+    "SELECT",
+    "INSERT",
+    "UPDATE",
+    "DELETE",
+    "INDEX",
+    "LOCK TABLES",
+];
+
+/// 明确撤销的全局危险权限，防止用户删除数据库或修改结构
+///
+/// 供应时在所有数据库范围（`*.*`）上撤销，权限配置档只在用户自己的数据库上授权，
+/// 因此不受此列表影响。
+pub const DANGEROUS_PRIVILEGES: &[&str] = &[
+    "CREATE",
+    "DROP",
+    "ALTER",
+    "REFERENCES",
+    "CREATE TEMPORARY TABLES",
+    "EXECUTE",
+    "CREATE VIEW",
+    "SHOW VIEW",
+    "CREATE ROUTINE",
+    "ALTER ROUTINE",
+    "EVENT",
+    "TRIGGER",
+];
+
+/// 超出存储配额时撤销的写入权限
+///
+/// 保留 DELETE 等权限，用户可以自行清理数据使用量回落到配额以内。
+pub const QUOTA_RESTRICTED_PRIVILEGES: &[&str] = &["INSERT", "UPDATE"];
+
+/// 权限配置档中允许使用的数据库级权限
+///
+/// 不包含 GRANT OPTION、SUPER、FILE 等会突破数据库隔离的权限。
+pub const DATABASE_PRIVILEGES: &[&str] = &[
+    "SELECT",
+    "INSERT",
+    "UPDATE",
+    "DELETE",
+    "INDEX",
+    "LOCK TABLES",
+    "CREATE",
+    "DROP",
+    "ALTER",
+    "REFERENCES",
+    "CREATE TEMPORARY TABLES",
+    "EXECUTE",
+    "CREATE VIEW",
+    "SHOW VIEW",
+    "CREATE ROUTINE",
+    "ALTER ROUTINE",
+    "EVENT",
+    "TRIGGER",
+];
+
+/// 数据库供应后端
+///
+/// 封装创建数据库、创建用户、授权、撤权、删除和存在性检查等底层操作。
+/// `DatabaseManager` 只负责编排申请流程和维护 SQLite 中的记录，
+/// 具体的 DDL 由各个后端实现，便于接入其他数据库引擎或在测试中替换。
+///
+/// 调用方负责在调用前校验数据库名和用户名的格式。
+#[async_trait]
+pub trait Provisioner: Send + Sync {
+    /// 后端对应的数据库引擎
+    fn engine(&self) -> DatabaseEngine;
+
+    /// 检查后端连接是否可用
+    async fn ping(&self) -> Result<()>;
+
+    /// 创建数据库（已存在时不报错）
+    async fn create_database(&self, db_name: &str) -> Result<()>;
+
+    /// 创建带资源限制的登录用户（已存在时不报错）
+    async fn create_user(
+        &self,
+        username: &str,
+        password: &str,
+        limits: &ResourceLimits,
+    ) -> Result<()>;
+
+    /// 修改用户的登录密码
+    async fn set_password(&self, username: &str, password: &str) -> Result<()>;
+
+    /// 锁定用户，锁定后无法登录，数据和权限保持不变
+    async fn lock_user(&self, username: &str) -> Result<()>;
+
+    /// 解锁用户
+    async fn unlock_user(&self, username: &str) -> Result<()>;
+
+    /// 断开用户当前所有的会话，返回断开的会话数
+    async fn kill_sessions(&self, username: &str) -> Result<u32>;
+
+    /// 修改用户的资源限制
+    async fn alter_resource_limits(&self, username: &str, limits: &ResourceLimits) -> Result<()>;
+
+    /// 授予用户或角色在指定数据库上的权限
+    async fn grant_privileges(
+        &self,
+        db_name: &str,
+        username: &str,
+        privileges: &[&str],
+    ) -> Result<()>;
+
+    /// 撤销用户或角色的权限，`db_name` 为 `None` 时作用于所有数据库
+    async fn revoke_privileges(
+        &self,
+        db_name: Option<&str>,
+        username: &str,
+        privileges: &[&str],
+    ) -> Result<()>;
+
+    /// 刷新权限缓存，不需要此步骤的后端可以保持默认实现
+    async fn flush_privileges(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// 删除用户（不存在时不报错）
+    async fn drop_user(&self, username: &str) -> Result<()>;
+
+    /// 删除数据库（不存在时不报错）
+    async fn drop_database(&self, db_name: &str) -> Result<()>;
+
+    /// 将数据库整体移动到新名称下并删除原数据库，用于归档、软删除和恢复
+    ///
+    /// 移动后的数据库归管理员所有，原用户的授权不会保留，需要时由调用方重新授权。
+    async fn move_database(&self, from: &str, to: &str) -> Result<()>;
+
+    /// 删除数据库中的所有表、视图和存储过程，保留数据库本身和用户的授权
+    ///
+    /// 返回删除的对象数。调用方需要先断开用户的会话，避免被元数据锁阻塞。
+    async fn clear_database(&self, db_name: &str) -> Result<u32>;
+
+    /// 检查数据库是否存在
+    async fn database_exists(&self, db_name: &str) -> Result<bool>;
+
+    /// 检查用户是否存在
+    async fn user_exists(&self, username: &str) -> Result<bool>;
+
+    /// 统计数据库占用的空间（字节）
+    async fn database_size(&self, db_name: &str) -> Result<u64>;
+
+    /// 检测后端是否支持角色，不支持时改为直接向用户授权
+    async fn supports_roles(&self) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// 创建角色（已存在时不报错）
+    async fn create_role(&self, _role: &str) -> Result<()> {
+        Err(anyhow::anyhow!("{} 后端不支持角色", self.engine()))
+    }
+
+    /// 将角色授予用户并设为默认角色
+    async fn grant_role(&self, _role: &str, _username: &str) -> Result<()> {
+        Err(anyhow::anyhow!("{} 后端不支持角色", self.engine()))
+    }
+
+    /// 删除角色（不存在时不报错）
+    async fn drop_role(&self, _role: &str) -> Result<()> {
+        Err(anyhow::anyhow!("{} 后端不支持角色", self.engine()))
+    }
+
+    /// 以用户自己的身份登录数据库，校验密码是否正确
+    ///
+    /// 密码错误时返回 `Ok(false)`，账号锁定、连接失败等其他错误原样返回。
+    async fn verify_password(&self, db_name: &str, username: &str, password: &str) -> Result<bool>;
+
+    /// 将数据库导出为 SQL，按顺序通过 `sender` 发送表结构和批量 INSERT 语句
+    async fn dump_database(&self, _db_name: &str, _sender: &DumpSender) -> Result<()> {
+        Err(anyhow::anyhow!("{} 后端暂不支持导出", self.engine()))
+    }
+
+    /// 以管理员连接在指定数据库中依次执行 SQL 语句，用于应用结构模板
+    ///
+    /// 任一语句失败即停止执行并返回错误，已执行的语句不会回滚。
+    async fn execute_script(&self, db_name: &str, statements: &[SqlStatement]) -> Result<()>;
+
+    /// 以指定用户的身份在只读事务中执行查询，用于作业评分
+    ///
+    /// 评分使用只有 SELECT 权限的临时用户，查询无法读取服务器文件或其他数据库。
+    /// 值按文本返回，结果超过 `GRADER_MAX_ROWS` 行或执行超过 `GRADER_QUERY_TIMEOUT_MS` 时返回错误。
+    async fn query_rows(
+        &self,
+        db_name: &str,
+        username: &str,
+        password: &str,
+        sql: &str,
+    ) -> Result<QueryRows>;
+
+    /// 获取表的字段名（按定义顺序），表不存在时返回 `None`
+    async fn table_columns(&self, db_name: &str, table: &str) -> Result<Option<Vec<String>>>;
+
+    /// 读取数据库的表、字段和索引，用于结构相似度检查
+    ///
+    /// `include_rows` 为 true 时还读取每张表最多 `SIMILARITY_ROWS_PER_TABLE` 行数据。
+    async fn schema_snapshot(&self, db_name: &str, include_rows: bool) -> Result<SchemaSnapshot>;
+
+    /// 以用户自己的身份依次执行 SQL 语句，权限配置档照常生效
+    ///
+    /// 单条语句失败不影响后续语句，返回已执行语句的结果（失败时为错误信息），
+    /// 失败数达到 `max_errors` 时停止执行。
+    async fn execute_as_user(
+        &self,
+        _db_name: &str,
+        _username: &str,
+        _password: &str,
+        _statements: &[SqlStatement],
+        _max_errors: usize,
+    ) -> Result<Vec<std::result::Result<(), String>>> {
+        Err(anyhow::anyhow!("{} 后端暂不支持导入", self.engine()))
+    }
+
+    /// 生成返回给用户的连接信息
+    fn credentials(&self, db_name: &str, username: &str, password: &str) -> DatabaseCredentials;
+}
@@ -0,0 +1,256 @@
+use super::grader::QueryRows;
+use crate::models::SimilarPair;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+
+/// 未指定时报告的最低相似度
+pub const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.6;
+
+/// 读取数据时每张表最多读取的行数
+pub const SIMILARITY_ROWS_PER_TABLE: usize = 1_000;
+
+/// 超过该比例的学生都具有的特征视为公共特征（结构模板、作业要求），不参与比较
+const COMMON_FEATURE_RATIO: f64 = 0.5;
+
+/// 相同特征少于该数量的两名学生不报告
+const MIN_SHARED_FEATURES: usize = 3;
+
+/// 每对学生最多列出的相同特征数
+const MAX_SHARED_EXAMPLES: usize = 10;
+
+/// 表中的一个字段
+#[derive(Debug, Clone)]
+pub struct SchemaColumn {
+    pub table: String,
+    pub name: String,
+    pub data_type: String,
+    pub nullable: bool,
+}
+
+/// 表上的一个索引，`columns` 按索引中的顺序排列
+#[derive(Debug, Clone)]
+pub struct SchemaIndex {
+    pub table: String,
+    pub name: String,
+    pub primary: bool,
+    pub unique: bool,
+    pub columns: Vec<String>,
+}
+
+/// 数据库的结构快照
+#[derive(Debug, Default)]
+pub struct SchemaSnapshot {
+    pub columns: Vec<SchemaColumn>,
+    pub indexes: Vec<SchemaIndex>,
+    /// 每张表的部分数据，只在要求读取数据时填充
+    pub rows: Vec<(String, QueryRows)>,
+}
+
+/// 将结构快照转换为特征集合
+///
+/// 名称统一为小写。主键索引不记录名称，因为 PostgreSQL 会按表名自动命名。
+pub fn fingerprint(snapshot: &SchemaSnapshot) -> BTreeSet<String> {
+    let mut features = BTreeSet::new();
+
+    for column in &snapshot.columns {
+        let table = column.table.to_lowercase();
+        let name = column.name.to_lowercase();
+        features.insert(format!("table:{}", table));
+        features.insert(format!("column:{}.{}", table, name));
+        features.insert(format!(
+            "column_type:{}.{}:{}:{}",
+            table,
+            name,
+            column.data_type.to_lowercase(),
+            if column.nullable { "null" } else { "not_null" }
+        ));
+    }
+
+    for index in &snapshot.indexes {
+        let table = index.table.to_lowercase();
+        let columns: Vec<String> = index.columns.iter().map(|c| c.to_lowercase()).collect();
+        let kind = if index.primary {
+            "primary"
+        } else if index.unique {
+            "unique"
+        } else {
+            "index"
+        };
+        features.insert(format!("index:{}:{}:{}", table, kind, columns.join(",")));
+        if !index.primary {
+            features.insert(format!(
+                "index_name:{}.{}",
+                table,
+                index.name.to_lowercase()
+            ));
+        }
+    }
+
+    for (table, rows) in &snapshot.rows {
+        let table = table.to_lowercase();
+        for row in rows {
+            let mut hasher = Sha256::new();
+            for value in row {
+                match value {
+                    Some(value) => {
+                        hasher.update(b"v");
+                        hasher.update(value.trim().as_bytes());
+                    }
+                    None => hasher.update(b"n"),
+                }
+                hasher.update([0x1f]);
+            }
+            let hash = format!("{:x}", hasher.finalize());
+            features.insert(format!("row:{}:{}", table, &hash[..16]));
+        }
+    }
+
+    features
+}
+
+/// 找出特征集合相似度不低于 `threshold` 的学生对，按相似度从高到低排序
+///
+/// 超过 `COMMON_FEATURE_RATIO` 的学生都具有的特征先被去掉，
+/// 相似度为剩余特征的 Jaccard 系数。返回结果中的 `class_info` 为空，由调用方填写。
+pub fn rank_pairs(students: &[(String, BTreeSet<String>)], threshold: f64) -> Vec<SimilarPair> {
+    let mut frequency: HashMap<&str, usize> = HashMap::new();
+    for (_, features) in students {
+        for feature in features {
+            *frequency.entry(feature.as_str()).or_default() += 1;
+        }
+    }
+
+    // 只有两名学生具有的特征正是要找的，无论班级多小都不算公共特征
+    let is_common = |feature: &str| {
+        let count = frequency[feature];
+        count > 2 && count as f64 > students.len() as f64 * COMMON_FEATURE_RATIO
+    };
+    let distinctive: Vec<BTreeSet<&str>> = students
+        .iter()
+        .map(|(_, features)| {
+            features
+                .iter()
+                .map(String::as_str)
+                .filter(|feature| !is_common(feature))
+                .collect()
+        })
+        .collect();
+
+    let mut pairs = Vec::new();
+    for i in 0..students.len() {
+        for j in (i + 1)..students.len() {
+            let shared: Vec<&str> = distinctive[i]
+                .intersection(&distinctive[j])
+                .copied()
+                .collect();
+            if shared.len() < MIN_SHARED_FEATURES {
+                continue;
+            }
+            let union = distinctive[i].union(&distinctive[j]).count();
+            let similarity = shared.len() as f64 / union as f64;
+            if similarity < threshold {
+                continue;
+            }
+
+            // 先列出出现次数最少的特征，它们最能说明问题
+            let mut examples = shared.clone();
+            examples.sort_by_key(|feature| (frequency[feature], *feature));
+            pairs.push(SimilarPair {
+                identity_key_a: students[i].0.clone(),
+                identity_key_b: students[j].0.clone(),
+                class_info: None,
+                similarity: (similarity * 1000.0).round() / 1000.0,
+                shared_features: shared.len() as u32,
+                total_features: union as u32,
+                shared_examples: examples
+                    .into_iter()
+                    .take(MAX_SHARED_EXAMPLES)
+                    .map(String::from)
+                    .collect(),
+            });
+        }
+    }
+
+    sort_pairs(&mut pairs);
+    pairs
+}
+
+/// 按相似度从高到低、相同特征数从多到少排序
+pub fn sort_pairs(pairs: &mut [SimilarPair]) {
+    pairs.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then(b.shared_features.cmp(&a.shared_features))
+            .then_with(|| a.identity_key_a.cmp(&b.identity_key_a))
+            .then_with(|| a.identity_key_b.cmp(&b.identity_key_b))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(table: &str, name: &str, data_type: &str) -> SchemaColumn {
+        SchemaColumn {
+            table: table.to_string(),
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable: true,
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_normalizes_names() {
+        let snapshot = SchemaSnapshot {
+            columns: vec![column("Instructor", "ID", "VARCHAR(5)")],
+            indexes: vec![
+                SchemaIndex {
+                    table: "instructor".to_string(),
+                    name: "instructor_pkey".to_string(),
+                    primary: true,
+                    unique: true,
+                    columns: vec!["id".to_string()],
+                },
+                SchemaIndex {
+                    table: "instructor".to_string(),
+                    name: "Idx_Salary".to_string(),
+                    primary: false,
+                    unique: false,
+                    columns: vec!["salary".to_string()],
+                },
+            ],
+            rows: vec![("instructor".to_string(), vec![vec![Some("1".to_string())]])],
+        };
+        let features = fingerprint(&snapshot);
+        assert!(features.contains("column_type:instructor.id:varchar(5):null"));
+        assert!(features.contains("index:instructor:primary:id"));
+        assert!(features.contains("index_name:instructor.idx_salary"));
+        assert!(!features.iter().any(|f| f.contains("pkey")));
+        assert_eq!(features.iter().filter(|f| f.starts_with("row:")).count(), 1);
+    }
+
+    #[test]
+    fn test_rank_pairs_ignores_common_features() {
+        let features = |extra: &[&str]| -> BTreeSet<String> {
+            ["table:student", "column:student.id", "column:student.name"]
+                .iter()
+                .chain(extra)
+                .map(|f| f.to_string())
+                .collect()
+        };
+        let students = vec![
+            ("a".to_string(), features(&["x1", "x2", "x3"])),
+            ("b".to_string(), features(&["x1", "x2", "x3", "b1"])),
+            ("c".to_string(), features(&["c1", "c2", "c3"])),
+            ("d".to_string(), features(&["d1"])),
+        ];
+
+        let pairs = rank_pairs(&students, 0.5);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].identity_key_a, "a");
+        assert_eq!(pairs[0].identity_key_b, "b");
+        assert_eq!(pairs[0].shared_features, 3);
+        assert_eq!(pairs[0].total_features, 4);
+        assert_eq!(pairs[0].similarity, 0.75);
+    }
+}
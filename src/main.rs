@@ -54,6 +54,10 @@ async fn main() -> std::io::Result<()> {
     // Create service
     let database_service = DatabaseService::new(db_manager);
 
+    // Start background storage quota monitor
+    database_service.start_storage_monitor();
+    database_service.start_lifecycle_monitor();
+
     // Setup OpenAPI
     let openapi = ApiDoc::openapi();
 
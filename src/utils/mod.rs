@@ -1,6 +1,8 @@
 use rand::Rng;
 use rand::seq::SliceRandom;
 
+pub mod client_config;
+
 pub fn generate_secure_password(length: usize) -> String {
     let mut rng = rand::thread_rng();
 
@@ -51,6 +53,31 @@ pub fn generate_secure_password(length: usize) -> String {
     password.into_iter().collect()
 }
 
+/// 生成一次性重置码，不包含容易混淆的字符（0/O、1/I/L）
+pub fn generate_reset_code(length: usize) -> String {
+    const CHARSET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+    let mut rng = rand::thread_rng();
+    (0..length)
+        .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
+/// 按 RFC 3986 对 URL 组成部分进行百分号编码，只保留非保留字符
+///
+/// 用于连接字符串中的用户名、密码和查询参数，密码中的 `@`、`#`、`&`、`%` 等字符不会破坏 URL 结构。
+pub fn encode_uri_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
 pub fn validate_identity_key(identity_key: &str) -> bool {
     // 检查身份标识是否为空或过长
     if identity_key.is_empty() || identity_key.len() > 50 {
@@ -85,6 +112,16 @@ mod tests {
         assert!(has_symbol);
     }
 
+    #[test]
+    fn test_encode_uri_component() {
+        assert_eq!(encode_uri_component("user_2023010101"), "user_2023010101");
+        assert_eq!(encode_uri_component("a@b#c&d%e"), "a%40b%23c%26d%25e");
+        assert_eq!(
+            encode_uri_component("!$^*:/? ~"),
+            "%21%24%5E%2A%3A%2F%3F%20~"
+        );
+    }
+
     #[test]
     fn test_validate_identity_key() {
         assert!(validate_identity_key("20250701"));